@@ -0,0 +1,85 @@
+//! Measures how long it takes to evaluate an expression for each file in a large synthetic tree.
+//!
+//! Run with `cargo bench --bench eval`, optionally followed by `-- <number of files>`. The tree is
+//! created in the temporary directory and removed afterwards. Each file is `stat`ed up front, so
+//! the times are just for evaluating the expression (plus reading contents, for the ones that do).
+
+use findfile::parse2::Parser;
+use findfile::play::{Env, PathInfo};
+use findfile::vm::{vm::Config, Vm};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const EXPRESSIONS: &[(&str, &str)] = &[
+	("metadata", "file? && size > 100"),
+	("path", r#"basename =~ $/_1[0-9]\./ || extname == "rs""#),
+	("arithmetic", "total = 0; for num in range(20) do total += num * 2; total > 100"),
+	(
+		"functions",
+		"fn big(amount) amount > 100; fn small(amount) !big(amount); small(size)",
+	),
+	("contents", r#""needle""#),
+];
+
+const ITERATIONS: usize = 5;
+
+fn main() {
+	let files = std::env::args()
+		.skip(1)
+		.find(|arg| !arg.starts_with('-'))
+		.map_or(10_000, |arg| arg.parse().expect("the number of files should be a number"));
+
+	let root = std::env::temp_dir().join(format!("findfile-bench-{}", std::process::id()));
+	let paths = build_tree(&root, files).expect("couldn't create the tree");
+	let infos = paths.iter().map(PathInfo::new).collect::<Result<Vec<_>, _>>().unwrap();
+
+	println!("{} files in {}", infos.len(), root.display());
+	for (name, source) in EXPRESSIONS {
+		let per_file = bench(source, &root, &infos) / infos.len() as u32;
+		println!("{name:>12}: {:>8.2?} per file  ({source})", per_file);
+	}
+
+	std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// Creates `files` files within `root`, spread across two levels of directories. Every tenth file
+/// contains `needle`.
+fn build_tree(root: &Path, files: usize) -> std::io::Result<Vec<PathBuf>> {
+	let mut paths = Vec::with_capacity(files);
+
+	for idx in 0..files {
+		let dir = root.join(format!("dir_{}", idx / 1000)).join(format!("sub_{}", idx / 50 % 20));
+		std::fs::create_dir_all(&dir)?;
+
+		let extension = ["rs", "txt", "md", "toml"][idx % 4];
+		let path = dir.join(format!("file_{idx}.{extension}"));
+		let mut contents = format!("file number {idx}\n").repeat(idx % 16);
+		if idx % 10 == 0 {
+			contents.push_str("the needle is here\n");
+		}
+		std::fs::write(&path, contents)?;
+		paths.push(path);
+	}
+
+	Ok(paths)
+}
+
+/// Runs `source` over every one of `infos` a few times, returning the fastest time.
+fn bench(source: &str, root: &Path, infos: &[PathInfo]) -> Duration {
+	let env = Env::default();
+	let expr = Parser::new(source, &env).parse_toplevel().expect("the expression should parse");
+	let (mut vm, block) = Vm::compile(Config::default(), expr).expect("it should compile");
+	vm.set_root(root.into());
+
+	(0..ITERATIONS)
+		.map(|_| {
+			let start = Instant::now();
+			for info in infos {
+				vm.set_pathinfo(info.clone());
+				std::hint::black_box(block.run(&mut vm).expect("it should run"));
+			}
+			start.elapsed()
+		})
+		.min()
+		.unwrap()
+}
@@ -1,5 +1,9 @@
-use crate::ast::{Block, Expression, Precedence};
-use crate::parse::{LexContext, ParseError, Token};
+#[cfg(test)]
+use crate::ast::Precedence;
+use crate::ast::{Block, Expression};
+use crate::parse::ParseError;
+#[cfg(test)]
+use crate::parse::{LexContext, Token};
 
 use crate::vm::{block::BuildContext, Builder, Opcode};
 use crate::Regex;
@@ -26,67 +30,80 @@ pub enum Atom {
 	Regex(Regex),
 	Variable(String),
 
-	FnCall(Box<Self>, Vec<Expression>), // note that only variables and blocks are the first arg.
+	Array(Vec<Expression>),
+	AssocArray(Vec<(Expression, Expression)>),
+
+	FnCall(Box<Self>, Vec<Expression>),
+	Index(Box<Self>, Box<Expression>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct RegexFlags;
+pub struct RegexFlags(pub Vec<u8>);
 
-impl Atom {
-	fn parse_path(lctx: &mut LexContext) -> Result<Self, ParseError> {
-		let (mut interpolated, _) = Interpolated::parse_until(lctx, Token::EndPath)?;
+// todo: fix this horrible hack
+/// Paths starting with `+` are relative to the root: `+foo` is `./**/foo`.
+fn expand_leading_plus(path: &mut Vec<u8>) {
+	if path.first() == Some(&b'+') {
+		path.splice(0..1, b"./**/*".iter().copied());
+	}
+}
 
+impl Atom {
+	/// Creates a path from its parts, which is a glob unless it's interpolated.
+	pub(crate) fn path(mut interpolated: Interpolated) -> Result<Self, ParseError> {
 		if interpolated.parts.is_empty() {
-			// todo: fix this horrible hack
-			if interpolated.tail.get(0) == Some(&b'+') {
-				interpolated.tail[0] = b'*';
-				interpolated.tail.insert(0, b'/');
-				interpolated.tail.insert(0, b'*');
-				interpolated.tail.insert(0, b'*');
-				interpolated.tail.insert(0, b'/');
-				interpolated.tail.insert(0, b'.');
-			}
+			expand_leading_plus(&mut interpolated.tail);
 			PathGlob::parse(std::path::Path::new(&OsStr::assert_from_raw_bytes(&interpolated.tail)))
 				.map(Value::PathGlob)
 				.map(Self::Value)
 				.map_err(ParseError::BadPath)
 		} else {
+			expand_leading_plus(&mut interpolated.parts[0].0);
 			Ok(Self::InterpolatedPath(interpolated))
 		}
 	}
 
-	fn parse_string(lctx: &mut LexContext) -> Result<Self, ParseError> {
-		let (interpolated, _) = Interpolated::parse_until(lctx, Token::EndString)?;
+	pub(crate) fn string(interpolated: Interpolated) -> Self {
+		if interpolated.parts.is_empty() {
+			Self::Value(Value::Text(interpolated.tail.into()))
+		} else {
+			Self::InterpolatedString(interpolated)
+		}
+	}
 
+	/// Creates a regex from its parts, which is compiled right away unless it's interpolated.
+	pub(crate) fn regex(interpolated: Interpolated, flags: Vec<u8>) -> Result<Self, ParseError> {
 		if interpolated.parts.is_empty() {
-			Ok(Self::Value(Value::Text(interpolated.tail.into())))
+			Ok(Self::Regex(Regex::new(&interpolated.tail, &flags)?))
 		} else {
-			Ok(Self::InterpolatedString(interpolated))
+			Ok(Self::InterpolatedRegex(interpolated, RegexFlags(flags)))
 		}
 	}
+}
+
+// Parsing with the original front end, which `parse2`'s parity tests are checked against.
+#[cfg(test)]
+impl Atom {
+	fn parse_path(lctx: &mut LexContext) -> Result<Self, ParseError> {
+		let (interpolated, _) = Interpolated::parse_until(lctx, Token::EndPath)?;
+		Self::path(interpolated)
+	}
+
+	fn parse_string(lctx: &mut LexContext) -> Result<Self, ParseError> {
+		let (interpolated, _) = Interpolated::parse_until(lctx, Token::EndString)?;
+		Ok(Self::string(interpolated))
+	}
 
 	fn parse_regex(lctx: &mut LexContext) -> Result<Self, ParseError> {
 		let (interpolated, flags) =
 			Interpolated::parse_until(lctx, |r: &Token| matches!(*r, Token::EndRegex(_)))?;
 
 		let Token::EndRegex(flags) = flags else { unreachable!(); };
-
-		if interpolated.parts.is_empty() {
-			return Ok(Self::Regex(Regex::new(&interpolated.tail, &flags)?));
-		} else {
-			todo!();
-		}
-		// if interpolated.parts.is_empty() {
-		// 	Ok(Self::String(OsString::assert_from_raw_vec(interpolated.tail)))
-		// } else {
-		// 	Ok(Self::InterpolatedString(interpolated))
-		// }
+		Self::regex(interpolated, flags)
 	}
 
-	fn parse_fncall_if_given(self, lctx: &mut LexContext) -> Result<Self, ParseError> {
-		if !lctx.take_if(Token::LeftParen)? {
-			return Ok(self);
-		}
+	/// Parses the arguments to a function, up to and including the closing `)`.
+	fn parse_fncall_args(lctx: &mut LexContext) -> Result<Vec<Expression>, ParseError> {
 		let mut args = Vec::new();
 
 		while let Some(expr) = Expression::parse(lctx, false, Precedence::default())? {
@@ -100,7 +117,70 @@ impl Atom {
 			return Err(ParseError::NoClosingRightParen);
 		}
 
-		Ok(Self::FnCall(Box::new(self), args))
+		Ok(args)
+	}
+
+	/// Parses any function calls (`foo(1)`) and indexing (`foo[1]`) after `self`.
+	fn parse_postfix(mut self, lctx: &mut LexContext) -> Result<Self, ParseError> {
+		loop {
+			if lctx.take_if(Token::LeftParen)? {
+				self = Self::FnCall(Box::new(self), Self::parse_fncall_args(lctx)?);
+			} else if lctx.take_if(Token::LeftBracket)? {
+				let key = Expression::parse(lctx, false, Precedence::default())?
+					.ok_or(ParseError::Message("missing index"))?;
+
+				if !lctx.take_if(Token::RightBracket)? {
+					return Err(ParseError::Message("expected `]` after index"));
+				}
+
+				self = Self::Index(Box::new(self), Box::new(key));
+			} else {
+				return Ok(self);
+			}
+		}
+	}
+
+	/// Parses `[a, b, ...]`, after the `[`.
+	fn parse_array(lctx: &mut LexContext) -> Result<Self, ParseError> {
+		let mut elements = Vec::new();
+
+		while let Some(expr) = Expression::parse(lctx, false, Precedence::default())? {
+			elements.push(expr);
+			if !lctx.take_if(Token::Comma)? {
+				break;
+			}
+		}
+
+		if !lctx.take_if(Token::RightBracket)? {
+			return Err(ParseError::Message("expected `]` after array elements"));
+		}
+
+		Ok(Self::Array(elements))
+	}
+
+	/// Parses `{key: value, ...}`, after the `{`.
+	fn parse_assoc_array(lctx: &mut LexContext) -> Result<Self, ParseError> {
+		let mut pairs = Vec::new();
+
+		while let Some(key) = Expression::parse(lctx, false, Precedence::default())? {
+			if !lctx.take_if(Token::Colon)? {
+				return Err(ParseError::Message("expected `:` after key"));
+			}
+
+			let value = Expression::parse(lctx, false, Precedence::default())?
+				.ok_or(ParseError::Message("missing value for key"))?;
+			pairs.push((key, value));
+
+			if !lctx.take_if(Token::Comma)? {
+				break;
+			}
+		}
+
+		if !lctx.take_if(Token::EndBraceEscape)? {
+			return Err(ParseError::Message("expected `}` after assoc array elements"));
+		}
+
+		Ok(Self::AssocArray(pairs))
 	}
 
 	pub fn parse(lctx: &mut LexContext) -> Result<Option<Self>, ParseError> {
@@ -126,15 +206,16 @@ impl Atom {
 			)))),
 
 			Some(Token::LeftParen) => Ok(Some(
-				Self::Block(Block::parse_until(lctx, Token::RightParen)?)
-					.parse_fncall_if_given(lctx)?,
+				Self::Block(Block::parse_until(lctx, Token::RightParen)?).parse_postfix(lctx)?,
 			)),
-			Some(Token::Variable(var)) => Ok(Some(Self::Variable(var).parse_fncall_if_given(lctx)?)),
+			Some(Token::LeftBracket) => Ok(Some(Self::parse_array(lctx)?.parse_postfix(lctx)?)),
+			Some(Token::LeftBrace) => Ok(Some(Self::parse_assoc_array(lctx)?.parse_postfix(lctx)?)),
+			Some(Token::Variable(var)) => Ok(Some(Self::Variable(var).parse_postfix(lctx)?)),
 			Some(Token::Number(num)) => Ok(Some(Self::Value(Value::Number(num)))),
 			Some(Token::FileSize { fs, precision }) => {
 				Ok(Some(Self::Value(Value::FileSize { fs, precision })))
 			}
-			Some(Token::DateTime(_dt)) => todo!(), //Ok(Some(Self::Value(todo!() /*Value::DateTime(dt)*/))),
+			Some(Token::DateTime(dt)) => Ok(Some(Self::Value(Value::DateTime(dt)))),
 			Some(Token::CliArg(pos)) => {
 				let cli = lctx.get_cli(pos).ok_or(ParseError::InvalidCliPosition(pos))?;
 				Ok(Some(Self::Value(Value::Text(cli.to_raw_bytes().into_owned().into()))))
@@ -170,24 +251,33 @@ impl Atom {
 				atom.compile(builder, BuildContext::Normal)?;
 				builder.opcode(Opcode::UPositive);
 			}
-			Self::Block(_block) => todo!(),
+			Self::Block(block) => block.compile(builder, ctx)?,
 			Self::ForcedLogical(atom) => {
 				atom.compile(builder, BuildContext::Logical)?;
 				builder.opcode(Opcode::ForcedLogical);
 			}
 
 			Self::InterpolatedPath(interpolated) => {
-				let amount = interpolated.compile(builder);
+				let amount = interpolated.compile(builder)?;
 				builder.opcode(Opcode::CreatePath(amount));
+				if ctx != BuildContext::Normal {
+					builder.opcode(Opcode::ForcedLogical);
+				}
 			}
 			Self::InterpolatedString(interpolated) => {
-				let amount = interpolated.compile(builder);
+				let amount = interpolated.compile(builder)?;
 				builder.opcode(Opcode::CreateString(amount));
+				if ctx != BuildContext::Normal {
+					builder.opcode(Opcode::ForcedLogical);
+				}
 			}
-			Self::InterpolatedRegex(interpolated, _flags) => {
-				let amount = interpolated.compile(builder);
+			Self::InterpolatedRegex(interpolated, RegexFlags(flags)) => {
+				builder.load_constant(Value::Text(flags.into())); // the flags are below the parts
+				let amount = interpolated.compile(builder)?;
 				builder.opcode(Opcode::CreateRegex(amount));
-				panic!("todo: flags");
+				if ctx != BuildContext::Normal {
+					builder.opcode(Opcode::ForcedLogical);
+				}
 			}
 
 			Self::Regex(regex) => {
@@ -217,22 +307,44 @@ impl Atom {
 				}
 			}
 
-			Self::FnCall(func, args) => {
-				if !matches!(*func, Self::Variable(_)) {
-					func.clone().compile(builder, BuildContext::Normal)?;
+			Self::Array(elements) => {
+				let amount = elements.len();
+				for element in elements {
+					element.compile(builder, BuildContext::Normal)?;
 				}
+				builder.opcode(Opcode::CreateArray(amount));
+			}
 
+			Self::AssocArray(pairs) => {
+				let amount = pairs.len();
+				for (key, value) in pairs {
+					key.compile(builder, BuildContext::Normal)?;
+					value.compile(builder, BuildContext::Normal)?;
+				}
+				builder.opcode(Opcode::CreateAssocArray(amount));
+			}
+
+			Self::Index(target, key) => {
+				target.compile(builder, BuildContext::Normal)?;
+				key.compile(builder, BuildContext::Normal)?;
+				builder.opcode(Opcode::Index);
+			}
+
+			Self::FnCall(func, args) => {
 				let arglen = args.len();
 				for arg in args {
 					arg.compile(builder, BuildContext::Normal)?;
 				}
 
+				// The function itself is always on top of the stack, above its arguments.
 				if let Self::Variable(name) = &*func {
-					if Opcode::compile_fn_call(&name, arglen, builder) {
+					if Opcode::compile_fn_call(name, arglen, builder) {
 						return Ok(());
 					}
 
-					builder.load_variable(&name);
+					builder.load_variable(name);
+				} else {
+					func.compile(builder, BuildContext::Normal)?;
 				}
 
 				builder.opcode(Opcode::GenericCall(arglen));
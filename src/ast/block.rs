@@ -1,9 +1,23 @@
-use crate::ast::{Expression, Precedence};
-use crate::parse::{LexContext, ParseError, Token};
+use crate::ast::Expression;
+#[cfg(test)]
+use crate::ast::Precedence;
+use crate::parse::ParseError;
+#[cfg(test)]
+use crate::parse::{LexContext, Token};
+use crate::vm::{block::BuildContext, Builder, Opcode};
+use crate::Value;
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Block(Vec<Expression>);
+pub struct Block(pub(super) Vec<Expression>);
 
+impl Block {
+	pub fn new(exprs: Vec<Expression>) -> Self {
+		Self(exprs)
+	}
+}
+
+// Parsing with the original front end, which `parse2`'s parity tests are checked against.
+#[cfg(test)]
 impl Block {
 	pub fn parse_until(lctx: &mut LexContext, end: Token) -> Result<Self, ParseError> {
 		let mut args = Vec::new();
@@ -22,3 +36,21 @@ impl Block {
 		Ok(Self(args))
 	}
 }
+
+impl Block {
+	/// Compiles each expression in turn; only the last one's value is kept.
+	pub fn compile(self, builder: &mut Builder, ctx: BuildContext) -> Result<(), ParseError> {
+		let mut exprs = self.0;
+		let Some(last) = exprs.pop() else {
+			builder.load_constant(Value::default());
+			return Ok(());
+		};
+
+		for expr in exprs {
+			expr.compile(builder, BuildContext::Normal)?;
+			builder.opcode(Opcode::Pop);
+		}
+
+		last.compile(builder, ctx)
+	}
+}
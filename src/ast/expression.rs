@@ -1,9 +1,13 @@
-use crate::ast::{Atom, LogicOperator, MathOperator, Precedence};
-use crate::parse::{LexContext, ParseError, Token};
+use crate::ast::{Atom, LogicOperator, MathOperator};
+#[cfg(test)]
+use crate::ast::{Precedence, Script};
+use crate::parse::ParseError;
+#[cfg(test)]
+use crate::parse::{LexContext, Token};
 use crate::vm::block::{BuildContext, Builder};
 use crate::vm::Opcode;
 use crate::Value;
-use std::path::PathBuf;
+use std::ops::Range;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShortCircuit {
@@ -12,27 +16,64 @@ pub enum ShortCircuit {
 	Defined,
 }
 
+/// An expression, along with the bytes of the source it was parsed from.
+///
+/// Spans aren't compared, so the same tree parsed from different sources is equal.
+#[derive(Debug, Clone)]
+pub struct Expression {
+	pub kind: ExprKind,
+	pub span: Range<usize>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expression {
+pub enum ExprKind {
 	Atom(Atom),
-	Math(MathOperator, Box<Self>, Box<Self>),
-	Logic(LogicOperator, Box<Self>, Box<Self>),
-	Assignment(String, Option<MathOperator>, Box<Self>),
-	ShortCircuitAssignment(String, ShortCircuit, Box<Self>),
-	ShortCircuit(ShortCircuit, Box<Self>, Box<Self>),
-
-	If(Vec<(Self, Self)>, Option<Box<Self>>),
-	While(Box<Self>, Box<Self>),
+	Math(MathOperator, Box<Expression>, Box<Expression>),
+	Logic(LogicOperator, Box<Expression>, Box<Expression>),
+	Assignment(String, Option<MathOperator>, Box<Expression>),
+	// `a[b] = c`
+	IndexAssignment(Box<Atom>, Box<Expression>, Option<MathOperator>, Box<Expression>),
+	ShortCircuitAssignment(String, ShortCircuit, Box<Expression>),
+	ShortCircuit(ShortCircuit, Box<Expression>, Box<Expression>),
+
+	If(Vec<(Expression, Expression)>, Option<Box<Expression>>),
+	While(Box<Expression>, Box<Expression>),
+	For(String, Box<Expression>, Box<Expression>),
 	Break,
 	Continue,
-	Return(Option<Box<Self>>),
-	FnDecl(Option<String>, Vec<String>, Box<Self>),
+	Return(Option<Box<Expression>>),
+	FnDecl(Option<String>, Vec<String>, Box<Expression>),
+}
+
+impl Expression {
+	pub fn new(kind: ExprKind, span: Range<usize>) -> Self {
+		Self { kind, span }
+	}
+}
+
+impl PartialEq for Expression {
+	fn eq(&self, other: &Self) -> bool {
+		self.kind == other.kind
+	}
 }
 
+// The original front end doesn't keep track of where tokens are, so its spans are empty.
+#[cfg(test)]
+impl From<ExprKind> for Expression {
+	fn from(kind: ExprKind) -> Self {
+		Self::new(kind, 0..0)
+	}
+}
+
+// Parsing with the original front end, which `parse2`'s parity tests are checked against.
+#[cfg(test)]
 impl Expression {
+	/// Parses an entire program which can't have `^{ ... }` or `${ ... }` blocks.
 	pub fn parse_toplevel(lctx: &mut LexContext) -> Result<Self, ParseError> {
-		Ok(Self::parse(lctx, true, Precedence::default())?
-			.expect("Todo: error for no valid expression"))
+		match Script::parse(lctx)? {
+			Script { begin, main: Some(main), end } if begin.is_empty() && end.is_empty() => Ok(main),
+			_ => Err(ParseError::Message("begin and end blocks aren't allowed here")),
+		}
 	}
 
 	fn parse_single(lctx: &mut LexContext, msg: &'static str) -> Result<Self, ParseError> {
@@ -40,10 +81,17 @@ impl Expression {
 	}
 
 	fn parse_statement(lctx: &mut LexContext) -> Result<Option<Self>, ParseError> {
-		const STATEMENT_BEGIN: [Token; 6] =
-			[Token::If, Token::While, Token::Continue, Token::Break, Token::Return, Token::Fn];
-
-		let Some(token) = lctx.take_if_fn(|x| STATEMENT_BEGIN.contains(&x))? else {
+		const STATEMENT_BEGIN: [Token; 7] = [
+			Token::If,
+			Token::While,
+			Token::For,
+			Token::Continue,
+			Token::Break,
+			Token::Return,
+			Token::Fn,
+		];
+
+		let Some(token) = lctx.take_if_fn(|x| STATEMENT_BEGIN.contains(x))? else {
 			return Ok(None);
 		};
 
@@ -72,7 +120,7 @@ impl Expression {
 					None
 				};
 
-				Ok(Some(Self::If(conds, else_body.map(From::from))))
+				Ok(Some(ExprKind::If(conds, else_body.map(From::from)).into()))
 			}
 
 			Token::While => {
@@ -83,14 +131,31 @@ impl Expression {
 
 				let body = Self::parse_single(lctx, "missing body for while")?;
 
-				Ok(Some(Self::While(cond.into(), body.into())))
+				Ok(Some(ExprKind::While(cond.into(), body.into()).into()))
 			}
 
-			Token::Continue => Ok(Some(Self::Continue)),
-			Token::Break => Ok(Some(Self::Break)),
-			Token::Return => Ok(Some(Self::Return(
-				Self::parse(lctx, true, Precedence::default())?.map(From::from),
-			))),
+			Token::For => {
+				let var = lctx.take_ident()?.ok_or(ParseError::Message("expected variable name"))?;
+				if !lctx.take_if(Token::In)? {
+					return Err(ParseError::Message("expected `in` after `for` variable"));
+				}
+
+				let iterable = Self::parse_single(lctx, "missing iterable for for")?;
+				if !lctx.take_if(Token::Do)? {
+					return Err(ParseError::Message("expected `do` after `for` iterable"));
+				}
+
+				let body = Self::parse_single(lctx, "missing body for for")?;
+
+				Ok(Some(ExprKind::For(var, iterable.into(), body.into()).into()))
+			}
+
+			Token::Continue => Ok(Some(ExprKind::Continue.into())),
+			Token::Break => Ok(Some(ExprKind::Break.into())),
+			Token::Return => Ok(Some(
+				ExprKind::Return(Self::parse(lctx, true, Precedence::default())?.map(From::from))
+					.into(),
+			)),
 
 			Token::Fn => {
 				let name = lctx.take_ident()?;
@@ -114,7 +179,7 @@ impl Expression {
 
 				let body = Self::parse_single(lctx, "missing body for fn")?;
 
-				Ok(Some(Self::FnDecl(name, args, body.into())))
+				Ok(Some(ExprKind::FnDecl(name, args, body.into()).into()))
 			}
 			_ => unreachable!(),
 		}
@@ -133,7 +198,7 @@ impl Expression {
 			return Ok(None);
 		};
 
-		let mut lhs = Self::Atom(begin);
+		let mut lhs = Self::from(ExprKind::Atom(begin));
 
 		while let Some(token) = lctx.next()? {
 			let token_prec = match Precedence::of(&token, comma_is_and) {
@@ -148,38 +213,30 @@ impl Expression {
 				Self::parse(lctx, comma_is_and, token_prec)?.ok_or(ParseError::MissingRhsToOp)?;
 
 			if token == Token::Assign {
-				let Self::Atom(Atom::Variable(var)) = lhs else {
-					return Err(ParseError::AssignToNonVariable);
-				};
-
-				lhs = Self::Assignment(var, None, rhs.into());
+				lhs = Self::assignment(lhs, None, rhs)?;
 				continue;
 			};
 
 			if let Some((math, assign)) = MathOperator::from_token(&token) {
 				if assign {
-					let Self::Atom(Atom::Variable(var)) = lhs else {
-						return Err(ParseError::AssignToNonVariable);
-					};
-
-					lhs = Self::Assignment(var, Some(math), rhs.into());
+					lhs = Self::assignment(lhs, Some(math), rhs)?;
 				} else {
-					lhs = Self::Math(math, lhs.into(), rhs.into());
+					lhs = ExprKind::Math(math, lhs.into(), rhs.into()).into();
 				}
 				continue;
 			}
 
 			if let Some(logic) = LogicOperator::from_token(&token) {
-				lhs = Self::Logic(logic, lhs.into(), rhs.into());
+				lhs = ExprKind::Logic(logic, lhs.into(), rhs.into()).into();
 				continue;
 			}
 
 			if token == Token::And || token == Token::Comma && comma_is_and {
-				lhs = Self::ShortCircuit(ShortCircuit::And, lhs.into(), rhs.into());
+				lhs = ExprKind::ShortCircuit(ShortCircuit::And, lhs.into(), rhs.into()).into();
 				continue;
 			}
 			if token == Token::Or {
-				lhs = Self::ShortCircuit(ShortCircuit::Or, lhs.into(), rhs.into());
+				lhs = ExprKind::ShortCircuit(ShortCircuit::Or, lhs.into(), rhs.into()).into();
 				continue;
 			}
 
@@ -189,90 +246,46 @@ impl Expression {
 
 		Ok(Some(lhs))
 	}
+}
 
-	// im not really confident in this algorithm; in the future i'll make it more robust.
-	pub fn begin_position(&self) -> Vec<PathBuf> {
-		match self {
-			Self::Atom(Atom::Value(Value::Path(path))) => vec![path.to_path_buf()],
-			Self::Atom(Atom::Value(Value::PathGlob(pathglob))) => vec![pathglob.begin_position()],
-			Self::ShortCircuit(ShortCircuit::And, lhs, rhs) => {
-				let mut beginnings = lhs.begin_position();
-
-				'out: for new in rhs.begin_position() {
-					for idx in 0..beginnings.len() {
-						// `a/b && a/b` -> `a/b`
-						if beginnings[idx] == new {
-							continue 'out;
-						}
-
-						// `a/b && a/` -> keep `a/b`
-						if beginnings[idx].starts_with(&new) {
-							continue 'out;
-						}
-
-						// `a/ && a/b` -> replace with `a/b`
-						if new.starts_with(&beginnings[idx]) {
-							beginnings[idx] = new;
-							continue 'out;
-						}
-
-						// `a/b/c && a/b/d` -> replace with `a/b`
-						for ancestor in beginnings[idx].ancestors() {
-							if new.starts_with(&ancestor) {
-								beginnings[idx] = ancestor.into();
-								continue 'out;
-							}
-						}
-
-						panic!("when does this happen?: {new:?} {old:?}", old = beginnings[idx]);
-					}
-				}
-				beginnings
+impl Expression {
+	/// Creates an assignment to `lhs`, which must be either a variable or an index. It spans
+	/// from the start of `lhs` to the end of `rhs`.
+	pub(crate) fn assignment(
+		lhs: Self,
+		math: Option<MathOperator>,
+		rhs: Self,
+	) -> Result<Self, ParseError> {
+		let span = lhs.span.start..rhs.span.end;
+		let kind = match lhs.kind {
+			ExprKind::Atom(Atom::Variable(var)) => ExprKind::Assignment(var, math, rhs.into()),
+			ExprKind::Atom(Atom::Index(target, key)) => {
+				ExprKind::IndexAssignment(target, key, math, rhs.into())
 			}
+			_ => return Err(ParseError::AssignToNonVariable),
+		};
 
-			Self::ShortCircuit(ShortCircuit::Or, lhs, rhs) => {
-				let mut beginnings = lhs.begin_position();
-
-				'out: for new in rhs.begin_position() {
-					for current in beginnings.iter_mut() {
-						// we already have it, nothing to do
-						if current.starts_with(&new) {
-							continue 'out;
-						}
-
-						// it's more specific, let's add it in
-						if new.starts_with(&current) {
-							*current = new;
-							continue 'out;
-						}
-					}
-					// If nothing starts with it, let's add it to the list.
-					beginnings.push(new)
-				}
-				beginnings
-			}
-			_ => vec![],
-		}
+		Ok(Self::new(kind, span))
 	}
 }
 
 impl Expression {
 	pub fn compile(self, builder: &mut Builder, ctx: BuildContext) -> Result<(), ParseError> {
-		match self {
-			Self::Atom(atom) => atom.compile(builder, ctx),
-			Self::Math(mop, lhs, rhs) => {
+		match self.kind {
+			ExprKind::Atom(atom) => atom.compile(builder, ctx),
+			ExprKind::Math(mop, lhs, rhs) => {
 				lhs.compile(builder, BuildContext::Normal)?;
 				rhs.compile(builder, BuildContext::Normal)?;
 				mop.compile(builder);
 				Ok(())
 			}
-			Self::Logic(lop, lhs, rhs) => {
+			ExprKind::Logic(lop, lhs, rhs) => {
 				lhs.compile(builder, BuildContext::Normal)?;
 				rhs.compile(builder, BuildContext::Normal)?;
 				lop.compile(builder);
 				Ok(())
 			}
-			Self::Assignment(name, None, value) => {
+			ExprKind::Assignment(name, None, value) => {
 				// `foo = "a"` should not use `a` as logical at toplevel.
 				value.compile(
 					builder,
@@ -282,7 +295,7 @@ impl Expression {
 				builder.store_variable(&name);
 				Ok(())
 			}
-			Self::Assignment(name, Some(mop), value) => {
+			ExprKind::Assignment(name, Some(mop), value) => {
 				builder.load_variable(&name);
 				value.compile(builder, BuildContext::Normal)?; // All math assignments are normal context.
 				mop.compile(builder);
@@ -290,9 +303,25 @@ impl Expression {
 				builder.store_variable(&name);
 				Ok(())
 			}
-			Self::ShortCircuitAssignment(name, cond, value) => {
+			ExprKind::IndexAssignment(target, key, mop, value) => {
+				target.compile(builder, BuildContext::Normal)?;
+				key.compile(builder, BuildContext::Normal)?;
+				if let Some(mop) = mop {
+					builder.opcode(Opcode::Dup2);
+					builder.opcode(Opcode::Index);
+					value.compile(builder, BuildContext::Normal)?;
+					mop.compile(builder);
+				} else {
+					value.compile(builder, BuildContext::Normal)?;
+				}
+				builder.opcode(Opcode::SetIndex); // it returns the value
+				Ok(())
+			}
+			ExprKind::ShortCircuitAssignment(name, cond, value) => {
 				builder.load_variable(&name);
+				builder.opcode(Opcode::Dup); // the jump pops the condition
 				let end_jump = builder.defer_jump();
+				builder.opcode(Opcode::Pop);
 				value.compile(builder, BuildContext::Normal)?;
 				// note: this is different from short circuit itself!! this allows `a //= "b"`.
 				builder.opcode(Opcode::Dup); // it returns a value
@@ -305,7 +334,7 @@ impl Expression {
 				Ok(())
 			}
 
-			Self::ShortCircuit(cond, lhs, rhs) => {
+			ExprKind::ShortCircuit(cond, lhs, rhs) => {
 				lhs.compile(builder, BuildContext::Logical)?;
 				builder.opcode(Opcode::Dup);
 				let end_jump = builder.defer_jump();
@@ -319,7 +348,7 @@ impl Expression {
 				Ok(())
 			}
 
-			Self::If(conds, else_body) => {
+			ExprKind::If(conds, else_body) => {
 				let mut deferred_jumps = Vec::with_capacity(conds.len());
 				let mut to_next: Option<crate::vm::block::JumpIndex> = None;
 
@@ -336,10 +365,11 @@ impl Expression {
 
 				to_next.unwrap().jump_unless(builder);
 
+				// Every branch leaves exactly one value, so a missing `else` yields the default.
 				if let Some(elsebody) = else_body {
-					let jump_to_end = builder.defer_jump();
 					elsebody.compile(builder, ctx)?;
-					jump_to_end.jump_unconditional(builder);
+				} else {
+					builder.load_constant(Value::default());
 				}
 
 				for deferred_jump in deferred_jumps {
@@ -349,7 +379,7 @@ impl Expression {
 				Ok(())
 			}
 
-			Self::While(cond, body) => {
+			ExprKind::While(cond, body) => {
 				let token = builder.enter_loop();
 				let start = builder.position();
 
@@ -357,16 +387,39 @@ impl Expression {
 				let jump_to_end = builder.defer_jump();
 				// if we use the return value in the future, then we can forward `ctx`.
 				body.compile(builder, BuildContext::Normal)?;
+				builder.opcode(Opcode::Pop);
 				builder.jump_unconditional(start);
 				jump_to_end.jump_unless(builder);
 
 				builder.exit_loop(token);
+				builder.load_constant(Value::default());
 				Ok(())
 			}
 
-			Self::Break => builder.jump_to_loop_end(),
-			Self::Continue => builder.jump_to_loop_start(),
-			Self::Return(result) => {
+			ExprKind::For(var, iterable, body) => {
+				iterable.compile(builder, BuildContext::Normal)?;
+				builder.load_constant(Value::Number(0.0)); // the index of the next element
+
+				let token = builder.enter_loop();
+				let start = builder.position();
+
+				let jump_to_end = builder.defer_jump();
+				builder.store_variable(&var);
+				body.compile(builder, BuildContext::Normal)?;
+				builder.opcode(Opcode::Pop);
+				builder.jump_unconditional(start);
+				jump_to_end.for_each(builder);
+
+				builder.exit_loop(token);
+				builder.opcode(Opcode::Pop); // the index
+				builder.opcode(Opcode::Pop); // the array
+				builder.load_constant(Value::default());
+				Ok(())
+			}
+
+			ExprKind::Break => builder.jump_to_loop_end(),
+			ExprKind::Continue => builder.jump_to_loop_start(),
+			ExprKind::Return(result) => {
 				if let Some(result) = result {
 					result.compile(builder, BuildContext::Normal)?;
 				} else {
@@ -376,7 +429,18 @@ impl Expression {
 				builder.opcode(Opcode::Return);
 				Ok(())
 			}
-			Self::FnDecl(_name, _args, _body) => todo!(),
+			ExprKind::FnDecl(name, args, body) => {
+				let mut fn_builder = builder.function(args);
+				body.compile(&mut fn_builder, BuildContext::Normal)?;
+				let function = fn_builder.build();
+
+				builder.load_constant(Value::Function(function.into()));
+				if let Some(name) = name {
+					builder.opcode(Opcode::Dup); // it returns a value
+					builder.store_variable(&name);
+				}
+				Ok(())
+			}
 		}
 	}
 }
@@ -1,3 +1,4 @@
+#[cfg(test)]
 use crate::parse::Token;
 use crate::vm::{Builder, Opcode};
 
@@ -14,6 +15,7 @@ pub enum LogicOperator {
 }
 
 impl LogicOperator {
+	#[cfg(test)]
 	pub fn from_token(token: &Token) -> Option<Self> {
 		match token {
 			Token::Matches => Some(Self::Matches),
@@ -1,4 +1,5 @@
 use crate::ast::Precedence;
+#[cfg(test)]
 use crate::parse::Token;
 use crate::vm::{Builder, Opcode};
 
@@ -19,6 +20,7 @@ impl MathOperator {
 	}
 
 	// the bool is whether it was an assignment
+	#[cfg(test)]
 	pub fn from_token(token: &Token) -> Option<(Self, bool)> {
 		match token {
 			Token::Add => Some((Self::Add, false)),
@@ -3,11 +3,17 @@ mod block;
 mod expression;
 mod logicop;
 mod mathop;
+mod optimize;
 mod precedence;
+mod roots;
+mod script;
 
-use atom::Atom;
-use block::Block;
-pub use expression::Expression;
-use logicop::LogicOperator;
-use mathop::MathOperator;
-use precedence::Precedence;
+pub(crate) use atom::{Atom, Interpolated};
+pub(crate) use block::Block;
+pub use expression::{ExprKind, Expression};
+pub(crate) use expression::ShortCircuit;
+pub(crate) use logicop::LogicOperator;
+pub(crate) use mathop::MathOperator;
+pub(crate) use precedence::Precedence;
+pub use roots::{Reach, Roots};
+pub use script::Script;
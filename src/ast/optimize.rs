@@ -0,0 +1,549 @@
+//! An optimization pass over expressions, which is run after parsing and before compiling.
+//!
+//! Every transformation keeps what the expression does the same. This is trickier than it sounds,
+//! as the same value means different things in different contexts: a constant string is just a
+//! string when it's assigned, but a search through the file's contents when it's a condition.
+//! So each expression is optimized with the [`BuildContext`] it'll be compiled with, and constants
+//! are only substituted where they'd compile to the same thing.
+
+use crate::ast::{
+	Atom, Block, ExprKind, Expression, Interpolated, LogicOperator, MathOperator, ShortCircuit,
+};
+use crate::vm::block::BuildContext;
+use crate::vm::{vm::Config, Opcode};
+use crate::Value;
+use std::cmp::Ordering;
+
+impl Expression {
+	/// Optimizes `self`, which should then be compiled at the top level.
+	///
+	/// Math and comparisons between constants are folded, branches whose conditions are constant
+	/// are dropped, and `&&` (and `,`) chains are reordered so that predicates which only look at
+	/// a file's path or metadata are checked before ones that read its contents.
+	#[must_use]
+	pub fn optimize(self, config: &Config) -> Self {
+		// Content searches record their matches for `--lines`, so they can't be skipped.
+		let optimizer = Optimizer { reorder: !config.should_record_matches() };
+		optimizer.expression(self, BuildContext::TopLevel)
+	}
+}
+
+struct Optimizer {
+	reorder: bool,
+}
+
+/// How expensive a predicate is to check, in the order they're put into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Cost {
+	Metadata,
+	Contents,
+}
+
+/// The type of an operand to a comparison that's cheap to get: either it's a constant, or it's a
+/// builtin which only looks at the file's path or metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+	Number,
+	Size,
+	Text,
+	Regex,
+}
+
+fn number(expr: &Expression) -> Option<f64> {
+	match &expr.kind {
+		ExprKind::Atom(Atom::Value(Value::Number(num))) => Some(*num),
+		_ => None,
+	}
+}
+
+fn constant(expr: &Expression) -> Option<Value> {
+	match &expr.kind {
+		ExprKind::Atom(Atom::Value(value)) => Some(value.clone()),
+		ExprKind::Atom(Atom::Regex(regex)) => Some(Value::Regex(regex.clone())),
+		_ => None,
+	}
+}
+
+/// Returns `value` as an atom, if it can be used in `ctx`.
+///
+/// Outside of normal contexts, constants are converted to booleans (eg strings search the file's
+/// contents), so only numbers which are already booleans can be used there.
+fn folded(value: Value, ctx: BuildContext) -> Option<Atom> {
+	match value {
+		_ if ctx == BuildContext::Normal => Some(Atom::Value(value)),
+		Value::Number(num) if num == 1.0 || num.to_bits() == 0 => Some(Atom::Value(value)),
+		_ => None,
+	}
+}
+
+/// The longest string that repeating a constant string is folded into. Anything longer is left to
+/// be built at runtime (if it's ever run), rather than by every compile.
+const MAX_FOLDED_LEN: usize = 4096;
+
+fn math(op: MathOperator, lhs: &Value, rhs: &Value) -> Option<Value> {
+	match op {
+		MathOperator::Add => lhs.add(rhs),
+		MathOperator::Subtract => lhs.subtract(rhs),
+		MathOperator::Multiply => match (lhs, rhs) {
+			(Value::Text(text), Value::Number(num)) | (Value::Number(num), Value::Text(text))
+				if text.len() as f64 * num > MAX_FOLDED_LEN as f64 =>
+			{
+				return None
+			}
+			_ => lhs.multiply(rhs),
+		},
+		MathOperator::Divide => lhs.divide(rhs),
+		MathOperator::Modulo => lhs.modulo(rhs),
+	}
+	.ok()
+}
+
+fn logic(op: LogicOperator, lhs: &Value, rhs: &Value) -> Option<bool> {
+	match op {
+		LogicOperator::Matches | LogicOperator::NotMatches => {
+			Some(lhs.matches(rhs).ok()? == (op == LogicOperator::Matches))
+		}
+		LogicOperator::Equal => Some(lhs.equals(rhs)),
+		LogicOperator::NotEqual => Some(!lhs.equals(rhs)),
+		LogicOperator::LessThan => Some(lhs.compare(rhs).ok()? < Ordering::Equal),
+		LogicOperator::LessThanOrEqual => Some(lhs.compare(rhs).ok()? <= Ordering::Equal),
+		LogicOperator::GreaterThan => Some(lhs.compare(rhs).ok()? > Ordering::Equal),
+		LogicOperator::GreaterThanOrEqual => Some(lhs.compare(rhs).ok()? >= Ordering::Equal),
+	}
+}
+
+/// How expensive `expr` is to check as a condition, if it can be reordered: it has to be a
+/// predicate (so its result is always a boolean), have no side effects, and only be able to fail
+/// if it reads the file's contents. That way moving it around can only skip content reads.
+fn cost(expr: &Expression) -> Option<Cost> {
+	match &expr.kind {
+		ExprKind::Atom(atom) => atom_cost(atom),
+		ExprKind::Logic(op, lhs, rhs) => {
+			use Operand::*;
+			let operands = (operand(lhs)?, operand(rhs)?);
+			let infallible = match op {
+				LogicOperator::Equal | LogicOperator::NotEqual => true,
+				LogicOperator::Matches | LogicOperator::NotMatches => {
+					matches!(operands, (Text | Regex, Text) | (Text, Regex) | (Size, Size))
+				}
+				_ => matches!(operands, (Number | Size, Number | Size) | (Text, Text)),
+			};
+			infallible.then_some(Cost::Metadata)
+		}
+		ExprKind::ShortCircuit(ShortCircuit::And | ShortCircuit::Or, lhs, rhs) => {
+			Some(cost(lhs)?.max(cost(rhs)?))
+		}
+		_ => None,
+	}
+}
+
+fn atom_cost(atom: &Atom) -> Option<Cost> {
+	match atom {
+		Atom::Value(Value::Text(_)) | Atom::Regex(_) => Some(Cost::Contents),
+		Atom::Value(Value::Number(_) | Value::FileSize { .. } | Value::PathGlob(_)) => {
+			Some(Cost::Metadata)
+		}
+		Atom::Not(atom) | Atom::ForcedLogical(atom) => atom_cost(atom),
+		Atom::Block(Block(exprs)) if exprs.len() == 1 => cost(&exprs[0]),
+		Atom::Variable(name) => match Opcode::builtin(name, 0)? {
+			Opcode::IsFile { .. }
+			| Opcode::IsDirectory { .. }
+			| Opcode::IsExecutable { .. }
+			| Opcode::IsSymlink { .. }
+			| Opcode::IsBroken { .. } => Some(Cost::Metadata),
+			Opcode::IsBinary { .. } => Some(Cost::Contents),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+fn operand(expr: &Expression) -> Option<Operand> {
+	match &expr.kind {
+		ExprKind::Atom(Atom::Value(Value::Number(_))) => Some(Operand::Number),
+		ExprKind::Atom(Atom::Value(Value::FileSize { .. })) => Some(Operand::Size),
+		ExprKind::Atom(Atom::Value(Value::Text(_))) => Some(Operand::Text),
+		ExprKind::Atom(Atom::Regex(_)) => Some(Operand::Regex),
+		ExprKind::Atom(Atom::Variable(name)) => match Opcode::builtin(name, 0)? {
+			Opcode::FileSize { .. } => Some(Operand::Size),
+			Opcode::Dirname { .. }
+			| Opcode::Extname { .. }
+			| Opcode::ExtnameDot { .. }
+			| Opcode::Basename { .. }
+			| Opcode::Stemname { .. } => Some(Operand::Text),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+/// Splits `a && b && ...` into its operands.
+fn flatten_and(expr: Expression, operands: &mut Vec<Expression>) {
+	match expr.kind {
+		ExprKind::ShortCircuit(ShortCircuit::And, lhs, rhs) => {
+			flatten_and(*lhs, operands);
+			flatten_and(*rhs, operands);
+		}
+		kind => operands.push(Expression::new(kind, expr.span)),
+	}
+}
+
+/// Puts the cheapest operands of the `&&` chain `expr` first, if they can all be reordered. The
+/// operands might not be next to each other anymore, so the new `&&`s all span the whole chain.
+fn reorder_and(expr: Expression) -> Expression {
+	let span = expr.span.clone();
+	let mut operands = Vec::new();
+	flatten_and(expr, &mut operands);
+
+	if let Some(costs) = operands.iter().map(cost).collect::<Option<Vec<_>>>() {
+		let mut costed = costs.into_iter().zip(operands).collect::<Vec<_>>();
+		costed.sort_by_key(|&(cost, _)| cost); // it's stable, so equal costs stay in order.
+		operands = costed.into_iter().map(|(_, operand)| operand).collect();
+	}
+
+	let and = |lhs: Expression, rhs: Expression| {
+		Expression::new(
+			ExprKind::ShortCircuit(ShortCircuit::And, lhs.into(), rhs.into()),
+			span.clone(),
+		)
+	};
+	operands.into_iter().reduce(and).unwrap()
+}
+
+impl Optimizer {
+	fn boxed(&self, mut expr: Box<Expression>, ctx: BuildContext) -> Box<Expression> {
+		*expr = self.expression(*expr, ctx);
+		expr
+	}
+
+	fn expression(&self, expr: Expression, ctx: BuildContext) -> Expression {
+		use BuildContext::{Logical, Normal};
+
+		// Whatever an expression is rewritten into spans the same source.
+		let Expression { kind, span } = expr;
+		let at = |kind| Expression::new(kind, span.clone());
+
+		match kind {
+			ExprKind::Atom(atom) => match self.atom(atom, ctx) {
+				// A block of one expression compiles to just that expression.
+				Atom::Block(Block(mut exprs)) if exprs.len() == 1 => exprs.pop().unwrap(),
+				atom => at(ExprKind::Atom(atom)),
+			},
+			ExprKind::Math(op, lhs, rhs) => {
+				let (lhs, rhs) = (self.boxed(lhs, Normal), self.boxed(rhs, Normal));
+				let kind = constant(&lhs)
+					.zip(constant(&rhs))
+					.and_then(|(lhs, rhs)| math(op, &lhs, &rhs))
+					.and_then(|result| folded(result, ctx))
+					.map_or(ExprKind::Math(op, lhs, rhs), ExprKind::Atom);
+				at(kind)
+			}
+			ExprKind::Logic(op, lhs, rhs) => {
+				let (lhs, rhs) = (self.boxed(lhs, Normal), self.boxed(rhs, Normal));
+				let kind = constant(&lhs)
+					.zip(constant(&rhs))
+					.and_then(|(lhs, rhs)| logic(op, &lhs, &rhs))
+					.map_or(ExprKind::Logic(op, lhs, rhs), |result| {
+						ExprKind::Atom(Atom::Value(result.into()))
+					});
+				at(kind)
+			}
+			ExprKind::Assignment(name, None, value) => {
+				let value_ctx = if ctx == Logical { ctx } else { Normal };
+				at(ExprKind::Assignment(name, None, self.boxed(value, value_ctx)))
+			}
+			ExprKind::Assignment(name, op, value) => {
+				at(ExprKind::Assignment(name, op, self.boxed(value, Normal)))
+			}
+			ExprKind::IndexAssignment(target, key, op, value) => at(ExprKind::IndexAssignment(
+				Box::new(self.atom(*target, Normal)),
+				self.boxed(key, Normal),
+				op,
+				self.boxed(value, Normal),
+			)),
+			ExprKind::ShortCircuitAssignment(name, cond, value) => {
+				at(ExprKind::ShortCircuitAssignment(name, cond, self.boxed(value, Normal)))
+			}
+
+			ExprKind::ShortCircuit(cond, lhs, rhs) => {
+				let (lhs, rhs) = (self.boxed(lhs, Logical), self.boxed(rhs, Logical));
+				let truthy = |expr: &Expression| number(expr).map(|num| num != 0.0);
+
+				// Constants are converted to booleans as conditions, so the result is a boolean.
+				let result = match (cond, truthy(&lhs), truthy(&rhs)) {
+					(ShortCircuit::And, Some(false), _) => Some(false),
+					(ShortCircuit::Or, Some(true), _) => Some(true),
+					(ShortCircuit::And | ShortCircuit::Or, Some(_), Some(rhs)) => Some(rhs),
+					_ => None,
+				};
+
+				match result {
+					Some(result) => at(ExprKind::Atom(Atom::Value(result.into()))),
+					None if cond == ShortCircuit::And && self.reorder => {
+						reorder_and(at(ExprKind::ShortCircuit(cond, lhs, rhs)))
+					}
+					None => at(ExprKind::ShortCircuit(cond, lhs, rhs)),
+				}
+			}
+
+			ExprKind::If(conds, else_body) => {
+				let mut branches = Vec::with_capacity(conds.len());
+				let mut else_body = else_body.map(|body| self.boxed(body, ctx));
+
+				for (cond, body) in conds {
+					let cond = self.expression(cond, Logical);
+					match number(&cond) {
+						Some(0.0) => continue, // it's never taken
+						Some(_) => {
+							// it's always taken, so the branches after it never are
+							else_body = Some(Box::new(self.expression(body, ctx)));
+							break;
+						}
+						None => branches.push((cond, self.expression(body, ctx))),
+					}
+				}
+
+				match (branches.is_empty(), else_body) {
+					(false, else_body) => at(ExprKind::If(branches, else_body)),
+					(true, Some(else_body)) => *else_body,
+					// Like a missing `else`, an empty block yields the default value.
+					(true, None) => at(ExprKind::Atom(Atom::Block(Block::new(vec![])))),
+				}
+			}
+			ExprKind::While(cond, body) => {
+				let cond = self.boxed(cond, Logical);
+				if number(&cond) == Some(0.0) {
+					return at(ExprKind::Atom(Atom::Block(Block::new(vec![]))));
+				}
+
+				at(ExprKind::While(cond, self.boxed(body, Normal)))
+			}
+			ExprKind::For(var, iterable, body) => {
+				at(ExprKind::For(var, self.boxed(iterable, Normal), self.boxed(body, Normal)))
+			}
+			ExprKind::Return(value) => {
+				at(ExprKind::Return(value.map(|value| self.boxed(value, Normal))))
+			}
+			ExprKind::FnDecl(name, args, body) => {
+				at(ExprKind::FnDecl(name, args, self.boxed(body, Normal)))
+			}
+			kind @ (ExprKind::Break | ExprKind::Continue) => at(kind),
+		}
+	}
+
+	fn block(&self, Block(exprs): Block, ctx: BuildContext) -> Block {
+		let last = exprs.len().saturating_sub(1);
+		let exprs = exprs.into_iter().enumerate().map(|(idx, expr)| {
+			self.expression(expr, if idx == last { ctx } else { BuildContext::Normal })
+		});
+
+		Block(exprs.collect())
+	}
+
+	fn interpolated(&self, interpolated: Interpolated) -> Interpolated {
+		let parts = interpolated.parts.into_iter();
+		Interpolated {
+			parts: parts
+				.map(|(prefix, block)| (prefix, self.block(block, BuildContext::Normal)))
+				.collect(),
+			tail: interpolated.tail,
+		}
+	}
+
+	fn atom(&self, atom: Atom, ctx: BuildContext) -> Atom {
+		use BuildContext::{Logical, Normal};
+		let boxed = |atom: Box<Atom>, ctx| Box::new(self.atom(*atom, ctx));
+
+		match atom {
+			Atom::Not(atom) => {
+				let atom = boxed(atom, Logical);
+				match *atom {
+					Atom::Value(Value::Number(num)) => Atom::Value((num == 0.0).into()),
+					_ => Atom::Not(atom),
+				}
+			}
+			Atom::Negate(atom) => {
+				let atom = boxed(atom, Normal);
+				match *atom {
+					Atom::Value(Value::Number(num)) => {
+						folded(Value::Number(-num), ctx).unwrap_or(Atom::Negate(atom))
+					}
+					_ => Atom::Negate(atom),
+				}
+			}
+			Atom::UPositive(atom) => Atom::UPositive(boxed(atom, Normal)),
+			Atom::ForcedLogical(atom) => {
+				let atom = boxed(atom, Logical);
+				match *atom {
+					Atom::Value(Value::Number(num)) => Atom::Value((num != 0.0).into()),
+					_ => Atom::ForcedLogical(atom),
+				}
+			}
+			Atom::Block(block) => match self.block(block, ctx) {
+				Block(mut exprs)
+					if matches!(exprs[..], [Expression { kind: ExprKind::Atom(_), .. }]) =>
+				{
+					let Some(Expression { kind: ExprKind::Atom(atom), .. }) = exprs.pop() else {
+						unreachable!()
+					};
+					atom
+				}
+				block => Atom::Block(block),
+			},
+
+			Atom::InterpolatedPath(interpolated) => {
+				Atom::InterpolatedPath(self.interpolated(interpolated))
+			}
+			Atom::InterpolatedString(interpolated) => {
+				Atom::InterpolatedString(self.interpolated(interpolated))
+			}
+			Atom::InterpolatedRegex(interpolated, flags) => {
+				Atom::InterpolatedRegex(self.interpolated(interpolated), flags)
+			}
+
+			Atom::Array(elements) => Atom::Array(
+				elements.into_iter().map(|element| self.expression(element, Normal)).collect(),
+			),
+			Atom::AssocArray(pairs) => Atom::AssocArray(
+				pairs
+					.into_iter()
+					.map(|(key, value)| (self.expression(key, Normal), self.expression(value, Normal)))
+					.collect(),
+			),
+			Atom::FnCall(func, args) => {
+				// Unwrapping `(name)(...)` would make it call the builtin `name` with the arguments.
+				let func = match *func {
+					Atom::Block(block) => Atom::Block(self.block(block, Normal)),
+					func => self.atom(func, Normal),
+				};
+				let args = args.into_iter().map(|arg| self.expression(arg, Normal));
+				Atom::FnCall(Box::new(func), args.collect())
+			}
+			Atom::Index(target, key) => Atom::Index(boxed(target, Normal), self.boxed(key, Normal)),
+
+			atom @ (Atom::Value(_) | Atom::Regex(_) | Atom::Variable(_)) => atom,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::ast::Expression;
+	use crate::parse2::Parser;
+	use crate::play::{Env, PathInfo};
+	use crate::vm::{vm::Config, RunError, Vm};
+	use crate::Value;
+	use std::path::Path;
+
+	fn parse(source: &str) -> Expression {
+		Parser::new(source, &Env::default()).parse_toplevel().unwrap()
+	}
+
+	fn optimized(source: &str) -> Expression {
+		parse(source).optimize(&Config::default())
+	}
+
+	/// Runs `expr` for `path`, returning what it evaluated to and the variables it set.
+	fn run(expr: Expression, path: &str) -> (Result<Value, RunError>, Vec<String>) {
+		let (mut vm, block) = Vm::compile(Config::default(), expr).unwrap();
+		vm.set_root(Path::new(".").into());
+		vm.set_pathinfo(PathInfo::new(path).unwrap());
+		let result = block.run(&mut vm);
+
+		let mut globals =
+			vm.globals().map(|(name, value)| format!("{name}={value}")).collect::<Vec<_>>();
+		globals.sort();
+		(result, globals)
+	}
+
+	#[test]
+	fn folds_constants() {
+		assert_eq!(parse("x = 7"), optimized("x = 1 + 2 * 3"));
+		assert_eq!(parse("x = 9"), optimized("x = (1 + 2) * 3"));
+		assert_eq!(parse(r#"x = "ab""#), optimized(r#"x = "a" + "b""#));
+		assert_eq!(parse("x = true"), optimized("x = 2 > 1 && 3 == 3"));
+		assert_eq!(parse(r#"x = "ababab""#), optimized(r#"x = "ab" * 3"#));
+		assert_eq!(parse("false"), optimized(r#"!1 || "a" =~ $/b/"#));
+
+		// Outside of assignments, strings and numbers are conditions, so they're left alone.
+		assert_eq!(parse(r#""a" + "b""#), optimized(r#""a" + "b""#));
+		assert_eq!(parse("1 + 2"), optimized("1 + 2"));
+
+		// Huge strings are only built if they're actually needed.
+		assert_eq!(parse(r#"x = "a" * 1e18"#), optimized(r#"x = "a" * 1e18"#));
+		assert_eq!(parse(r#"x = "ab" * 2049"#), optimized(r#"x = "ab" * 2049"#));
+		assert_eq!(parse("x = false"), optimized(r#"x = false && "a" * 1e18"#));
+	}
+
+	#[test]
+	fn drops_dead_branches() {
+		assert_eq!(parse("y"), optimized("if 0 do x elif 1 do y else w"));
+		assert_eq!(parse("if x do y else w"), optimized("if x do y elif false do v else w"));
+		assert_eq!(parse("()"), optimized("if false do print(1)"));
+		assert_eq!(parse("()"), optimized("while 1 - 1 do print(1)"));
+	}
+
+	#[test]
+	fn reorders_predicates() {
+		assert_eq!(parse(r#"file? && "x""#), optimized(r#""x" && file?"#));
+		assert_eq!(
+			optimized(r#"ext == "rs", size > 1kb, !dir?, "TODO", $/fn/"#),
+			optimized(r#""TODO", ext == "rs", $/fn/, size > 1kb, !dir?"#)
+		);
+		assert_eq!(optimized(r#"x = (file? && "a")"#), optimized(r#"x = ("a" && file?)"#));
+
+		// Reordering would change whether `print` is called, or what the result is.
+		assert_eq!(parse(r#""x" && print(1)"#), optimized(r#""x" && print(1)"#));
+		assert_eq!(parse(r#""x" && ext"#), optimized(r#""x" && ext"#));
+	}
+
+	#[test]
+	fn agrees_with_unoptimized() {
+		const SOURCES: &[&str] = &[
+			"x = 1 + 2 * 3",
+			"1 + 2 * 3",
+			"x = -(2 - 3) * 1.5",
+			r#"x = "a" * 3 + "b""#,
+			r#"x = "a" * 1e18"#,
+			r#"x = false && "a" * 1e18; y = "ab" * 1e4"#,
+			r#""pack" + "age""#,
+			"x = 2 > 1; y = !0; z = 1 div 0",
+			"x = (1 && 0) || (0 || 1)",
+			"if 0 do 1 elif 1 + 1 == 2 do 2 else 3",
+			"x = if false do 1 else 2",
+			"while false do print(1)",
+			"0 && size > 1",
+			r#"1 || "x""#,
+			r#""package" && file?"#,
+			r#"$/fn main/ && ext == "rs""#,
+			r#""[package]", size > 10, !dir?"#,
+			r#"("nope" || file?) && basename =~ $/^C/ && size > 1kb"#,
+			r#"x = "TODO" && ext != "toml""#,
+			r#"fn f(a) (if 1 do a * (2 + 3)); f(2) == 10 && "version""#,
+			r#"x = "{1 + 2}-{if 0 do 1 else 2}""#,
+		];
+
+		for source in SOURCES {
+			for path in ["Cargo.toml", "src", "src/main.rs", "README.md"] {
+				let (expected, expected_globals) = run(parse(source), path);
+				let (actual, actual_globals) = run(optimized(source), path);
+				assert_eq!(expected_globals, actual_globals, "{source:?} for {path}");
+
+				match (expected, actual) {
+					// Unreadable contents (eg a directory's) are the same as not matching, and
+					// skipping the read that caused one is the point of reordering.
+					(Err(RunError::Unreadable(_)), Ok(actual)) => {
+						assert!(!actual.is_truthy(), "{source:?} for {path}: {actual:?}");
+					}
+					(expected, actual) => {
+						assert_eq!(
+							format!("{expected:?}"),
+							format!("{actual:?}"),
+							"{source:?} for {path}"
+						);
+					}
+				}
+			}
+		}
+	}
+}
@@ -1,3 +1,4 @@
+#[cfg(test)]
 use crate::parse::Token;
 
 /// Order of operations precedence. The lower it is, the less tight the binding is.
@@ -15,6 +16,7 @@ pub enum Precedence {
 }
 
 impl Precedence {
+	#[cfg(test)]
 	pub fn of(token: &Token, comma_is_and: bool) -> Option<Self> {
 		match token {
 			Token::Add | Token::Subtract => Some(Self::AddSub),
@@ -0,0 +1,456 @@
+//! Works out which directories have to be traversed for an expression to match anything.
+//!
+//! Paths are matched against globs lexically: `./src/*.rs` only matches paths that start with
+//! `./src/`, so it's only worth traversing `./src`. An expression can combine any number of globs,
+//! so for each expression, we find where it could be truthy, where it could be falsy (for `!` and
+//! `--invert`), and where it could have side effects, which have to happen regardless of whether
+//! anything's matched.
+//!
+//! What's tracked for each is a [`Reach`], a union of intersections of globs, which can tell both
+//! where to start traversing and, while traversing, which directories can be skipped entirely.
+
+use crate::ast::{Atom, Block, ExprKind, Expression, ShortCircuit};
+use crate::vm::Opcode;
+use crate::{PathGlob, Value};
+use std::path::{Path, PathBuf};
+
+/// The directories an expression could match paths within, from [`Expression::begin_position`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Roots {
+	/// Nothing could be ruled out, so everything has to be traversed.
+	Anywhere,
+
+	/// Only paths within (or equal to) these could match. They're sorted and never overlap, and
+	/// there aren't any if the expression can never match.
+	Within(Vec<PathBuf>),
+}
+
+impl Roots {
+	fn nowhere() -> Self {
+		Self::Within(Vec::new())
+	}
+
+	fn within(mut roots: Vec<PathBuf>) -> Self {
+		// Paths always sort right before the paths within them.
+		roots.sort();
+		roots.dedup_by(|within, root| within.starts_with(root));
+		Self::Within(roots)
+	}
+
+	/// Whether no paths at all could be matched.
+	#[must_use]
+	pub fn never_matches(&self) -> bool {
+		matches!(self, Self::Within(roots) if roots.is_empty())
+	}
+
+	/// The paths to start traversing from, where `Anywhere` is the current directory.
+	#[must_use]
+	pub fn into_paths(self) -> Vec<PathBuf> {
+		match self {
+			Self::Anywhere => vec![".".into()],
+			Self::Within(roots) => roots,
+		}
+	}
+
+	fn union(self, other: Self) -> Self {
+		match (self, other) {
+			(Self::Within(mut lhs), Self::Within(rhs)) => {
+				lhs.extend(rhs);
+				Self::within(lhs)
+			}
+			_ => Self::Anywhere,
+		}
+	}
+
+	fn intersect(self, other: Self) -> Self {
+		match (self, other) {
+			(Self::Anywhere, other) | (other, Self::Anywhere) => other,
+			(Self::Within(lhs), Self::Within(rhs)) => {
+				// Two roots only overlap if one is within the other, in which case it's the overlap.
+				let mut roots = Vec::new();
+				for lhs in &lhs {
+					for rhs in &rhs {
+						if lhs.starts_with(rhs) {
+							roots.push(lhs.clone());
+						} else if rhs.starts_with(lhs) {
+							roots.push(rhs.clone());
+						}
+					}
+				}
+				Self::within(roots)
+			}
+		}
+	}
+}
+
+/// The most intersections a [`Reach`] keeps track of before giving up and reaching anywhere, as
+/// each `&&` of `||`s multiplies how many there are.
+const MAX_INTERSECTIONS: usize = 64;
+
+/// The paths an expression could match or do something for, from [`Expression::reach`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Reach {
+	/// Nothing could be ruled out, so everything has to be traversed.
+	#[default]
+	Anywhere,
+
+	/// Only paths that match every glob of at least one of these could be reached. Each
+	/// intersection's globs all begin within one another, and there aren't any intersections if
+	/// nothing's reached.
+	Globs(Vec<Vec<PathGlob>>),
+}
+
+impl Reach {
+	fn nowhere() -> Self {
+		Self::Globs(Vec::new())
+	}
+
+	fn globs(intersections: Vec<Vec<PathGlob>>) -> Self {
+		if intersections.len() > MAX_INTERSECTIONS {
+			Self::Anywhere
+		} else {
+			Self::Globs(intersections)
+		}
+	}
+
+	/// Where all of `globs` could match paths within, if anywhere.
+	fn root(globs: &[PathGlob]) -> Roots {
+		globs
+			.iter()
+			.map(|glob| Roots::Within(vec![glob.begin_position()]))
+			.fold(Roots::Anywhere, Roots::intersect)
+	}
+
+	/// The directories to start traversing from.
+	#[must_use]
+	pub fn roots(&self) -> Roots {
+		match self {
+			Self::Anywhere => Roots::Anywhere,
+			Self::Globs(intersections) => {
+				intersections.iter().map(|globs| Self::root(globs)).fold(Roots::nowhere(), Roots::union)
+			}
+		}
+	}
+
+	/// Whether anything within the directory `dir` could be reached, ie whether it's worth
+	/// traversing into.
+	#[must_use]
+	pub fn could_enter(&self, dir: &Path) -> bool {
+		match self {
+			Self::Anywhere => true,
+			Self::Globs(intersections) => {
+				intersections.iter().any(|globs| globs.iter().all(|glob| glob.could_match_within(dir)))
+			}
+		}
+	}
+
+	fn union(self, other: Self) -> Self {
+		match (self, other) {
+			(Self::Globs(mut lhs), Self::Globs(rhs)) => {
+				lhs.extend(rhs);
+				Self::globs(lhs)
+			}
+			_ => Self::Anywhere,
+		}
+	}
+
+	fn intersect(self, other: Self) -> Self {
+		match (self, other) {
+			(Self::Anywhere, other) | (other, Self::Anywhere) => other,
+			(Self::Globs(lhs), Self::Globs(rhs)) => {
+				let mut intersections = Vec::new();
+				for lhs in &lhs {
+					for rhs in &rhs {
+						let globs = lhs.iter().chain(rhs).cloned().collect::<Vec<_>>();
+
+						// Globs that begin in unrelated directories can't both match anything.
+						if !Self::root(&globs).never_matches() {
+							intersections.push(globs);
+						}
+					}
+				}
+				Self::globs(intersections)
+			}
+		}
+	}
+}
+
+impl Expression {
+	/// Where paths that `self` matches could be, or where it could do something even if it
+	/// doesn't match. With `inverted`, it's where paths it doesn't match could be instead.
+	#[must_use]
+	pub fn reach(&self, inverted: bool) -> Reach {
+		let analysis = analyze(self);
+		let matches = if inverted { analysis.falsy } else { analysis.truthy };
+		matches.union(analysis.effects)
+	}
+
+	/// The directories that [`reach`](Self::reach) is within.
+	#[must_use]
+	pub fn begin_position(&self, inverted: bool) -> Roots {
+		self.reach(inverted).roots()
+	}
+}
+
+/// Where an expression could be truthy, falsy, and have side effects.
+struct Analysis {
+	truthy: Reach,
+	falsy: Reach,
+	effects: Reach,
+}
+
+impl Analysis {
+	/// For expressions we know nothing about.
+	fn opaque(pure: bool) -> Self {
+		let effects = if pure { Reach::nowhere() } else { Reach::Anywhere };
+		Self { truthy: Reach::Anywhere, falsy: Reach::Anywhere, effects }
+	}
+
+	fn constant(truthy: bool) -> Self {
+		let (truthy, falsy) = if truthy {
+			(Reach::Anywhere, Reach::nowhere())
+		} else {
+			(Reach::nowhere(), Reach::Anywhere)
+		};
+		Self { truthy, falsy, effects: Reach::nowhere() }
+	}
+
+	fn glob(glob: PathGlob) -> Self {
+		Self {
+			truthy: Reach::Globs(vec![vec![glob]]),
+			falsy: Reach::Anywhere,
+			effects: Reach::nowhere(),
+		}
+	}
+
+	fn negated(self) -> Self {
+		Self { truthy: self.falsy, falsy: self.truthy, effects: self.effects }
+	}
+}
+
+fn analyze(expr: &Expression) -> Analysis {
+	match &expr.kind {
+		ExprKind::Atom(atom) => analyze_atom(atom),
+
+		// The rhs is only run where the lhs is truthy (for `&&`) or falsy (for `||`).
+		ExprKind::ShortCircuit(ShortCircuit::And, lhs, rhs) => {
+			let (lhs, rhs) = (analyze(lhs), analyze(rhs));
+			Analysis {
+				effects: lhs.effects.union(lhs.truthy.clone().intersect(rhs.effects)),
+				falsy: lhs.falsy.union(lhs.truthy.clone().intersect(rhs.falsy)),
+				truthy: lhs.truthy.intersect(rhs.truthy),
+			}
+		}
+		ExprKind::ShortCircuit(ShortCircuit::Or, lhs, rhs) => {
+			let (lhs, rhs) = (analyze(lhs), analyze(rhs));
+			Analysis {
+				effects: lhs.effects.union(lhs.falsy.clone().intersect(rhs.effects)),
+				truthy: lhs.truthy.union(lhs.falsy.clone().intersect(rhs.truthy)),
+				falsy: lhs.falsy.intersect(rhs.falsy),
+			}
+		}
+
+		_ => Analysis::opaque(is_pure(expr)),
+	}
+}
+
+fn analyze_atom(atom: &Atom) -> Analysis {
+	match atom {
+		Atom::Value(Value::PathGlob(glob)) => Analysis::glob(glob.clone()),
+		Atom::Value(Value::Path(path)) => {
+			PathGlob::parse(path).map_or_else(|_| Analysis::opaque(true), Analysis::glob)
+		}
+		Atom::Value(Value::Number(num)) => Analysis::constant(*num != 0.0),
+		Atom::Not(atom) => analyze_atom(atom).negated(),
+		Atom::ForcedLogical(atom) => analyze_atom(atom),
+
+		// Only the last expression is the result, but all of them can do things.
+		Atom::Block(Block(exprs)) if !exprs.is_empty() => {
+			let (last, rest) = exprs.split_last().unwrap();
+			let mut analysis = analyze(last);
+			if !rest.iter().all(is_pure) {
+				analysis.effects = Reach::Anywhere;
+			}
+			analysis
+		}
+
+		other => Analysis::opaque(is_pure_atom(other)),
+	}
+}
+
+/// Whether `expr` definitely doesn't do anything besides evaluating to something.
+fn is_pure(expr: &Expression) -> bool {
+	match &expr.kind {
+		ExprKind::Atom(atom) => is_pure_atom(atom),
+		ExprKind::Math(_, lhs, rhs)
+		| ExprKind::Logic(_, lhs, rhs)
+		| ExprKind::ShortCircuit(_, lhs, rhs) => is_pure(lhs) && is_pure(rhs),
+		ExprKind::If(conds, else_body) => {
+			conds.iter().all(|(cond, body)| is_pure(cond) && is_pure(body))
+				&& else_body.as_deref().is_none_or(is_pure)
+		}
+
+		// Assignments, loops (which assign, or might never end), functions, and control flow.
+		_ => false,
+	}
+}
+
+fn is_pure_atom(atom: &Atom) -> bool {
+	let all_pure = |exprs: &[Expression]| exprs.iter().all(is_pure);
+
+	match atom {
+		Atom::Value(_) | Atom::Regex(_) => true,
+		Atom::Variable(name) => Opcode::builtin(name, 0).as_ref().is_none_or(is_pure_builtin),
+		Atom::Not(atom) | Atom::Negate(atom) | Atom::UPositive(atom) | Atom::ForcedLogical(atom) => {
+			is_pure_atom(atom)
+		}
+		Atom::Block(Block(exprs)) | Atom::Array(exprs) => all_pure(exprs),
+		Atom::InterpolatedPath(interpolated)
+		| Atom::InterpolatedString(interpolated)
+		| Atom::InterpolatedRegex(interpolated, _) => {
+			interpolated.parts.iter().all(|(_, Block(exprs))| all_pure(exprs))
+		}
+		Atom::AssocArray(pairs) => pairs.iter().all(|(key, value)| is_pure(key) && is_pure(value)),
+		Atom::Index(target, key) => is_pure_atom(target) && is_pure(key),
+
+		// Calling anything other than a builtin could do anything.
+		Atom::FnCall(func, args) => {
+			let Atom::Variable(name) = &**func else {
+				return false;
+			};
+			Opcode::builtin(name, args.len()).as_ref().is_some_and(is_pure_builtin) && all_pure(args)
+		}
+	}
+}
+
+fn is_pure_builtin(opcode: &Opcode) -> bool {
+	use Opcode::*;
+
+	matches!(
+		opcode,
+		IsFile { .. }
+			| IsDirectory { .. }
+			| IsExecutable { .. }
+			| IsSymlink { .. }
+			| IsBinary { .. }
+			| IsHidden { .. }
+			| IsGitIgnored { .. }
+			| IsBroken { .. }
+			| Mime { .. }
+			| Encoding { .. }
+			| FileSize { .. }
+			| ModifyTime { .. }
+			| AccessTime { .. }
+			| ChangeTime { .. }
+			| BirthTime { .. }
+			| PushRoot
+			| PushPath
+			| Dirname { .. }
+			| Extname { .. }
+			| ExtnameDot { .. }
+			| Basename { .. }
+			| Stemname { .. }
+			| ReadLink { .. }
+			| Len | Keys
+			| Values
+			| Get | Range(_)
+			| Lines { .. }
+			| Children { .. }
+			| Head { .. }
+			| Tail { .. }
+			| Depth(_)
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Reach, Roots};
+	use crate::parse2::Parser;
+	use crate::play::Env;
+	use std::path::PathBuf;
+
+	fn roots(source: &str, inverted: bool) -> Roots {
+		let expr = Parser::new(source, &Env::default()).parse_toplevel().unwrap();
+		expr.begin_position(inverted)
+	}
+
+	fn reach(source: &str) -> Reach {
+		let expr = Parser::new(source, &Env::default()).parse_toplevel().unwrap();
+		expr.reach(false)
+	}
+
+	fn within(source: &str) -> Vec<String> {
+		match roots(source, false) {
+			Roots::Anywhere => panic!("{source:?} could match anywhere"),
+			Roots::Within(roots) => roots.iter().map(|root| root.display().to_string()).collect(),
+		}
+	}
+
+	#[test]
+	fn globs() {
+		assert_eq!(["./src"], *within("src/*.rs"));
+		assert_eq!(["./src/vm"], *within("src/vm/**/*.rs"));
+		assert_eq!(["."], *within("**/*.rs"));
+		assert_eq!(["/etc"], *within("/etc/*.conf"));
+		assert_eq!(["../sibling"], *within("../sibling/*"));
+
+		let home = home::home_dir().unwrap().join("notes");
+		assert_eq!([home.display().to_string()], *within("~/notes/*.md"));
+	}
+
+	#[test]
+	fn combinations() {
+		assert_eq!(["./src"], *within(r#"src/*.rs && "unsafe""#));
+		assert_eq!(["./a/b/c"], *within("a/b/c && a/**"));
+		assert_eq!(["./a/b/c"], *within("a/** && a/b/c"));
+		assert_eq!(["./a", "./b"], *within("b/* || a/*"));
+		assert_eq!(["./src"], *within("src/vm/* || src/*.rs || src/vm/block/*"));
+		assert_eq!(["./a", "./b/c"], *within("(a/* || b/*) && (a/x/* || b/c/* || a/*)"));
+		assert_eq!(["./src"], *within("0 || src/*"));
+		assert_eq!(["./src"], *within("!!src/*"));
+		assert_eq!(roots("1 || src/*", false), Roots::Anywhere);
+		assert_eq!(roots(r#""x" || src/*"#, false), Roots::Anywhere);
+		assert_eq!(roots("!src/*", false), Roots::Anywhere);
+	}
+
+	#[test]
+	fn never_matches() {
+		assert!(roots("a/x && b/y", false).never_matches());
+		assert!(roots("false", false).never_matches());
+		assert!(roots("(a/* || b/*) && c/*", false).never_matches());
+		assert!(!roots("a/x || b/y", false).never_matches());
+	}
+
+	#[test]
+	fn side_effects() {
+		assert_eq!(["./src"], *within("src/* && print(path)"));
+		assert_eq!(["./src"], *within("(if size > 1 do 1), src/*"));
+		assert_eq!(roots("print(path) && src/*", false), Roots::Anywhere);
+		assert_eq!(roots("n += 1; src/*", false), Roots::Anywhere);
+		assert_eq!(roots("src/* || print(path)", false), Roots::Anywhere);
+	}
+
+	#[test]
+	fn inverted() {
+		assert_eq!(roots("src/*", true), Roots::Anywhere);
+		assert_eq!(roots("!src/*", true), Roots::Within(vec![PathBuf::from("./src")]));
+		assert_eq!(roots("!(a/* || b/*)", true), Roots::Within(vec!["./a".into(), "./b".into()]));
+	}
+
+	#[test]
+	fn could_enter() {
+		let globs = reach(r#"src/**/*.rs && "unsafe""#);
+		assert!(globs.could_enter(&PathBuf::from(".")));
+		assert!(globs.could_enter(&PathBuf::from("./src/vm")));
+		assert!(!globs.could_enter(&PathBuf::from("./target")));
+		assert!(!globs.could_enter(&PathBuf::from("./target/debug")));
+
+		let globs = reach("(a/*/x || b/*) && (a/y/* || c/*)");
+		assert!(globs.could_enter(&PathBuf::from("./a/y")));
+		assert!(!globs.could_enter(&PathBuf::from("./a/z")));
+		assert!(!globs.could_enter(&PathBuf::from("./b")));
+		assert!(!globs.could_enter(&PathBuf::from("./c")));
+
+		assert!(reach("src/* || print(path)").could_enter(&PathBuf::from("./target")));
+	}
+}
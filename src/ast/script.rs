@@ -0,0 +1,76 @@
+#[cfg(test)]
+use crate::ast::Precedence;
+use crate::ast::{Atom, Block, ExprKind, Expression};
+use crate::parse::ParseError;
+#[cfg(test)]
+use crate::parse::{LexContext, Token};
+
+/// An entire program: the expression that's run for each path, along with any `^{ ... }` blocks
+/// to run before traversing and `${ ... }` blocks to run after.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+	pub begin: Vec<Expression>,
+	pub main: Option<Expression>,
+	pub end: Vec<Expression>,
+}
+
+// Parsing with the original front end, which `parse2`'s parity tests are checked against.
+#[cfg(test)]
+impl Script {
+	pub fn parse(lctx: &mut LexContext) -> Result<Self, ParseError> {
+		let mut begin = Vec::new();
+		let mut exprs = Vec::new();
+		let mut end = Vec::new();
+
+		// Toplevel expressions can be separated by `;`s, but they're optional, which allows for
+		// files of function declarations to be written one per line. Begin and end blocks can be
+		// interspersed anywhere, and are run in the order they're given.
+		loop {
+			if lctx.take_if(Token::BeginBlockStart)? {
+				begin.push(Self::parse_block(lctx)?);
+			} else if lctx.take_if(Token::EndBlockStart)? {
+				end.push(Self::parse_block(lctx)?);
+			} else if let Some(expr) = Expression::parse(lctx, true, Precedence::default())? {
+				exprs.push(expr);
+			} else {
+				break;
+			}
+
+			let _ = lctx.take_if(Token::Semicolon)?;
+		}
+
+		if lctx.next()?.is_some() {
+			return Err(ParseError::UnexpectedToken);
+		}
+
+		Self::new(begin, exprs, end)
+	}
+
+	fn parse_block(lctx: &mut LexContext) -> Result<Expression, ParseError> {
+		Ok(ExprKind::Atom(Atom::Block(Block::parse_until(lctx, Token::EndBraceEscape)?)).into())
+	}
+}
+
+impl Script {
+	/// Creates a script out of its begin blocks, toplevel expressions, and end blocks. Multiple
+	/// toplevel expressions are run one after another, like a block which spans all of them.
+	pub(crate) fn new(
+		begin: Vec<Expression>,
+		mut exprs: Vec<Expression>,
+		end: Vec<Expression>,
+	) -> Result<Self, ParseError> {
+		let main = match exprs.len() {
+			0 if begin.is_empty() && end.is_empty() => {
+				return Err(ParseError::Message("no expression given"))
+			}
+			0 => None,
+			1 => exprs.pop(),
+			_ => {
+				let span = exprs[0].span.start..exprs[exprs.len() - 1].span.end;
+				Some(Expression::new(ExprKind::Atom(Atom::Block(Block::new(exprs))), span))
+			}
+		};
+
+		Ok(Self { begin, main, end })
+	}
+}
@@ -1,3 +1,4 @@
+use crate::filesize::{FileSize, Suffix};
 use clap::{Parser, ValueEnum};
 use std::ffi::OsString;
 use std::path::PathBuf;
@@ -53,6 +54,14 @@ pub struct Args {
 	#[arg(short, long, overrides_with("jobs"))]
 	pub stable: bool,
 
+	/// How to order entries within a directory when traversing; implies `--stable`
+	#[arg(long, value_enum, value_name = "ORDER", conflicts_with("jobs"))]
+	pub sort: Option<SortOrder>,
+
+	/// Traverse directories before files within each directory; implies `--stable`
+	#[arg(long, conflicts_with("jobs"))]
+	pub dirs_first: bool,
+
 	/// Emit `\0` instead of `\n` after each match.
 	///
 	/// This is especially useful if you want to use findfile in conjunction
@@ -71,6 +80,69 @@ pub struct Args {
 	#[arg(short = 'n', long, verbatim_doc_comment, conflicts_with("print0"))]
 	pub dont_print: bool,
 
+	/// Print out the lines that matched within each file, a-la ripgrep.
+	///
+	/// Each line is printed as `path:line:column:text`, where `column` is where the first match on
+	/// the line starts. Only string and regex matches against the contents of files are printed;
+	/// paths which matched some other way (eg `*.txt`) are printed by themselves.
+	#[arg(long, conflicts_with("print0"))]
+	pub lines: bool,
+
+	/// Print NUM lines after each matched line; implies `--lines`
+	#[arg(short = 'A', long, value_name = "NUM", conflicts_with("print0"))]
+	pub after_context: Option<usize>,
+
+	/// Print NUM lines before each matched line; implies `--lines`
+	#[arg(short = 'B', long, value_name = "NUM", conflicts_with("print0"))]
+	pub before_context: Option<usize>,
+
+	/// Print NUM lines before and after each matched line; implies `--lines`
+	#[arg(short = 'C', long, value_name = "NUM", conflicts_with("print0"))]
+	pub context: Option<usize>,
+
+	/// Only read the first SIZE bytes of files when matching against their contents.
+	///
+	/// SIZE is a file size, such as `4096`, `10mb`, or `1gib`. Anything after the first SIZE bytes
+	/// is ignored by strings, regexes, `head`, `tail`, `lines`, and `--lines`, as if the file ended
+	/// there (so `tail` is the end of the first SIZE bytes). This is useful to avoid reading through
+	/// huge build artifacts, as regexes have to read everything up to SIZE at once.
+	#[arg(long, value_name = "SIZE", value_parser = parse_size)]
+	pub max_read: Option<u64>,
+
+	/// Match strings and regexes against the contents of binary files too.
+	///
+	/// By default, files which look binary (see `binary?`) never match strings or regexes, so that
+	/// searching for text doesn't turn up images and executables.
+	#[arg(short = 'a', long, visible_alias = "text")]
+	pub binary: bool,
+
+	/// Don't traverse more than NUM directories below the start positions.
+	///
+	/// The start positions themselves are at depth 0, so `--max-depth 1` only looks at their
+	/// immediate children.
+	#[arg(long, value_name = "NUM")]
+	pub max_depth: Option<usize>,
+
+	/// Only run the expression on paths at least NUM directories below the start positions.
+	///
+	/// Shallower directories are still traversed, they're just never matched.
+	#[arg(long, value_name = "NUM")]
+	pub min_depth: Option<usize>,
+
+	/// Traverse into symlinked directories.
+	///
+	/// By default, symlinks are visited but never traversed into. When following them, each
+	/// directory (by its device and inode) is only traversed once, so symlink loops are skipped.
+	#[arg(short = 'L', long)]
+	pub follow: bool,
+
+	/// Don't traverse anything that's ignored by a `.gitignore` or `.ignore` file.
+	///
+	/// Ignored directories are skipped entirely, as are paths ignored by `.git/info/exclude` and
+	/// the global excludes file. The paths that traversal starts at are never skipped.
+	#[arg(long)]
+	pub respect_ignore: bool,
+
 	/// Print out how many things matched at the end; implies `-n`
 	#[arg(short, long, conflicts_with("print0"))]
 	pub count: bool,
@@ -81,10 +153,11 @@ pub struct Args {
 	/// variables assigned in a process won't be visible in others. (In the future, we may have
 	/// ways around that.)
 	///
-	/// Many things conflict with a job count of more than 1. All the prompt commands (`mvi, cpi,
-	/// rmi, rmri, lni, ok?`) currently require a single thread to use, as well as the
-	/// `--interactive` flag. (In the future, this restriction may be relaxed). Additionally, the
-	/// `--stable` is not compatible.
+	/// Many things conflict with a job count of more than 1, and only ever use a single job. All the
+	/// prompt commands (`mvi, cpi, rmi, rmri, lni, ok?`) require a single thread to use, as do
+	/// `mv, cp, rm, rmr, ln` and `lns` when they'd prompt (eg with `--prompt=always`, or when
+	/// stdin is a terminal). (In the future, this restriction may be relaxed). Additionally,
+	/// `--interactive` and `--stable` are not compatible.
 	#[arg(short, long, conflicts_with_all(["stable", "interactive"]))]
 	pub jobs: Option<usize>,
 
@@ -96,6 +169,23 @@ pub struct Args {
 	#[arg(long)]
 	pub run_once: bool,
 
+	/// Print the parsed expression and exit, without traversing anything.
+	#[arg(long)]
+	pub dump_ast: bool,
+
+	/// Print the compiled bytecode and exit, without traversing anything.
+	///
+	/// Constants, variables, and jump targets are annotated, and functions are listed separately.
+	#[arg(long)]
+	pub dump_bytecode: bool,
+
+	/// Compile expressions exactly as they're written.
+	///
+	/// Normally, constants are folded, branches which are never taken are removed, and checks of
+	/// a file's contents are moved after checks of its metadata where that doesn't change anything.
+	#[arg(long)]
+	pub no_optimize: bool,
+
 	/// When to prompt for dangerous actions.
 	///
 	/// Dangerous actions are when any of `mv, cp, rm, rmr, ln` would end up deleting or overwriting
@@ -119,7 +209,10 @@ pub struct Args {
 	pub interactive: bool,
 
 	/// Print out changes that'd happen but don't execute them. Implies `-n`
-	#[arg(short, long)]
+	///
+	/// Each change is printed on its own line (eg `mv a -> b`), and any conflicts between them (eg
+	/// two files being moved to the same place) are reported.
+	#[arg(short, long, conflicts_with("print0"))]
 	pub dry: bool,
 
 	/// Never ask before doing anything destructive.
@@ -133,6 +226,30 @@ pub struct Args {
 	pub color: Colour,
 }
 
+impl Args {
+	/// Whether matched lines should be printed, ie `--lines` or any of the context options.
+	pub fn prints_lines(&self) -> bool {
+		self.lines
+			|| self.after_context.is_some()
+			|| self.before_context.is_some()
+			|| self.context.is_some()
+	}
+}
+
+/// Parses a file size, such as `4096` or `10mb`, into a number of bytes.
+fn parse_size(source: &str) -> Result<u64, String> {
+	let idx = source.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(source.len());
+	let (amount, suffix) = source.split_at(idx);
+	let amount = amount.parse::<f64>().map_err(|err| err.to_string())?;
+	let suffix = if suffix.is_empty() {
+		Suffix::None
+	} else {
+		Suffix::from_bytes(suffix.as_bytes()).ok_or_else(|| format!("unknown suffix {suffix:?}"))?
+	};
+
+	FileSize::new(amount, suffix).map(FileSize::bytes).ok_or_else(|| "size too large".to_string())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum IgnoreErrors {
 	/// Ignore permission errors for when interacting with dirs & files.
@@ -147,6 +264,18 @@ pub enum IgnoreErrors {
 	Subcommand,
 }
 
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+	/// Compare the raw bytes of names.
+	#[default]
+	#[value(alias = "b")]
+	Bytes,
+
+	/// Compare runs of digits by their numerical value, so `file2` comes before `file10`.
+	#[value(aliases = ["n", "version", "v"])]
+	Natural,
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum Prompt {
 	/// Prompt for destructive actions only when connected to a TTY.
@@ -0,0 +1,233 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+const SECONDS_PER_DAY: i128 = 24 * 60 * 60;
+
+/// A date and time, or an offset from the current time.
+///
+/// Absolute times are always in UTC, and are written like `2024-01-31` or `2024-01-31T12:00`.
+/// Relative times are written like `3h` or `-10d` (ten days ago), and are relative to when the
+/// program started, so that every file is compared against the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateTime {
+	/// Nanoseconds since the unix epoch.
+	Absolute(i128),
+
+	/// Nanoseconds from now.
+	Relative(i128),
+}
+
+/// The units that relative times can be written in, along with how many seconds they are.
+const UNITS: [(&str, i128); 5] =
+	[("w", 7 * SECONDS_PER_DAY), ("d", SECONDS_PER_DAY), ("h", 60 * 60), ("min", 60), ("s", 1)];
+
+impl DateTime {
+	/// The time the program started at, which relative times are relative to.
+	pub fn now() -> Self {
+		static NOW: OnceLock<DateTime> = OnceLock::new();
+		*NOW.get_or_init(|| SystemTime::now().into())
+	}
+
+	/// Creates a new absolute time; returns `None` if any of the fields are out of range.
+	pub fn from_ymd_hms(
+		year: i64,
+		month: u32,
+		day: u32,
+		hour: u32,
+		min: u32,
+		sec: u32,
+	) -> Option<Self> {
+		if !(1..=12).contains(&month)
+			|| day == 0
+			|| day > days_in_month(year, month)
+			|| 24 <= hour
+			|| 60 <= min
+			|| 60 <= sec
+		{
+			return None;
+		}
+
+		let days = days_from_civil(year, month, day) as i128;
+		let seconds = days * SECONDS_PER_DAY + (hour * 60 * 60 + min * 60 + sec) as i128;
+		Some(Self::Absolute(seconds * NANOS_PER_SECOND))
+	}
+
+	/// Creates a relative time of `amount` `unit`s, where `unit` is something like `d` or `h`.
+	pub fn relative(amount: f64, unit: &[u8]) -> Option<Self> {
+		let unit = match unit {
+			b"s" | b"sec" | b"secs" => 1,
+			b"min" | b"mins" => 60,
+			b"h" | b"hr" | b"hrs" => 60 * 60,
+			b"d" | b"day" | b"days" => SECONDS_PER_DAY,
+			b"w" | b"wk" | b"wks" => 7 * SECONDS_PER_DAY,
+			_ => return None,
+		};
+
+		Some(Self::Relative((amount * (unit * NANOS_PER_SECOND) as f64) as i128))
+	}
+
+	/// Gets the absolute time of `self`, in nanoseconds since the unix epoch. Relative times too
+	/// far from now saturate.
+	pub fn nanos(self) -> i128 {
+		match self {
+			Self::Absolute(nanos) => nanos,
+			Self::Relative(offset) => Self::now().nanos().saturating_add(offset),
+		}
+	}
+
+	pub fn is_relative(self) -> bool {
+		matches!(self, Self::Relative(_))
+	}
+
+	/// Adds `rhs` to `self`; at most one of them can be absolute. Returns `None` if they're both
+	/// absolute, or if the result would overflow.
+	pub fn checked_add(self, rhs: Self) -> Option<Self> {
+		match (self, rhs) {
+			(Self::Absolute(abs), Self::Relative(rel))
+			| (Self::Relative(rel), Self::Absolute(abs)) => abs.checked_add(rel).map(Self::Absolute),
+			(Self::Relative(lhs), Self::Relative(rhs)) => lhs.checked_add(rhs).map(Self::Relative),
+			(Self::Absolute(_), Self::Absolute(_)) => None,
+		}
+	}
+
+	/// Subtracts `rhs` from `self`. The difference between two absolute times is relative. Returns
+	/// `None` if `rhs` is absolute but `self` isn't, or if the result would overflow.
+	pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+		match (self, rhs) {
+			(Self::Absolute(lhs), Self::Relative(rhs)) => lhs.checked_sub(rhs).map(Self::Absolute),
+			(Self::Absolute(lhs), Self::Absolute(rhs))
+			| (Self::Relative(lhs), Self::Relative(rhs)) => lhs.checked_sub(rhs).map(Self::Relative),
+			(Self::Relative(_), Self::Absolute(_)) => None,
+		}
+	}
+
+	/// Compares the absolute times of `self` and `rhs`.
+	pub fn compare(self, rhs: Self) -> Ordering {
+		self.nanos().cmp(&rhs.nanos())
+	}
+}
+
+impl From<SystemTime> for DateTime {
+	fn from(time: SystemTime) -> Self {
+		match time.duration_since(UNIX_EPOCH) {
+			Ok(after) => Self::Absolute(after.as_nanos() as i128),
+			Err(before) => Self::Absolute(-(before.duration().as_nanos() as i128)),
+		}
+	}
+}
+
+impl From<Duration> for DateTime {
+	fn from(duration: Duration) -> Self {
+		Self::Relative(duration.as_nanos() as i128)
+	}
+}
+
+impl Display for DateTime {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match *self {
+			Self::Relative(0) => f.write_str("0s"),
+			Self::Relative(nanos) => {
+				let seconds = nanos / NANOS_PER_SECOND;
+				match UNITS.iter().find(|(_, size)| seconds % size == 0) {
+					Some((name, size)) if nanos % NANOS_PER_SECOND == 0 => {
+						write!(f, "{}{name}", seconds / size)
+					}
+					_ => write!(f, "{}s", nanos as f64 / NANOS_PER_SECOND as f64),
+				}
+			}
+			Self::Absolute(nanos) => {
+				let seconds = nanos.div_euclid(NANOS_PER_SECOND);
+				let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+				let time = seconds.rem_euclid(SECONDS_PER_DAY);
+				let (hour, min, sec) = (time / 3600, time / 60 % 60, time % 60);
+				write!(f, "{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}")
+			}
+		}
+	}
+}
+
+fn is_leap_year(year: i64) -> bool {
+	year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+	match month {
+		2 if is_leap_year(year) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	}
+}
+
+// These two algorithms are from http://howardhinnant.github.io/date_algorithms.html, and convert
+// between dates in the proleptic gregorian calendar and days since the unix epoch.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year.rem_euclid(400);
+	let day_of_year = (153 * ((month + 9) % 12) as i64 + 2) / 5 + day as i64 - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146_097 + day_of_era - 719_468
+}
+
+// Days are an `i128`, as that's what absolute times are, and they can be far beyond an `i64`.
+fn civil_from_days(days: i128) -> (i128, u32, u32) {
+	let days = days + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days.rem_euclid(146_097);
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+	let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+	let year = year_of_era + era * 400 + (month <= 2) as i128;
+	(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn civil_roundtrips() {
+		for days in [-800_000, -1, 0, 1, 11_016, 19_753, 2_932_896] {
+			let (year, month, day) = civil_from_days(days);
+			assert_eq!(days, days_from_civil(year as i64, month, day) as i128);
+		}
+	}
+
+	#[test]
+	fn display() {
+		assert_eq!("1970-01-01T00:00:00", DateTime::Absolute(0).to_string());
+		let date = DateTime::from_ymd_hms(2024, 2, 29, 12, 30, 5).unwrap();
+		assert_eq!("2024-02-29T12:30:05", date.to_string());
+		assert_eq!("-10d", DateTime::relative(-10.0, b"d").unwrap().to_string());
+		assert_eq!("90min", DateTime::relative(1.5, b"h").unwrap().to_string());
+		assert_eq!(None, DateTime::from_ymd_hms(2023, 2, 29, 0, 0, 0));
+	}
+
+	#[test]
+	fn arithmetic() {
+		let date = DateTime::from_ymd_hms(2024, 3, 1, 0, 0, 0).unwrap();
+		let day = DateTime::relative(1.0, b"d").unwrap();
+		let before = date.checked_sub(day).unwrap();
+		assert_eq!("2024-02-29T00:00:00", before.to_string());
+		assert_eq!(Some(date), before.checked_add(day));
+		assert_eq!(Some(day), date.checked_sub(before));
+		assert_eq!(None, date.checked_add(before));
+		assert_eq!(None, day.checked_sub(date));
+
+		// Overflowing is an error, rather than panicking or wrapping around.
+		let (max, min) = (DateTime::Relative(i128::MAX), DateTime::Relative(i128::MIN));
+		assert_eq!(None, date.checked_add(max));
+		assert_eq!(None, date.checked_sub(min));
+		assert_eq!(None, max.checked_add(day));
+		assert_eq!(None, DateTime::Absolute(i128::MIN).checked_sub(date));
+		assert_eq!(i128::MAX, max.nanos());
+		assert_eq!(Ordering::Less, min.compare(date));
+	}
+}
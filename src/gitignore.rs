@@ -0,0 +1,418 @@
+//! Working out whether paths are ignored, the same way `git` does.
+//!
+//! Only paths within a repository (a directory containing `.git`) can be ignored. Each directory
+//! within it can have a `.gitignore` and an `.ignore` (which is used by tools such as `rg` and
+//! `fd`, and takes precedence over `.gitignore`). The root of each repository can also have
+//! `.git/info/exclude`, and the global excludes file (`core.excludesFile`, which defaults to
+//! `~/.config/git/ignore`) applies to every repository.
+use crate::PathGlob;
+use os_str_bytes::OsStrBytes;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The rules from a single ignore file.
+#[derive(Debug, Default, Clone)]
+pub struct Gitignore {
+	rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+	glob: PathGlob,
+	negated: bool,  // if it started with `!`, ie it re-includes paths.
+	dir_only: bool, // if it ended with `/`.
+}
+
+impl Gitignore {
+	/// Parses the contents of an ignore file. Patterns which can't be parsed are skipped.
+	pub fn parse(source: &[u8]) -> Self {
+		Self { rules: source.split(|&c| c == b'\n').filter_map(Rule::parse).collect() }
+	}
+
+	/// Reads the ignore file at `path`, returning no rules if it can't be read.
+	pub fn from_file(path: &Path) -> Self {
+		std::fs::read(path).map(|source| Self::parse(&source)).unwrap_or_default()
+	}
+
+	/// Adds `rhs`'s rules after `self`'s, so they take precedence.
+	pub fn extend(&mut self, rhs: Self) {
+		self.rules.extend(rhs.rules);
+	}
+
+	/// Returns whether `relative` (which is relative to the directory the ignore file is in) is
+	/// ignored, or `None` if no rules matched it. The last rule which matches wins.
+	pub fn matched(&self, relative: &Path, is_dir: bool) -> Option<bool> {
+		let relative = Path::new(".").join(relative);
+
+		self
+			.rules
+			.iter()
+			.rev()
+			.find(|rule| (is_dir || !rule.dir_only) && rule.glob.is_match(&relative))
+			.map(|rule| !rule.negated)
+	}
+}
+
+impl Rule {
+	fn parse(line: &[u8]) -> Option<Self> {
+		let line = line.strip_suffix(b"\r").unwrap_or(line);
+		if line.is_empty() || line[0] == b'#' {
+			return None;
+		}
+
+		let (negated, line) = match line.strip_prefix(b"!") {
+			Some(rest) => (true, rest),
+			None => (false, line),
+		};
+
+		let line = trim_trailing_spaces(line);
+		let (dir_only, line) = match line.strip_suffix(b"/") {
+			Some(rest) => (true, rest),
+			None => (false, line),
+		};
+
+		// Patterns with a `/` at the start or in the middle are relative to the ignore file;
+		// otherwise, they can match at any depth.
+		let anchored = line.contains(&b'/');
+		let line = line.strip_prefix(b"/").unwrap_or(line);
+		if line.is_empty() {
+			return None;
+		}
+
+		// Since everything within an ignored directory is ignored, `foo/**` is the same as `foo/*`.
+		let line = line.strip_suffix(b"/**").map_or(line.to_vec(), |rest| [rest, b"/*"].concat());
+
+		let mut source = if anchored { b"./".to_vec() } else { b"./**/".to_vec() };
+		escape_glob(&line, &mut source);
+
+		let glob = PathGlob::parse(Path::new(&*OsStr::assert_from_raw_bytes(&source[..]))).ok()?;
+		Some(Self { glob, negated, dir_only })
+	}
+}
+
+/// Trailing spaces are ignored, unless they're escaped with a `\`.
+fn trim_trailing_spaces(mut line: &[u8]) -> &[u8] {
+	while let Some(rest) = line.strip_suffix(b" ") {
+		if rest.ends_with(b"\\") {
+			break;
+		}
+		line = rest;
+	}
+	line
+}
+
+/// Converts a gitignore pattern into the syntax [`PathGlob`] uses, escaping anything it'd treat
+/// differently.
+fn escape_glob(pattern: &[u8], out: &mut Vec<u8>) {
+	let mut iter = pattern.iter().copied();
+
+	while let Some(byte) = iter.next() {
+		match byte {
+			b'\\' => match iter.next() {
+				Some(c @ (b'*' | b'?' | b'{')) => out.extend_from_slice(&[b'[', c, b']']),
+				Some(c @ (b'[' | b'\\')) => out.extend_from_slice(&[b'[', b'\\', c, b']']),
+				Some(c) => out.push(c),
+				None => {}
+			},
+			b'{' => out.extend_from_slice(b"[{]"),
+			b'[' => match pattern_range(&mut iter.clone()) {
+				Some((range, len)) => {
+					out.extend_from_slice(&range);
+					for _ in 0..len {
+						iter.next();
+					}
+				}
+				None => out.extend_from_slice(b"[\\[]"),
+			},
+			other => out.push(other),
+		}
+	}
+}
+
+/// Converts the character range after a `[`, returning it (including its `[` and `]`) and how many
+/// bytes of the pattern it took up. Returns `None` if the range is never closed.
+fn pattern_range(iter: &mut impl Iterator<Item = u8>) -> Option<(Vec<u8>, usize)> {
+	let mut range = b"[".to_vec();
+	let mut len = 0;
+	let mut next = || {
+		len += 1;
+		iter.next()
+	};
+
+	let mut byte = next()?;
+	if byte == b'!' || byte == b'^' {
+		range.push(b'^');
+		byte = next()?;
+	}
+
+	// A `]` at the very start is part of the range, not its end.
+	let mut first = true;
+	loop {
+		match byte {
+			b']' if !first => break,
+			b'\\' => match next()? {
+				c @ (b'\\' | b'[' | b']' | b'-' | b'^') => range.extend_from_slice(&[b'\\', c]),
+				b'&' if range.last() == Some(&b'&') => {}
+				c => range.push(c),
+			},
+			b'-' if first => range.extend_from_slice(b"\\-"),
+			b'[' | b']' => range.extend_from_slice(&[b'\\', byte]),
+			b'&' if range.last() == Some(&b'&') => {} // `&&` means something else to `PathGlob`.
+			other => range.push(other),
+		}
+
+		first = false;
+		byte = next()?;
+	}
+
+	// A trailing `-` is a literal, not the start of a range.
+	if range.last() == Some(&b'-') && range.len() > 1 {
+		range.pop();
+		range.extend_from_slice(b"\\-");
+	}
+
+	range.push(b']');
+	Some((range, len))
+}
+
+/// Every ignore file that applies to a directory's children.
+#[derive(Debug, Default)]
+struct Level {
+	ignore: Gitignore,
+	gitignore: Gitignore,
+	exclude: Option<Gitignore>, // `.git/info/exclude` and the global excludes, for repo roots.
+}
+
+impl Level {
+	fn load(dir: &Path, global: &Gitignore, is_repo_root: bool) -> Self {
+		let exclude = is_repo_root.then(|| {
+			let mut exclude = global.clone();
+			exclude.extend(Gitignore::from_file(&dir.join(".git/info/exclude")));
+			exclude
+		});
+
+		Self {
+			ignore: Gitignore::from_file(&dir.join(".ignore")),
+			gitignore: Gitignore::from_file(&dir.join(".gitignore")),
+			exclude,
+		}
+	}
+}
+
+/// Checks whether paths are ignored, caching the ignore files it reads along the way.
+///
+/// This is shared between jobs, so that each ignore file is only read once.
+#[derive(Debug, Default)]
+pub struct Ignores {
+	global: OnceLock<Gitignore>,
+	levels: Mutex<HashMap<PathBuf, Arc<Level>>>,
+	ignored_dirs: Mutex<HashMap<PathBuf, bool>>,
+	repo_roots: Mutex<HashMap<PathBuf, Option<PathBuf>>>,
+}
+
+impl Ignores {
+	/// Returns whether `path` is ignored. Like `git`, everything within an ignored directory is
+	/// ignored too, and `.git` directories are always ignored.
+	pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+		let Ok(path) = crate::absolute_path(path) else {
+			return false;
+		};
+
+		self.is_normalized_path_ignored(&path, is_dir)
+	}
+
+	fn is_normalized_path_ignored(&self, path: &Path, is_dir: bool) -> bool {
+		match path.parent() {
+			Some(parent) if self.is_dir_ignored(parent) => true,
+			_ => self.matches(path, is_dir),
+		}
+	}
+
+	fn is_dir_ignored(&self, dir: &Path) -> bool {
+		if let Some(&ignored) = self.ignored_dirs.lock().unwrap().get(dir) {
+			return ignored;
+		}
+
+		let ignored = self.is_normalized_path_ignored(dir, true);
+		self.ignored_dirs.lock().unwrap().insert(dir.to_path_buf(), ignored);
+		ignored
+	}
+
+	/// Checks the ignore files in each of `path`'s parents, nearest first, up to the root of the
+	/// repository. The first one with a matching rule decides. Like `git`, nothing is ignored
+	/// outside of a repository, and ignore files above the repository's root don't apply.
+	fn matches(&self, path: &Path, is_dir: bool) -> bool {
+		if path.file_name() == Some(OsStr::new(".git")) {
+			return true;
+		}
+
+		let Some(root) = path.parent().and_then(|parent| self.repo_root(parent)) else {
+			return false;
+		};
+
+		for dir in path.ancestors().skip(1) {
+			let is_repo_root = dir == root;
+			let level = self.level(dir, is_repo_root);
+			let relative = path.strip_prefix(dir).expect("`dir` is an ancestor");
+
+			for gitignore in [Some(&level.ignore), Some(&level.gitignore), level.exclude.as_ref()] {
+				if let Some(ignored) = gitignore.and_then(|gi| gi.matched(relative, is_dir)) {
+					return ignored;
+				}
+			}
+
+			if is_repo_root {
+				break;
+			}
+		}
+
+		false
+	}
+
+	/// Finds the root of the repository `dir` is in, ie the nearest directory containing `.git`.
+	fn repo_root(&self, dir: &Path) -> Option<PathBuf> {
+		if let Some(root) = self.repo_roots.lock().unwrap().get(dir) {
+			return root.clone();
+		}
+
+		let root = if dir.join(".git").exists() {
+			Some(dir.to_path_buf())
+		} else {
+			dir.parent().and_then(|parent| self.repo_root(parent))
+		};
+
+		self.repo_roots.lock().unwrap().insert(dir.to_path_buf(), root.clone());
+		root
+	}
+
+	fn level(&self, dir: &Path, is_repo_root: bool) -> Arc<Level> {
+		if let Some(level) = self.levels.lock().unwrap().get(dir) {
+			return level.clone();
+		}
+
+		// Don't hold the lock while reading files, so other jobs aren't held up.
+		let global = self.global.get_or_init(|| {
+			global_excludes().map_or_else(Default::default, |path| Gitignore::from_file(&path))
+		});
+		let level = Arc::new(Level::load(dir, global, is_repo_root));
+		self.levels.lock().unwrap().insert(dir.to_path_buf(), level.clone());
+		level
+	}
+}
+
+/// Finds the global excludes file: `core.excludesFile` from `~/.gitconfig`, or
+/// `$XDG_CONFIG_HOME/git/ignore` if it's not set.
+fn global_excludes() -> Option<PathBuf> {
+	let home = home::home_dir()?;
+
+	if let Ok(config) = std::fs::read_to_string(home.join(".gitconfig")) {
+		let mut in_core = false;
+		for line in config.lines().map(str::trim) {
+			if line.starts_with('[') {
+				in_core = line.eq_ignore_ascii_case("[core]");
+				continue;
+			}
+
+			let Some((key, value)) = line.split_once('=') else {
+				continue;
+			};
+
+			if in_core && key.trim().eq_ignore_ascii_case("excludesfile") {
+				let value = value.trim().trim_matches('"');
+				return Some(match value.strip_prefix("~/") {
+					Some(rest) => home.join(rest),
+					None => value.into(),
+				});
+			}
+		}
+	}
+
+	let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+		.filter(|dir| !dir.is_empty())
+		.map_or_else(|| home.join(".config"), PathBuf::from);
+	Some(config_dir.join("git/ignore"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn matched(source: &str, path: &str, is_dir: bool) -> Option<bool> {
+		Gitignore::parse(source.as_bytes()).matched(Path::new(path), is_dir)
+	}
+
+	#[test]
+	fn patterns() {
+		assert_eq!(Some(true), matched("*.o", "a/b/c.o", false));
+		assert_eq!(None, matched("*.o", "a/b/c.oo", false));
+		assert_eq!(Some(true), matched("/target", "target", true));
+		assert_eq!(None, matched("/target", "a/target", true));
+		assert_eq!(Some(true), matched("a/b", "a/b", false));
+		assert_eq!(None, matched("a/b", "x/a/b", false));
+		assert_eq!(Some(true), matched("**/build", "x/y/build", true));
+		assert_eq!(Some(true), matched("a/**/z", "a/z", false));
+		assert_eq!(Some(true), matched("a/**/z", "a/b/c/z", false));
+		assert_eq!(Some(true), matched("logs/**", "logs/today", false));
+		assert_eq!(None, matched("logs/**", "logs", true));
+		assert_eq!(None, matched("out/", "out", false));
+		assert_eq!(Some(true), matched("out/", "out", true));
+		assert_eq!(Some(false), matched("*.log\n!keep.log", "keep.log", false));
+		assert_eq!(Some(true), matched("!keep.log\n*.log", "keep.log", false));
+		assert_eq!(Some(true), matched("\\#notes\n# comment", "#notes", false));
+		assert_eq!(Some(true), matched("\\!important", "!important", false));
+		assert_eq!(Some(true), matched("file[0-9].txt", "file3.txt", false));
+		assert_eq!(Some(true), matched("file[!0-9].txt", "filex.txt", false));
+		assert_eq!(None, matched("file[!0-9].txt", "file3.txt", false));
+		assert_eq!(Some(true), matched("[-_]x", "-x", false));
+		assert_eq!(Some(true), matched("a\\*b", "a*b", false));
+		assert_eq!(None, matched("a\\*b", "axb", false));
+		assert_eq!(Some(true), matched("{a}", "{a}", false));
+		assert_eq!(Some(true), matched("trailing  ", "trailing", false));
+	}
+
+	#[test]
+	fn repositories() {
+		let root = std::env::temp_dir().join(format!("ff-gitignore-{}", std::process::id()));
+		let write = |path: &str, contents: &str| {
+			let path = root.join(path);
+			std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+			std::fs::write(path, contents).unwrap();
+		};
+
+		write(".gitignore", "*.txt\n*.o\n");
+		write("outside/.ignore", "*.md\n");
+		write("repo/.git/info/exclude", "*.tmp\n");
+		write("repo/.gitignore", "*.o\n/build/\n");
+		write("repo/sub/.gitignore", "!keep.o\n");
+		write("repo/sub/.ignore", "*.md\n");
+
+		let ignores = Ignores::default();
+		ignores.global.set(Gitignore::parse(b"*.global\n")).unwrap();
+		let is_ignored = |path: &str, is_dir| ignores.is_ignored(&root.join(path), is_dir);
+
+		// Nothing is ignored outside of a repository.
+		assert!(!is_ignored("a.txt", false));
+		assert!(!is_ignored("outside/a.md", false));
+		assert!(!is_ignored("outside/a.global", false));
+
+		// Ignore files above the repository's root don't apply within it.
+		assert!(!is_ignored("repo/a.txt", false));
+		assert!(!is_ignored("repo/sub/a.txt", false));
+
+		assert!(is_ignored("repo/a.o", false));
+		assert!(is_ignored("repo/a.tmp", false));
+		assert!(is_ignored("repo/a.global", false));
+		assert!(is_ignored("repo/build", true));
+		assert!(is_ignored("repo/build/inner.rs", false));
+		assert!(!is_ignored("repo/sub/build", true));
+		assert!(is_ignored("repo/sub/a.o", false));
+		assert!(!is_ignored("repo/sub/keep.o", false));
+		assert!(is_ignored("repo/sub/a.md", false));
+		assert!(!is_ignored("repo/a.md", false));
+		assert!(is_ignored("repo/.git", true));
+
+		std::fs::remove_dir_all(root).unwrap();
+	}
+}
@@ -1,6 +1,7 @@
 // #![allow(unused)]
 extern crate static_assertions as sa;
 
+#[allow(unused_macros)]
 macro_rules! likely {
 	($x:expr) => {
 		$x
@@ -15,24 +16,59 @@ macro_rules! unlikely {
 
 pub mod ast;
 pub mod cli;
+pub mod datetime;
 pub mod filesize;
+pub mod gitignore;
 pub mod parse;
-// pub mod parse2;
+pub mod parse2;
 pub mod pathglob;
 pub mod play;
 mod regex;
 mod value;
 pub mod vm;
-pub mod vm2;
 pub use regex::Regex;
 pub use value::Value;
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct DateTime;
+pub use datetime::DateTime;
 pub use pathglob::PathGlob;
 
 pub use filesize::FileSize;
 
+/// Makes `path` absolute, and removes any `.`s and `..`s from it, without touching the filesystem.
+fn absolute_path(path: &std::path::Path) -> std::io::Result<std::path::PathBuf> {
+	use std::path::{Component, PathBuf};
+
+	let mut normalized = PathBuf::new();
+	for component in std::path::absolute(path)?.components() {
+		match component {
+			Component::CurDir => {}
+			Component::ParentDir => {
+				normalized.pop();
+			}
+			other => normalized.push(other),
+		}
+	}
+	Ok(normalized)
+}
+
 fn slice_contains(haystack: &[u8], needle: &[u8]) -> bool {
-	haystack.windows(needle.len()).any(|c| c == needle)
+	needle.is_empty() || haystack.windows(needle.len()).any(|c| c == needle)
+}
+
+/// Finds every non-overlapping occurrence of `needle` in `haystack`.
+fn slice_find_all<'a>(
+	haystack: &'a [u8],
+	needle: &'a [u8],
+) -> impl Iterator<Item = std::ops::Range<usize>> + 'a {
+	let mut start = 0;
+	std::iter::from_fn(move || {
+		if needle.is_empty() {
+			return None;
+		}
+
+		let offset = haystack[start..].windows(needle.len()).position(|c| c == needle)?;
+		let range = start + offset..start + offset + needle.len();
+		start = range.end;
+		Some(range)
+	})
 }
@@ -4,7 +4,6 @@ use clap::CommandFactory;
 use clap::Parser;
 use findfile::ast::Expression;
 use findfile::cli::{Args, Colour, IgnoreErrors, Prompt};
-use findfile::parse::LexContext;
 use findfile::play::program::{Config, Program};
 use findfile::play::Env;
 use findfile::play::{PlayError, PlayResult};
@@ -13,17 +12,22 @@ use std::process::ExitCode;
 fn main() -> ExitCode {
 	match _main() {
 		Ok(status) => status,
+		Err(PlayError::CannotParse(diag)) => {
+			eprintln!("{diag}");
+			ExitCode::from(2)
+		}
 		Err(err) => {
 			eprintln!("uncaught error: {err:?}");
-			ExitCode::FAILURE
+			ExitCode::from(2)
 		}
 	}
 }
 
 fn _main() -> PlayResult<ExitCode> {
 	let mut args = Args::parse();
-	let source = if let Some(ref file) = args.file {
-		std::fs::read_to_string(file)?
+	// Scripts given with `--file` are read by the program, so errors in them can name the file.
+	let source = if args.file.is_some() {
+		String::new()
 	} else {
 		let mut expr = std::mem::take(&mut args.expression).unwrap_or_else(|| ".".into());
 		while expr.ends_with(',') {
@@ -47,8 +51,12 @@ fn _main() -> PlayResult<ExitCode> {
 		program.run_file(&imported_file)?;
 	}
 
-	program.play_expr(&source)?;
-	return Ok(ExitCode::SUCCESS);
+	if let Some(ref file) = args.file {
+		program.play_file(file)?;
+	} else {
+		program.play_expr(&source)?;
+	}
+	Ok(ExitCode::from(program.exit_status()))
 
 	// Args::command()
 	// 	.error(ErrorKind::ArgumentConflict, "Can't do relative and absolute version change").a
@@ -0,0 +1,115 @@
+//! Showing [`ParseError`]s to the user, along with where in the source they happened.
+
+use crate::parse::ParseError;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A [`ParseError`] and where it happened. When displayed, the offending line is shown with the
+/// error's span underlined, a-la `rustc`.
+#[derive(Debug)]
+pub struct Diagnostic {
+	pub error: ParseError,
+	file: Option<PathBuf>,
+	snippet: Option<(Vec<u8>, Range<usize>)>, // the entire source, and the span within it.
+}
+
+impl Diagnostic {
+	/// Creates a diagnostic which doesn't know where `error` happened, which is the case for errors
+	/// from compiling.
+	pub fn new(error: ParseError) -> Self {
+		Self { error, file: None, snippet: None }
+	}
+
+	/// Sets where `error` happened to `span`, which is a range of bytes within `source`.
+	pub fn with_snippet(mut self, source: &[u8], mut span: Range<usize>) -> Self {
+		// Errors at the very end of the source should point just after the last token, rather than
+		// at trailing whitespace.
+		if span.is_empty() && source[span.start..].trim_ascii().is_empty() {
+			span.start = source[..span.start].trim_ascii_end().len();
+			span.end = span.start;
+		}
+
+		self.snippet = Some((source.to_owned(), span));
+		self
+	}
+
+	/// Records that the source came from the file at `path`.
+	pub fn in_file(mut self, path: &Path) -> Self {
+		self.file = Some(path.to_owned());
+		self
+	}
+
+	pub fn file(&self) -> Option<&Path> {
+		self.file.as_deref()
+	}
+}
+
+impl From<ParseError> for Diagnostic {
+	fn from(error: ParseError) -> Self {
+		Self::new(error)
+	}
+}
+
+impl Display for Diagnostic {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		write!(f, "error: {}", self.error)?;
+
+		let file = self.file.as_ref().map(|path| path.display());
+		let Some((ref source, ref span)) = self.snippet else {
+			return match file {
+				Some(file) => write!(f, "\n --> {file}"),
+				None => Ok(()),
+			};
+		};
+
+		let start = span.start.min(source.len());
+		let line_start = source[..start].iter().rposition(|&c| c == b'\n').map_or(0, |idx| idx + 1);
+		let line_end =
+			source[start..].iter().position(|&c| c == b'\n').map_or(source.len(), |idx| start + idx);
+		let line_number = source[..start].iter().filter(|&&c| c == b'\n').count() + 1;
+
+		// Columns are counted in characters, and tabs are kept so the carets still line up.
+		let before = String::from_utf8_lossy(&source[line_start..start]);
+		let column = before.chars().count() + 1;
+		let padding = before.chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+		let underlined = &source[start..span.end.clamp(start, line_end)];
+		let carets = "^".repeat(String::from_utf8_lossy(underlined).chars().count().max(1));
+
+		let line = String::from_utf8_lossy(&source[line_start..line_end]);
+		let gutter = " ".repeat(line_number.to_string().len());
+		match file {
+			Some(file) => writeln!(f, "\n{gutter}--> {file}:{line_number}:{column}")?,
+			None => writeln!(f, "\n{gutter}--> <expression>:{line_number}:{column}")?,
+		}
+		writeln!(f, "{gutter} |")?;
+		writeln!(f, "{line_number} | {line}")?;
+		write!(f, "{gutter} | {padding}{carets}")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parse2::Parser;
+	use crate::play::Env;
+
+	fn render(source: &str) -> String {
+		let env = Env::default();
+		Parser::new(source, &env).parse_script().unwrap_err().diagnose(source).to_string()
+	}
+
+	#[test]
+	fn renders() {
+		assert_eq!(
+			"error: expected `do` after `if` condition\n \
+			 --> <expression>:1:6\n  |\n1 | if 1 then 2\n  |      ^^^^",
+			render("if 1 then 2")
+		);
+
+		assert_eq!(
+			"error: expected an expression after operator\n \
+			 --> <expression>:2:5\n  |\n2 | \t1 +\n  | \t   ^",
+			render("x=1\n\t1 +\n\n")
+		);
+	}
+}
@@ -1,7 +1,8 @@
-use crate::parse::{ParseError, Stream, Token};
+use crate::parse::{Diagnostic, ParseError, Stream, Token};
 use crate::play::Program;
 use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
+use std::ops::Range;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Phase {
@@ -17,6 +18,7 @@ pub struct LexContext<'a> {
 	pub(super) stream: Stream<'a>,
 	phases: Vec<Phase>,
 	tokens: Vec<Token>,
+	pub(super) span: Range<usize>, // where the most recently lexed token is, for errors.
 	env: HashMap<OsString, Option<OsString>>,
 	program: &'a mut Program,
 }
@@ -27,20 +29,14 @@ impl<'a> LexContext<'a> {
 			stream: Stream::new(source.as_ref()),
 			phases: Vec::with_capacity(2), // sensible defaults
 			tokens: Vec::with_capacity(2),
+			span: 0..0,
 			env: Default::default(),
 			program,
 		}
 	}
 
 	pub fn get_cli(&self, pos: isize) -> Option<&OsStr> {
-		assert_ne!(pos, 0, "$0 doesnt exist rn");
-
-		let pos = if let Ok(pos) = usize::try_from(pos) {
-			pos - 1
-		} else {
-			usize::try_from((self.program.env().cli_len() as isize) - pos).ok()?
-		};
-		self.program.env().get_cli(pos)
+		self.program.env().get_cli_arg(pos)
 	}
 
 	pub fn get_env<'b>(&'b mut self, name: &OsStr) -> Option<&'b OsStr> {
@@ -53,6 +49,13 @@ impl<'a> LexContext<'a> {
 		env[name].as_deref()
 	}
 
+	/// Attaches where the most recently lexed token is to `error`. Errors are always about the token
+	/// that was just lexed (or the end of the source, if there are none left), so this points at
+	/// what the error's about.
+	pub fn diagnose(&self, error: ParseError) -> Diagnostic {
+		Diagnostic::new(error).with_snippet(self.stream.source(), self.span.clone())
+	}
+
 	pub fn phase(&self) -> Option<Phase> {
 		self.phases.last().copied()
 	}
@@ -86,7 +89,7 @@ impl<'a> LexContext<'a> {
 		&mut self,
 		cond: impl FnOnce(&Token) -> bool,
 	) -> Result<Option<Token>, ParseError> {
-		if !self.peek()?.map_or(false, cond) {
+		if !self.peek()?.is_some_and(cond) {
 			return Ok(None);
 		}
 
@@ -95,7 +98,7 @@ impl<'a> LexContext<'a> {
 
 	pub fn peek(&mut self) -> Result<Option<&Token>, ParseError> {
 		if self.tokens.is_empty() {
-			if let Some(token) = Token::parse(self)? {
+			if let Some(token) = self.lex()? {
 				self.tokens.push(token);
 			}
 		}
@@ -112,11 +115,20 @@ impl<'a> LexContext<'a> {
 		}
 	}
 
+	#[allow(clippy::should_implement_trait)]
 	pub fn next(&mut self) -> Result<Option<Token>, ParseError> {
 		if let Some(token) = self.tokens.pop() {
 			return Ok(Some(token));
 		}
 
-		Token::parse(self)
+		self.lex()
+	}
+
+	fn lex(&mut self) -> Result<Option<Token>, ParseError> {
+		// Leading whitespace is skipped over by moving `span.start` up once it's been stripped.
+		self.span.start = self.stream.index();
+		let token = Token::parse(self);
+		self.span.end = self.stream.index();
+		token
 	}
 }
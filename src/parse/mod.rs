@@ -1,11 +1,27 @@
+//! The [`ParseError`]s and [`Diagnostic`]s both front ends use, along with the original lexer.
+//!
+//! Source code is parsed with [`parse2`](crate::parse2). The original lexer (and the parsing
+//! functions on the AST nodes built on top of it) is only compiled for tests, where it's the
+//! oracle that `parse2`'s parity tests check trees and errors against.
+
+mod diagnostic;
+#[cfg(test)]
 mod lex_context;
+#[cfg(test)]
 mod stream;
-pub(crate) mod token; // tmp
+#[cfg(test)]
+mod token;
 
+pub use diagnostic::Diagnostic;
+#[cfg(test)]
 pub use lex_context::{LexContext, Phase};
+#[cfg(test)]
 pub use stream::Stream;
+#[cfg(test)]
 pub use token::Token;
 
+use std::fmt::{self, Display, Formatter};
+
 #[derive(Debug)]
 pub enum ParseError {
 	Eof,
@@ -29,10 +45,57 @@ pub enum ParseError {
 	MissingRhsToOp,
 	MissingRhsToLogicOp,
 	FileSizeLiteralTooLarge,
+	UnknownNumberSuffix(String),
+	InvalidDateTime,
 	CliArgMissing,
 	AssignToNonVariable,
 	InvalidRegex(crate::regex::RegexParseError),
 	InvalidFunctionCall { name: String, argc: usize },
+	UnexpectedToken,
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Self::Eof => f.write_str("unexpected end of input"),
+			Self::VariableIsntUtf8 => f.write_str("variable names must be valid UTF-8"),
+			Self::UnknownTokenStart(chr) => write!(f, "unexpected character `{chr}`"),
+			Self::BadEscape(why) => write!(f, "bad escape: {why}"),
+			Self::Message(msg) => f.write_str(msg),
+			Self::InvalidEscape(chr) => write!(f, "unknown escape `\\{chr}`"),
+			Self::CliArgTooLarge => f.write_str("command-line argument position is too large"),
+			Self::InvalidDollarSign => {
+				f.write_str("expected a variable name or argument position after `$`")
+			}
+			Self::BadFloat => f.write_str("invalid number"),
+			Self::MissingEndingBrace => f.write_str("missing closing `}`"),
+			Self::InvalidCliPosition(pos) => write!(f, "there's no command-line argument ${pos}"),
+			Self::MissingEnvVar(name) => {
+				write!(f, "environment variable `{}` isn't set", name.to_string_lossy())
+			}
+			Self::BadPath(err) => write!(f, "invalid path: {err}"),
+			Self::MissingEndQuote => f.write_str("missing closing `\"`"),
+			Self::NotAndEndOfExpression => f.write_str("expected an expression after unary operator"),
+			Self::MissingEndRegex => f.write_str("missing closing `/` for regex"),
+			Self::NoClosingRightParen => f.write_str("missing closing `)`"),
+			Self::MissingRhsToAssignment => f.write_str("expected a value after `=`"),
+			Self::MissingRhsToOp => f.write_str("expected an expression after operator"),
+			Self::MissingRhsToLogicOp => f.write_str("expected an expression after `&&` or `||`"),
+			Self::FileSizeLiteralTooLarge => f.write_str("file size is too large"),
+			Self::UnknownNumberSuffix(suffix) => write!(f, "unknown number suffix `{suffix}`"),
+			Self::InvalidDateTime => f.write_str("invalid date"),
+			Self::CliArgMissing => f.write_str("expected an argument position after `$`"),
+			Self::AssignToNonVariable => f.write_str("can only assign to variables and indices"),
+			Self::InvalidRegex(err) => write!(f, "invalid regex: {err}"),
+			Self::InvalidFunctionCall { name, argc: 1 } => {
+				write!(f, "`{name}` can't be called with 1 argument")
+			}
+			Self::InvalidFunctionCall { name, argc } => {
+				write!(f, "`{name}` can't be called with {argc} arguments")
+			}
+			Self::UnexpectedToken => f.write_str("unexpected token"),
+		}
+	}
 }
 
 impl From<crate::regex::RegexParseError> for ParseError {
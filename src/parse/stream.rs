@@ -26,6 +26,18 @@ impl<'a> Stream<'a> {
 		}
 	}
 
+	/// How many bytes into the source the stream is.
+	#[must_use]
+	pub fn index(&self) -> usize {
+		self.index
+	}
+
+	/// The entire source, including what's already been taken.
+	#[must_use]
+	pub fn source(&self) -> &'a [u8] {
+		self.source
+	}
+
 	/// Returns whether the stream is currently at end of file.
 	#[must_use]
 	pub fn is_eof(&self) -> bool {
@@ -35,7 +47,7 @@ impl<'a> Stream<'a> {
 	/// Returns the first character without consuming it.
 	#[must_use]
 	pub fn peek(&self) -> Option<u8> {
-		self.remainder().get(0).copied()
+		self.remainder().first().copied()
 	}
 
 	/// Advances the stream by `len` bytes.
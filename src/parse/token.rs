@@ -1,6 +1,7 @@
 use crate::filesize::{FileSize, Suffix};
 use crate::parse::Stream;
 use crate::parse::{LexContext, ParseError, Phase};
+use crate::DateTime;
 use os_str_bytes::OsStringBytes;
 use std::ffi::OsString;
 use std::fmt::Debug;
@@ -38,7 +39,7 @@ pub enum Token {
 	EnvVar(OsString),
 	Variable(String),
 	Number(f64),
-	DateTime(crate::DateTime),
+	DateTime(DateTime),
 	FileSize { fs: crate::FileSize, precision: u8 },
 
 	// Keywords
@@ -48,6 +49,7 @@ pub enum Token {
 	Elif,
 	While,
 	For,
+	In,
 	Break,
 	Continue,
 	Return,
@@ -70,6 +72,9 @@ pub enum Token {
 	EndBlockStart,   // `$(`
 	LeftParen,       // `(`
 	RightParen,      // `)`
+	LeftBracket,     // `[`
+	RightBracket,    // `]`
+	LeftBrace,       // `{` (the closing `}` is an `EndBraceEscape`)
 
 	// control characters
 	Question,  // `?`
@@ -137,10 +142,8 @@ impl<'a> Stream<'a> {
 			b'u' => {
 				let [a, b, c, d] = self.take_n().ok_or(ParseError::BadEscape("nothing after `u`"))?;
 
-				let hexnum = (parse_hex(a)? << 12)
-					| (parse_hex(b)? << 8)
-					| (parse_hex(c)? << 4)
-					| (parse_hex(d)? << 0);
+				let hexnum =
+					(parse_hex(a)? << 12) | (parse_hex(b)? << 8) | (parse_hex(c)? << 4) | parse_hex(d)?;
 
 				char::from_u32(hexnum).ok_or(ParseError::BadEscape("invalid `\\u` escape"))
 			}
@@ -152,7 +155,7 @@ impl<'a> Stream<'a> {
 	/// Parses out `[0-9][0-9_]*`
 	fn parse_digits(&mut self, into: &mut String) -> bool {
 		// Make sure we start with just a digit, not a `_`
-		if !self.peek().map_or(false, |x| x.is_ascii_digit()) {
+		if !self.peek().is_some_and(|x| x.is_ascii_digit()) {
 			return false;
 		}
 
@@ -173,6 +176,44 @@ impl<'a> Stream<'a> {
 		}
 	}
 
+	/// Parses out `YYYY-MM-DD`, optionally followed by `THH:MM` or `THH:MM:SS`.
+	fn parse_datetime(&mut self) -> Result<Option<DateTime>, ParseError> {
+		fn number(digits: &[u8]) -> Option<u32> {
+			digits
+				.iter()
+				.try_fold(0, |acc, c| c.is_ascii_digit().then(|| acc * 10 + (c - b'0') as u32))
+		}
+
+		let rest = self.remainder();
+		let Some(date) = rest.get(..10).filter(|date| date[4] == b'-' && date[7] == b'-') else {
+			return Ok(None);
+		};
+		let (Some(year), Some(month), Some(day)) =
+			(number(&date[..4]), number(&date[5..7]), number(&date[8..]))
+		else {
+			return Ok(None);
+		};
+
+		let (mut len, mut hour, mut min, mut sec) = (date.len(), 0, 0, 0);
+		if let Some(time) =
+			rest.get(10..16).filter(|time| b"Tt".contains(&time[0]) && time[3] == b':')
+		{
+			if let (Some(h), Some(m)) = (number(&time[1..3]), number(&time[4..])) {
+				(len, hour, min) = (16, h, m);
+
+				if let Some(s) = rest.get(16..19).filter(|s| s[0] == b':').and_then(|s| number(&s[1..]))
+				{
+					(len, sec) = (19, s);
+				}
+			}
+		}
+
+		let datetime = DateTime::from_ymd_hms(year.into(), month, day, hour, min, sec)
+			.ok_or(ParseError::InvalidDateTime)?;
+		self.advance_by(len);
+		Ok(Some(datetime))
+	}
+
 	fn parse_base_integer(&mut self) -> Result<Option<u64>, ParseError> {
 		// If it doesn't start with `0`, it's not a base integer.
 		if !self.advance_if(b'0') {
@@ -189,7 +230,7 @@ impl<'a> Stream<'a> {
 
 			// Binary literal
 			// `0b` is a byte literal, so `0b<DIGIT>` is required for binary literal
-			Some(b'b' | b'B') if self.peek().map_or(false, |c| c.is_ascii_digit()) => 2,
+			Some(b'b' | b'B') if self.peek().is_some_and(|c| c.is_ascii_digit()) => 2,
 			Some(b'b' | b'B') => {
 				self.untake();
 				self.untake();
@@ -204,7 +245,7 @@ impl<'a> Stream<'a> {
 		};
 
 		let buf = self
-			.take_while(|c| (c as char).to_digit(radix).is_some() || c == b'_')
+			.take_while(|c| (c as char).is_digit(radix) || c == b'_')
 			.iter()
 			.filter_map(|&x| (x != b'_').then_some(x as char))
 			.collect::<String>();
@@ -276,6 +317,10 @@ fn is_ascii_alphanumeric_or_underscore(c: u8) -> bool {
 	c.is_ascii_alphanumeric() || c == b'_'
 }
 
+fn is_dollar_sign_escape_start(c: u8) -> bool {
+	is_ascii_alphanumeric_or_underscore(c) || c == b'{'
+}
+
 fn is_path_literal_character(c: u8) -> bool {
 	!c.is_ascii_whitespace() && !b",();&|".contains(&c)
 }
@@ -420,8 +465,9 @@ impl Token {
 		let mut buf = Vec::new();
 		while let Some(c) = lctx.stream.take() {
 			match c {
-				// `$` escapes for cli values and env vars
-				b'$' => {
+				// `$` escapes for cli values and env vars. A `$` not followed by a name is the regex
+				// end-of-line anchor.
+				b'$' if lctx.stream.peek().is_some_and(is_dollar_sign_escape_start) => {
 					lctx.push_phase(Phase::DollarSignEscape);
 					break;
 				}
@@ -433,6 +479,14 @@ impl Token {
 					break;
 				}
 
+				// `\/` is a literal `/`; every other escape is left for the regex engine to handle,
+				// which also lets `\{` and `\$` be used literally.
+				b'\\' => match lctx.stream.take() {
+					Some(b'/') => buf.push(b'/'),
+					Some(other) => buf.extend([b'\\', other]),
+					None => return Err(ParseError::MissingEndRegex),
+				},
+
 				// `/` ends the regex, with optional syntax vars at the end.
 				b'/' => {
 					let flags = lctx.stream.take_while(|c| c.is_ascii_alphabetic());
@@ -502,7 +556,12 @@ impl Token {
 				precision: precision.unwrap_or(0),
 			});
 		}
-		todo!()
+
+		if let Some(datetime) = DateTime::relative(num, suffix) {
+			return Ok(Self::DateTime(datetime));
+		}
+
+		Err(ParseError::UnknownNumberSuffix(String::from_utf8_lossy(suffix).into_owned()))
 	}
 
 	fn _is_path_next_token(stream: &mut Stream) -> bool {
@@ -512,7 +571,7 @@ impl Token {
 		// these all unambiguously indicate a path start
 		if b"*.+/~".contains(&rest[0]) {
 			if (rest[0] == b'*' || rest[0] == b'+')
-				&& rest.get(1).map_or(false, |x| *x == b'=' || x.is_ascii_whitespace())
+				&& rest.get(1).is_some_and(|x| *x == b'=' || x.is_ascii_whitespace())
 			{
 				return false;
 			}
@@ -543,7 +602,7 @@ impl Token {
 	}
 
 	// this is not terrific, it doesnt do legit parsing like it should. (eg what if
-	// a `[]` has `/` in it, and we add `[]` for array literals in the future?)
+	// a `[]` has `/` in it? `[]` are also array literals, so `["a/b"]` is a path.)
 	// It also doesn't account for interpolation with spaces in them. So yeah,
 	// redo this in the future.
 	fn parse_path_glob(lctx: &mut LexContext) -> Result<Option<Self>, ParseError> {
@@ -585,9 +644,11 @@ impl Token {
 	fn parse_normal(lctx: &mut LexContext) -> Result<Option<Self>, ParseError> {
 		// remove whitespace
 		lctx.stream.strip_whitespace_and_comments();
+		lctx.span.start = lctx.stream.index();
 
+		// Trailing whitespace and comments are allowed at the end of the source.
 		if lctx.stream.is_eof() {
-			return Err(ParseError::Eof);
+			return Ok(None);
 		}
 
 		if let Some(pathglob) = Self::parse_path_glob(lctx)? {
@@ -626,6 +687,9 @@ impl Token {
 			b'}' => Ok(Some(Self::EndBraceEscape)),
 			b'(' => Ok(Some(Self::LeftParen)),
 			b')' => Ok(Some(Self::RightParen)),
+			b'[' => Ok(Some(Self::LeftBracket)),
+			b']' => Ok(Some(Self::RightBracket)),
+			b'{' => Ok(Some(Self::LeftBrace)),
 
 			// Control Characters
 			b'?' => Ok(Some(Self::Question)), // TODO: this can conflict with `?/`
@@ -682,6 +746,7 @@ impl Token {
 					b"else" => Ok(Some(Self::Else)),
 					b"while" => Ok(Some(Self::While)),
 					b"for" => Ok(Some(Self::For)),
+					b"in" => Ok(Some(Self::In)),
 					b"break" => Ok(Some(Self::Break)),
 					b"continue" => Ok(Some(Self::Continue)),
 					b"return" => Ok(Some(Self::Return)),
@@ -696,6 +761,9 @@ impl Token {
 			}
 			x if x.is_ascii_digit() => {
 				lctx.stream.untake();
+				if let Some(datetime) = lctx.stream.parse_datetime()? {
+					return Ok(Some(Self::DateTime(datetime)));
+				}
 				Self::parse_number(lctx).map(Some)
 			}
 
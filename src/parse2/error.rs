@@ -1,31 +1,38 @@
+use crate::parse::{Diagnostic, ParseError};
 use crate::parse2::stream::SourcePosition;
+use std::ops::Range;
 
+/// A [`ParseError`], along with where in the source it happened.
 #[derive(Debug)]
 pub struct Error {
 	whence: SourcePosition,
-	kind: ErrorKind,
+	span: Range<usize>,
+	kind: ParseError,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 impl Error {
-	pub fn new(kind: ErrorKind, whence: SourcePosition) -> Self {
-		Self { kind, whence }
+	pub fn new(kind: ParseError, whence: SourcePosition, span: Range<usize>) -> Self {
+		Self { whence, span, kind }
 	}
 
 	pub fn whence(&self) -> SourcePosition {
 		self.whence
 	}
 
-	pub fn kind(&self) -> &ErrorKind {
+	/// The bytes of the source the error is about.
+	pub fn span(&self) -> Range<usize> {
+		self.span.clone()
+	}
+
+	pub fn kind(&self) -> &ParseError {
 		&self.kind
 	}
-}
 
-#[derive(Debug)]
-pub enum ErrorKind {
-	UnterminatedString,
-	UnterminatedRegex,
-	InvalidEscape,
-	IntegerLiteralTooLarge,
+	/// Converts `self` into a [`Diagnostic`] which points into `source`, which must be the source
+	/// that was being parsed.
+	pub fn diagnose(self, source: &str) -> Diagnostic {
+		Diagnostic::new(self.kind).with_snippet(source.as_bytes(), self.span)
+	}
 }
@@ -0,0 +1,504 @@
+//! Parsing tokens into the AST.
+
+use crate::ast::{
+	Atom, Block, ExprKind, Expression, Interpolated, LogicOperator, MathOperator, Precedence,
+	Script, ShortCircuit,
+};
+use crate::parse::ParseError;
+use crate::parse2::{Parser, Result, Token};
+use crate::Value;
+use os_str_bytes::OsStrBytes;
+
+/// What a binary operator does with its operands.
+enum Operator {
+	Assign(Option<MathOperator>),
+	ShortCircuitAssign(ShortCircuit),
+	Math(MathOperator),
+	Logic(LogicOperator),
+	ShortCircuit(ShortCircuit),
+}
+
+impl Operator {
+	fn of(token: &Token, comma_is_and: bool) -> Option<(Self, Precedence)> {
+		let (op, prec) = match token {
+			Token::Add => (Self::Math(MathOperator::Add), Precedence::AddSub),
+			Token::Subtract => (Self::Math(MathOperator::Subtract), Precedence::AddSub),
+			Token::Multiply => (Self::Math(MathOperator::Multiply), Precedence::MulDivMod),
+			Token::Divide => (Self::Math(MathOperator::Divide), Precedence::MulDivMod),
+			Token::Modulo => (Self::Math(MathOperator::Modulo), Precedence::MulDivMod),
+
+			Token::Assign => (Self::Assign(None), Precedence::Assignment),
+			Token::AddAssign => (Self::Assign(Some(MathOperator::Add)), Precedence::Assignment),
+			Token::SubtractAssign => {
+				(Self::Assign(Some(MathOperator::Subtract)), Precedence::Assignment)
+			}
+			Token::MultiplyAssign => {
+				(Self::Assign(Some(MathOperator::Multiply)), Precedence::Assignment)
+			}
+			Token::DivideAssign => (Self::Assign(Some(MathOperator::Divide)), Precedence::Assignment),
+			Token::ModuloAssign => (Self::Assign(Some(MathOperator::Modulo)), Precedence::Assignment),
+			Token::AndAndAssign => {
+				(Self::ShortCircuitAssign(ShortCircuit::And), Precedence::Assignment)
+			}
+			Token::OrOrAssign => (Self::ShortCircuitAssign(ShortCircuit::Or), Precedence::Assignment),
+
+			Token::Matches => (Self::Logic(LogicOperator::Matches), Precedence::Logic),
+			Token::NotMatches => (Self::Logic(LogicOperator::NotMatches), Precedence::Logic),
+			Token::Equal => (Self::Logic(LogicOperator::Equal), Precedence::Logic),
+			Token::NotEqual => (Self::Logic(LogicOperator::NotEqual), Precedence::Logic),
+			Token::LessThan => (Self::Logic(LogicOperator::LessThan), Precedence::Logic),
+			Token::LessThanOrEqual => (Self::Logic(LogicOperator::LessThanOrEqual), Precedence::Logic),
+			Token::GreaterThan => (Self::Logic(LogicOperator::GreaterThan), Precedence::Logic),
+			Token::GreaterThanOrEqual => {
+				(Self::Logic(LogicOperator::GreaterThanOrEqual), Precedence::Logic)
+			}
+
+			Token::AndAnd => (Self::ShortCircuit(ShortCircuit::And), Precedence::ShortCircuit),
+			Token::OrOr => (Self::ShortCircuit(ShortCircuit::Or), Precedence::ShortCircuit),
+			Token::Comma if comma_is_and => (Self::ShortCircuit(ShortCircuit::And), Precedence::Comma),
+			_ => return None,
+		};
+
+		Some((op, prec))
+	}
+}
+
+impl<'a> Parser<'a> {
+	/// Converts errors from creating AST nodes into [`Error`](crate::parse2::Error)s about the
+	/// most recent token.
+	fn spanned<T>(&self, result: std::result::Result<T, ParseError>) -> Result<T> {
+		result.map_err(|err| self.error(err))
+	}
+
+	/// Parses an entire script, along with any `^{ ... }` and `${ ... }` blocks.
+	pub fn parse_script(&mut self) -> Result<Script> {
+		let mut begin = Vec::new();
+		let mut exprs = Vec::new();
+		let mut end = Vec::new();
+
+		// Toplevel expressions can be separated by `;`s, but they're optional, which allows for
+		// files of function declarations to be written one per line. Begin and end blocks can be
+		// interspersed anywhere, and are run in the order they're given.
+		loop {
+			let start = self.next_start()?;
+			if self.take_if(&Token::BeginBlock)? {
+				let block = Atom::Block(self.parse_block(&Token::RightBrace)?);
+				begin.push(Expression::new(ExprKind::Atom(block), self.span_from(start)));
+			} else if self.take_if(&Token::EndBlock)? {
+				let block = Atom::Block(self.parse_block(&Token::RightBrace)?);
+				end.push(Expression::new(ExprKind::Atom(block), self.span_from(start)));
+			} else if let Some(expr) = self.parse_expression(true, Precedence::default())? {
+				exprs.push(expr);
+			} else {
+				break;
+			}
+
+			let _ = self.take_if(&Token::Semicolon)?;
+		}
+
+		if self.next()?.is_some() {
+			return Err(self.error(ParseError::UnexpectedToken));
+		}
+
+		self.spanned(Script::new(begin, exprs, end))
+	}
+
+	/// Parses an entire program which can't have `^{ ... }` or `${ ... }` blocks.
+	pub fn parse_toplevel(&mut self) -> Result<Expression> {
+		match self.parse_script()? {
+			Script { begin, main: Some(main), end } if begin.is_empty() && end.is_empty() => Ok(main),
+			_ => Err(self.error(ParseError::Message("begin and end blocks aren't allowed here"))),
+		}
+	}
+
+	/// Parses `;`-separated expressions, up to and including `end`.
+	fn parse_block(&mut self, end: &Token) -> Result<Block> {
+		let mut exprs = Vec::new();
+
+		while let Some(expr) = self.parse_expression(true, Precedence::default())? {
+			exprs.push(expr);
+			if !self.take_if(&Token::Semicolon)? {
+				break;
+			}
+		}
+
+		if !self.take_if(end)? {
+			return Err(self.error(ParseError::NoClosingRightParen));
+		}
+
+		Ok(Block::new(exprs))
+	}
+
+	fn parse_single(&mut self, msg: &'static str) -> Result<Expression> {
+		self
+			.parse_expression(true, Precedence::default())?
+			.ok_or_else(|| self.error(ParseError::Message(msg)))
+	}
+
+	fn expect(&mut self, token: &Token, msg: &'static str) -> Result<()> {
+		if self.take_if(token)? {
+			Ok(())
+		} else {
+			Err(self.error(ParseError::Message(msg)))
+		}
+	}
+
+	fn parse_statement(&mut self) -> Result<Option<Expression>> {
+		let start = self.next_start()?;
+		let Some(token) = self.take_if_fn(|token| {
+			matches!(
+				token,
+				Token::If
+					| Token::While
+					| Token::For
+					| Token::Continue
+					| Token::Break
+					| Token::Return
+					| Token::Fn
+			)
+		})?
+		else {
+			return Ok(None);
+		};
+
+		let kind = match token {
+			Token::If => {
+				let cond = self.parse_single("missing condition for if")?;
+				self.expect(&Token::Do, "expected `do` after `if` condition")?;
+				let body = self.parse_single("missing body for if")?;
+
+				let mut conds = vec![(cond, body)];
+				while self.take_if(&Token::Elif)? {
+					let elif_cond = self.parse_single("missing condition for elif")?;
+					self.expect(&Token::Do, "expected `do` after `elif` condition")?;
+					conds.push((elif_cond, self.parse_single("missing body for elif")?));
+				}
+
+				let else_body = if self.take_if(&Token::Else)? {
+					Some(self.parse_single("missing body for else")?)
+				} else {
+					None
+				};
+
+				ExprKind::If(conds, else_body.map(From::from))
+			}
+
+			Token::While => {
+				let cond = self.parse_single("missing condition for while")?;
+				self.expect(&Token::Do, "expected `do` after `while` condition")?;
+				let body = self.parse_single("missing body for while")?;
+
+				ExprKind::While(cond.into(), body.into())
+			}
+
+			Token::For => {
+				let var = self
+					.take_ident()?
+					.ok_or_else(|| self.error(ParseError::Message("expected variable name")))?;
+				self.expect(&Token::In, "expected `in` after `for` variable")?;
+				let iterable = self.parse_single("missing iterable for for")?;
+				self.expect(&Token::Do, "expected `do` after `for` iterable")?;
+				let body = self.parse_single("missing body for for")?;
+
+				ExprKind::For(var, iterable.into(), body.into())
+			}
+
+			Token::Continue => ExprKind::Continue,
+			Token::Break => ExprKind::Break,
+			Token::Return => {
+				ExprKind::Return(self.parse_expression(true, Precedence::default())?.map(From::from))
+			}
+
+			Token::Fn => {
+				let name = self.take_ident()?;
+				self.expect(&Token::LeftParen, "expected `(` after fn name")?;
+
+				let mut args = Vec::new();
+				while !self.take_if(&Token::RightParen)? {
+					let ident = self
+						.take_ident()?
+						.ok_or_else(|| self.error(ParseError::Message("expected variable name")))?;
+					args.push(ident);
+
+					if !self.take_if(&Token::Comma)? {
+						self.expect(&Token::RightParen, "expected `,` or `)` after variable")?;
+						break;
+					}
+				}
+
+				let body = self.parse_single("missing body for fn")?;
+				ExprKind::FnDecl(name, args, body.into())
+			}
+
+			_ => unreachable!(),
+		};
+
+		Ok(Some(Expression::new(kind, self.span_from(start))))
+	}
+
+	/// Parses an expression whose binary operators bind at most as loosely as `prec`. When
+	/// `comma_is_and` is set, `,` is a loosely-binding `&&`.
+	pub fn parse_expression(
+		&mut self,
+		comma_is_and: bool,
+		prec: Precedence,
+	) -> Result<Option<Expression>> {
+		if let Some(statement) = self.parse_statement()? {
+			return Ok(Some(statement));
+		}
+
+		let start = self.next_start()?;
+		let Some(begin) = self.parse_atom()? else {
+			return Ok(None);
+		};
+
+		let mut lhs = Expression::new(ExprKind::Atom(begin), self.span_from(start));
+
+		while let Some(token) = self.next()? {
+			let (op, token_prec) = match Operator::of(&token, comma_is_and) {
+				Some((op, token_prec)) if token_prec <= prec => (op, token_prec),
+				_ => {
+					self.push_back(token);
+					break;
+				}
+			};
+
+			let rhs = self
+				.parse_expression(comma_is_and, token_prec)?
+				.ok_or_else(|| self.error(ParseError::MissingRhsToOp))?;
+
+			let span = lhs.span.start..rhs.span.end;
+			let kind = match op {
+				Operator::Assign(math) => {
+					lhs = self.spanned(Expression::assignment(lhs, math, rhs))?;
+					continue;
+				}
+				Operator::ShortCircuitAssign(cond) => match lhs.kind {
+					ExprKind::Atom(Atom::Variable(var)) => {
+						ExprKind::ShortCircuitAssignment(var, cond, rhs.into())
+					}
+					_ => return Err(self.error(ParseError::AssignToNonVariable)),
+				},
+				Operator::Math(math) => ExprKind::Math(math, lhs.into(), rhs.into()),
+				Operator::Logic(logic) => ExprKind::Logic(logic, lhs.into(), rhs.into()),
+				Operator::ShortCircuit(cond) => ExprKind::ShortCircuit(cond, lhs.into(), rhs.into()),
+			};
+			lhs = Expression::new(kind, span);
+		}
+
+		Ok(Some(lhs))
+	}
+
+	/// Parses the rest of a string, regex, or path, up to and including the token `is_end`
+	/// matches.
+	fn parse_interpolated(
+		&mut self,
+		is_end: impl Fn(&Token) -> bool,
+	) -> Result<(Interpolated, Token<'a>)> {
+		let mut parts = Vec::new();
+		let mut current = Vec::new();
+
+		loop {
+			match self.next()?.expect("the lexer errors on unterminated literals") {
+				Token::CliArg(pos) => current.extend_from_slice(&self.get_cli(pos)?.to_raw_bytes()),
+				Token::EnvVar(var) => current.extend_from_slice(&self.get_env(var)?.to_raw_bytes()),
+				Token::RawData(data) => current.extend_from_slice(&data),
+				Token::StartInterpolation => {
+					let block = self.parse_block(&Token::EndInterpolation)?;
+					parts.push((std::mem::take(&mut current), block));
+				}
+				token if is_end(&token) => return Ok((Interpolated { parts, tail: current }, token)),
+				token => unreachable!("invalid token in interpolation: {token:?}"),
+			}
+		}
+	}
+
+	/// Parses `atom`, followed by a single atom.
+	fn parse_unary(&mut self, atom: fn(Box<Atom>) -> Atom) -> Result<Atom> {
+		let operand =
+			self.parse_atom()?.ok_or_else(|| self.error(ParseError::NotAndEndOfExpression))?;
+		Ok(atom(Box::new(operand)))
+	}
+
+	/// Parses a comma-separated list of expressions, up to and including `end`.
+	fn parse_list(&mut self, end: &Token, missing_end: ParseError) -> Result<Vec<Expression>> {
+		let mut elements = Vec::new();
+
+		while let Some(expr) = self.parse_expression(false, Precedence::default())? {
+			elements.push(expr);
+			if !self.take_if(&Token::Comma)? {
+				break;
+			}
+		}
+
+		if !self.take_if(end)? {
+			return Err(self.error(missing_end));
+		}
+
+		Ok(elements)
+	}
+
+	/// Parses `{key: value, ...}`, after the `{`.
+	fn parse_assoc_array(&mut self) -> Result<Atom> {
+		let mut pairs = Vec::new();
+
+		while let Some(key) = self.parse_expression(false, Precedence::default())? {
+			self.expect(&Token::Colon, "expected `:` after key")?;
+
+			let value = self
+				.parse_expression(false, Precedence::default())?
+				.ok_or_else(|| self.error(ParseError::Message("missing value for key")))?;
+			pairs.push((key, value));
+
+			if !self.take_if(&Token::Comma)? {
+				break;
+			}
+		}
+
+		self.expect(&Token::RightBrace, "expected `}` after assoc array elements")?;
+		Ok(Atom::AssocArray(pairs))
+	}
+
+	/// Parses any function calls (`foo(1)`) and indexing (`foo[1]`) after `atom`.
+	fn parse_postfix(&mut self, mut atom: Atom) -> Result<Atom> {
+		loop {
+			if self.take_if(&Token::LeftParen)? {
+				let args = self.parse_list(&Token::RightParen, ParseError::NoClosingRightParen)?;
+				atom = Atom::FnCall(Box::new(atom), args);
+			} else if self.take_if(&Token::LeftBracket)? {
+				let key = self
+					.parse_expression(false, Precedence::default())?
+					.ok_or_else(|| self.error(ParseError::Message("missing index")))?;
+				self.expect(&Token::RightBracket, "expected `]` after index")?;
+
+				atom = Atom::Index(Box::new(atom), Box::new(key));
+			} else {
+				return Ok(atom);
+			}
+		}
+	}
+
+	fn parse_atom(&mut self) -> Result<Option<Atom>> {
+		let atom = match self.next()? {
+			Some(Token::StartPath) => {
+				let (interpolated, _) = self.parse_interpolated(|t| *t == Token::EndPath)?;
+				self.spanned(Atom::path(interpolated))?
+			}
+			Some(Token::StartString) => {
+				Atom::string(self.parse_interpolated(|t| *t == Token::EndString)?.0)
+			}
+			Some(Token::StartRegex) => {
+				let (interpolated, flags) =
+					self.parse_interpolated(|t| matches!(t, Token::EndRegex(_)))?;
+				let Token::EndRegex(flags) = flags else { unreachable!() };
+				self.spanned(Atom::regex(interpolated, flags.as_bytes().to_vec()))?
+			}
+
+			Some(Token::Question) => self.parse_unary(Atom::ForcedLogical)?,
+			Some(Token::Not) => self.parse_unary(Atom::Not)?,
+			Some(Token::Subtract) => self.parse_unary(Atom::Negate)?,
+			Some(Token::Add) => self.parse_unary(Atom::UPositive)?,
+
+			Some(Token::LeftParen) => {
+				let block = self.parse_block(&Token::RightParen)?;
+				self.parse_postfix(Atom::Block(block))?
+			}
+			Some(Token::LeftBracket) => {
+				let elements = self.parse_list(
+					&Token::RightBracket,
+					ParseError::Message("expected `]` after array elements"),
+				)?;
+				self.parse_postfix(Atom::Array(elements))?
+			}
+			Some(Token::LeftBrace) => {
+				let assoc_array = self.parse_assoc_array()?;
+				self.parse_postfix(assoc_array)?
+			}
+			Some(Token::Identifier(var)) => self.parse_postfix(Atom::Variable(var.to_owned()))?,
+
+			Some(Token::Number(num)) => Atom::Value(Value::Number(num)),
+			Some(Token::FileSize { fs, precision }) => Atom::Value(Value::FileSize { fs, precision }),
+			Some(Token::DateTime(dt)) => Atom::Value(Value::DateTime(dt)),
+			Some(Token::CliArg(pos)) => {
+				Atom::Value(Value::Text(self.get_cli(pos)?.to_raw_bytes().into_owned().into()))
+			}
+			Some(Token::EnvVar(var)) => {
+				Atom::Value(Value::Text(self.get_env(var)?.to_raw_bytes().into_owned().into()))
+			}
+			Some(Token::True) => Atom::Value(true.into()),
+			Some(Token::False) => Atom::Value(false.into()),
+
+			Some(other) => {
+				self.push_back(other);
+				return Ok(None);
+			}
+			None => return Ok(None),
+		};
+
+		Ok(Some(atom))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::ast::{Atom, ExprKind, Expression};
+	use crate::parse2::Parser;
+	use crate::play::Env;
+
+	/// The source that each expression in `expr` spans, outermost first.
+	fn spans<'a>(source: &'a str, expr: &Expression, spanned: &mut Vec<&'a str>) {
+		spanned.push(&source[expr.span.clone()]);
+		match &expr.kind {
+			ExprKind::Math(_, lhs, rhs)
+			| ExprKind::Logic(_, lhs, rhs)
+			| ExprKind::ShortCircuit(_, lhs, rhs)
+			| ExprKind::While(lhs, rhs) => {
+				spans(source, lhs, spanned);
+				spans(source, rhs, spanned);
+			}
+			ExprKind::Assignment(_, _, value) | ExprKind::FnDecl(_, _, value) => {
+				spans(source, value, spanned);
+			}
+			ExprKind::If(conds, else_body) => {
+				for (cond, body) in conds {
+					spans(source, cond, spanned);
+					spans(source, body, spanned);
+				}
+				if let Some(else_body) = else_body {
+					spans(source, else_body, spanned);
+				}
+			}
+			ExprKind::Atom(Atom::Array(exprs)) => {
+				for expr in exprs {
+					spans(source, expr, spanned);
+				}
+			}
+			_ => {}
+		}
+	}
+
+	fn parse(source: &str) -> Vec<&str> {
+		let script = Parser::new(source, &Env::default()).parse_script().unwrap();
+		let mut spanned = Vec::new();
+		for expr in script.begin.iter().chain(&script.main).chain(&script.end) {
+			spans(source, expr, &mut spanned);
+		}
+		spanned
+	}
+
+	#[test]
+	fn spans_cover_their_source() {
+		assert_eq!(vec!["1 + 2 * 3", "1", "2 * 3", "2", "3"], parse("  1 + 2 * 3  "));
+		assert_eq!(vec!["x = [a, b]", "[a, b]", "a", "b"], parse("x = [a, b]"));
+		assert_eq!(vec![r#""a{b}c" =~ $/x/i"#, r#""a{b}c""#, "$/x/i"], parse(r#""a{b}c" =~ $/x/i"#));
+		assert_eq!(
+			vec!["if a do (b; c) else d", "a", "(b; c)", "d"],
+			parse("if a do (b; c) else d # comment")
+		);
+		assert_eq!(vec!["while i do i", "i", "i"], parse("while i do i"));
+		assert_eq!(vec!["fn f(a) a", "a"], parse("fn f(a) a"));
+
+		// Multiple toplevel expressions are a block, which spans all of them.
+		assert_eq!(vec!["^{ a }", "b; c", "${ d }"], parse("^{ a } b; c ${ d }"));
+	}
+}
@@ -1,12 +1,24 @@
-#![allow(unused)]
+//! The parser for source code, which lexes and parses in one go.
+//!
+//! The `parse` module has the [`ParseError`](crate::parse::ParseError)s and
+//! [`Diagnostic`](crate::parse::Diagnostic)s this uses. It also has the original lexer, which is
+//! only compiled for tests, where the `parity` tests check that both front ends agree.
+//!
+//! Every [`Expression`](crate::ast::Expression) records the bytes of the source it was parsed
+//! from, like errors do. When the optimizer rewrites an expression, the result keeps the span of
+//! what it replaced.
 
 mod error;
+mod grammar;
 mod parser;
 mod stream;
 mod token;
 
-pub use parser::Parser;
-pub use stream::Stream;
+#[cfg(test)]
+mod parity;
+
+pub use parser::{Parser, Phase};
+pub use stream::{SourcePosition, Stream};
 pub use token::Token;
 
-pub use error::{Error, ErrorKind, Result};
+pub use error::{Error, Result};
@@ -0,0 +1,233 @@
+//! Checks that [`Parser`] produces the same trees as the original `parse` front end.
+
+use crate::ast::{Atom, ExprKind, Expression, Script, ShortCircuit};
+use crate::parse::{LexContext, ParseError};
+use crate::parse2::Parser;
+use crate::play::program::Config;
+use crate::play::{Env, Program};
+use crate::Value;
+
+fn program() -> Program {
+	let args = ["first", "second arg", "third"];
+	Program::new(Config::default(), Env::new(args.into_iter().map(Into::into).collect()))
+}
+
+fn parse_old(source: &str) -> Result<Script, ParseError> {
+	let mut program = program();
+	let mut lctx = LexContext::new(source, &mut program);
+	Script::parse(&mut lctx)
+}
+
+fn parse_new(source: &str) -> Script {
+	let program = program();
+	Parser::new(source, program.env())
+		.parse_script()
+		.unwrap_or_else(|err| panic!("{source:?} failed with parse2: {}", err.kind()))
+}
+
+const CORPUS: &[&str] = &[
+	// Operators
+	"file?",
+	"print(path)",
+	"1 + 2 * 3 - 4",
+	"2 * 3 + 4 div 5 mod 6",
+	"a = 1; a += 2; a -= 3; a *= 4; a div= 5; a mod= 6; a",
+	"a == b != c",
+	"a < b <= c > d >= e",
+	"a && b || c, d",
+	"a = b = c",
+	"-1 + +2",
+	"!a && ?b && !!c",
+	"true || false",
+	// Strings
+	r#""hello""#,
+	r#""""#,
+	r#""a{1 + 2}b{x}""#,
+	r#""$1 and ${2} and $-1 and ${-2}""#,
+	r#""escapes: \n\t\r\0\\\"\'\$\{ \x41 A""#,
+	r#""$HOME/{name}""#,
+	r#""comma, semicolon; paren) pipe| and#hash""#,
+	// Regexes
+	"a =~ $/fo+/i",
+	"a !~ $/x{ y }z$/",
+	"$/$HOME/",
+	r"$/a\/b\d\{\$/",
+	"$/^${1}$/m",
+	// Paths
+	"./src",
+	"*.rs",
+	"+.rs",
+	"~/foo",
+	"/usr/bin",
+	"src/**/*.{rs,toml}",
+	"a/b${ x }c/d",
+	"./$1/${2}",
+	r"./foo\*\?\$x",
+	"!~/x",
+	"src/ && tests/ || benches/",
+	"../up",
+	"?/anywhere",
+	// Numbers
+	"1.500",
+	"1_000",
+	"0x1F",
+	"0o17",
+	"0b101",
+	"0b",
+	"1e3",
+	"1.5e-2",
+	"10kb",
+	"1.50mb",
+	"2024-01-02",
+	"2024-01-02T03:04",
+	"2024-01-02t03:04:05",
+	// Statements
+	"if a do b elif c do d else e",
+	"if a do b",
+	"while i < 3 do i += 1",
+	"for x in [1, 2, 3] do print(x)",
+	"fn add(a, b) a + b",
+	"fn (x) x",
+	"fn f() return",
+	"fn g(a,) return a",
+	"while true do (break; continue)",
+	"return 3",
+	// Arrays, calls, and indexing
+	r#"[1, [2, 3], {"a": 1, b: 2}]"#,
+	"[]",
+	"{}",
+	"x[0] = 1",
+	r#"x["a"] += 2"#,
+	"f(1, 2)(3)[4]",
+	"(1; 2; 3)",
+	"()",
+	"[a, b](c)",
+	// Scripts
+	"^{ n = 0 } file? && (n += 1) ${ print(n) }",
+	"^{ a };^{ b }; ${ c }",
+	"${ done }",
+	"a; b; c",
+	"a b c",
+	"# comment\n a # trailing comment\n",
+	"$1",
+	"$-1",
+	"$HOME",
+	"${HOME} ${1}",
+];
+
+const ERRORS: &[&str] = &[
+	"",
+	"# just a comment",
+	"1 -",
+	r#""abc"#,
+	"$/abc",
+	"(1",
+	"[1",
+	"{a: 1",
+	"{a 1}",
+	"x[1",
+	"f(1",
+	"if 1 then 2",
+	"if 1 do",
+	"for 1 in 2 do 3",
+	"for x of 2 do 3",
+	"fn f(1) 2",
+	"fn f a",
+	"1 = 2",
+	"$9",
+	"$0",
+	"$FINDFILE_PARITY_UNSET",
+	r#""\q""#,
+	r#""\xZZ""#,
+	"12xyz",
+	"1.",
+	"0x",
+	"1e",
+	"2024-13-01",
+	"1 )",
+	"$/(/",
+	"!",
+	"`",
+	"${-}",
+	"${1",
+];
+
+// Interpolations which the original front end needed spaces around, paired with how it had to be
+// written there.
+const UNSPACED: &[(&str, &str)] = &[
+	("$/{x}/", "$/{ x }/"),
+	("$/a{x}b/i", "$/a{ x }b/i"),
+	(r#""{./x}""#, r#""{ ./x }""#),
+	(r#""{x}{y}""#, r#""{ x }{ y }""#),
+	("a/${x}b", "a/${ x }b"),
+	("[{a: b/c}]", "[{ a: b/c }]"),
+	("{a: ./b}", "{ a: ./b }"),
+];
+
+#[test]
+fn same_trees() {
+	for source in CORPUS {
+		let old = parse_old(source).unwrap_or_else(|err| panic!("{source:?} failed: {err}"));
+		assert_eq!(old, parse_new(source), "{source:?} parsed differently");
+	}
+}
+
+#[test]
+fn same_trees_without_spaces() {
+	for (unspaced, spaced) in UNSPACED {
+		let old = parse_old(spaced).unwrap_or_else(|err| panic!("{spaced:?} failed: {err}"));
+		assert_eq!(old, parse_new(unspaced), "{unspaced:?} parsed differently to {spaced:?}");
+	}
+}
+
+#[test]
+fn same_errors() {
+	let program = program();
+
+	for source in ERRORS {
+		let old = parse_old(source).expect_err(source).to_string();
+		let new = Parser::new(source, program.env()).parse_script().expect_err(source);
+		assert_eq!(old, new.kind().to_string(), "{source:?} failed differently");
+	}
+}
+
+// Things the original front end got wrong or couldn't parse.
+#[test]
+fn improvements() {
+	let program = program();
+	let parse =
+		|source| Parser::new(source, program.env()).parse_script().unwrap().main.unwrap().kind;
+
+	// `{}`s within interpolations used to end the interpolation.
+	let ExprKind::Atom(Atom::InterpolatedString(interpolated)) = parse(r#""{ {a: 1}["a"] }!""#)
+	else {
+		panic!("not an interpolated string");
+	};
+	assert_eq!(b"!", &interpolated.tail[..]);
+
+	// `&&=` and `||=` are assignments, rather than `&&` followed by `=`.
+	assert_eq!(
+		ExprKind::ShortCircuitAssignment(
+			"a".into(),
+			ShortCircuit::Or,
+			Box::new(Expression::from(ExprKind::Atom(Atom::Value(Value::Number(1.0)))))
+		),
+		parse("a ||= 1")
+	);
+
+	// Top-level `${` always used to start an end block. Now it's a path when something directly
+	// follows the `}`, and an end block otherwise.
+	let script = Parser::new("${x}/*.txt", program.env()).parse_script().unwrap();
+	assert!(matches!(script.main.unwrap().kind, ExprKind::Atom(Atom::InterpolatedPath(_))));
+	assert!(script.end.is_empty());
+	for source in ["${x}", "${x} /*.txt", "${x};y", "a ${ {b: 1} }"] {
+		let script = Parser::new(source, program.env()).parse_script().unwrap();
+		assert_eq!(1, script.end.len(), "{source:?}");
+	}
+
+	// `\u` escapes are UTF-8 encoded, rather than panicking for anything past `ÿ`.
+	assert_eq!(
+		ExprKind::Atom(Atom::Value(Value::Text("é❦".as_bytes().to_vec().into()))),
+		parse(r#""\u00e9\u2766""#)
+	);
+}
@@ -1,21 +1,47 @@
-// use crate::parse2::token::{Operator, Parsable, Primary};
-use crate::parse2::{Result, Stream, Token};
+use crate::parse::ParseError;
+use crate::parse2::{Error, Result, Stream, Token};
+use crate::play::Env;
+use std::ffi::{OsStr, OsString};
+use std::ops::Range;
 
+/// What kind of token the lexer is within, which changes how the source is lexed.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Phase {
 	Text,
 	Regex,
 	Path,
+	Interpolation, // `{ ... }` within strings and regexes, or `${ ... }` within paths
+	Braces,        // `{ ... }`, `^{ ... }`, and `${ ... }` outside of literals
 }
 
+/// Parses source code into a [`Script`](crate::ast::Script).
+///
+/// `$1` and `$HOME` are replaced with their values while parsing, which is why an [`Env`] is
+/// needed.
 pub struct Parser<'a> {
 	stream: Stream<'a>,
 	phases: Vec<Phase>,
+	env: &'a Env,
+	pushed_back: Option<(Token<'a>, usize)>,
+	token_start: usize, // where the most recently lexed token starts, for errors.
+	// Where the most recently taken token ends, and where the one before it did, which is restored
+	// when it's pushed back (along with its end). Expressions span up to the end of their last
+	// token.
+	token_end: usize,
+	prev_token_end: usize,
 }
 
 impl<'a> Parser<'a> {
-	pub fn new(source: &'a str) -> Self {
-		Self { stream: Stream::new(source), phases: vec![] }
+	pub fn new(source: &'a str, env: &'a Env) -> Self {
+		Self {
+			stream: Stream::new(source),
+			phases: vec![],
+			env,
+			pushed_back: None,
+			token_start: 0,
+			token_end: 0,
+			prev_token_end: 0,
+		}
 	}
 
 	pub fn phase(&self) -> Option<Phase> {
@@ -26,6 +52,11 @@ impl<'a> Parser<'a> {
 		&self.stream
 	}
 
+	/// Whether there's an enclosing `{`, such as an interpolation or block.
+	pub fn is_within_braces(&self) -> bool {
+		self.phases.iter().any(|phase| matches!(phase, Phase::Interpolation | Phase::Braces))
+	}
+
 	pub fn enter_phase(&mut self, phase: Phase) {
 		self.phases.push(phase);
 	}
@@ -39,7 +70,90 @@ impl<'a> Parser<'a> {
 		&mut self.stream
 	}
 
-	// pub fn next<T: Parsable<'a>>(&mut self) -> Result<Option<Token<'a, T>>> {
-	// 	Token::parse(self)
-	// }
+	/// Marks the start of the token that's being lexed, after any leading whitespace.
+	pub(super) fn start_token(&mut self) {
+		self.token_start = self.stream.index();
+	}
+
+	/// Creates a `kind` [`Error`] that's about the most recently lexed token (or the one that's
+	/// being lexed), which is what every error is about.
+	pub fn error(&self, kind: ParseError) -> Error {
+		let span = self.token_start..self.stream.index();
+		Error::new(kind, self.stream.source_position_at(span.start), span)
+	}
+
+	/// Where the next token starts, or the end of the source if there aren't any more.
+	pub fn next_start(&mut self) -> Result<usize> {
+		match self.next()? {
+			Some(token) => {
+				self.push_back(token);
+				Ok(self.token_start)
+			}
+			None => Ok(self.stream.index()),
+		}
+	}
+
+	/// The bytes of the source from `start` up to the end of the most recently taken token.
+	pub fn span_from(&self, start: usize) -> Range<usize> {
+		start..self.token_end
+	}
+
+	/// Gets the next token, or `None` if the source is empty.
+	#[allow(clippy::should_implement_trait)]
+	pub fn next(&mut self) -> Result<Option<Token<'a>>> {
+		if let Some((token, end)) = self.pushed_back.take() {
+			self.prev_token_end = std::mem::replace(&mut self.token_end, end);
+			return Ok(Some(token));
+		}
+
+		self.token_start = self.stream.index();
+		let token = Token::parse(self)?;
+		if token.is_some() {
+			self.prev_token_end = std::mem::replace(&mut self.token_end, self.stream.index());
+		}
+		Ok(token)
+	}
+
+	/// Undoes the last [`Parser::next`]; `token` must be the token it returned.
+	pub fn push_back(&mut self, token: Token<'a>) {
+		debug_assert!(self.pushed_back.is_none(), "only one token can be pushed back");
+		self.pushed_back = Some((token, self.token_end));
+		self.token_end = self.prev_token_end;
+	}
+
+	/// Takes the next token if it's `token`, returning whether it was taken.
+	pub fn take_if(&mut self, token: &Token) -> Result<bool> {
+		Ok(self.take_if_fn(|next| next == token)?.is_some())
+	}
+
+	/// Takes the next token if `cond` returns true for it.
+	pub fn take_if_fn(&mut self, cond: impl FnOnce(&Token) -> bool) -> Result<Option<Token<'a>>> {
+		match self.next()? {
+			Some(token) if cond(&token) => Ok(Some(token)),
+			Some(token) => {
+				self.push_back(token);
+				Ok(None)
+			}
+			None => Ok(None),
+		}
+	}
+
+	/// Takes the next token if it's an identifier.
+	pub fn take_ident(&mut self) -> Result<Option<String>> {
+		match self.take_if_fn(|token| matches!(token, Token::Identifier(_)))? {
+			Some(Token::Identifier(ident)) => Ok(Some(ident.to_owned())),
+			_ => Ok(None),
+		}
+	}
+
+	/// Gets the cli argument `$pos`.
+	pub fn get_cli(&self, pos: isize) -> Result<&'a OsStr> {
+		self.env.get_cli_arg(pos).ok_or_else(|| self.error(ParseError::InvalidCliPosition(pos)))
+	}
+
+	/// Gets the environment variable `$name`.
+	pub fn get_env(&self, name: &str) -> Result<OsString> {
+		let name = OsStr::new(name);
+		self.env.get_env(name).ok_or_else(|| self.error(ParseError::MissingEnvVar(name.to_owned())))
+	}
 }
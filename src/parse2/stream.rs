@@ -1,4 +1,5 @@
-use crate::parse2::error::{Error, ErrorKind};
+use crate::parse::ParseError;
+use crate::parse2::Error;
 use std::fmt::{self, Display, Formatter};
 use std::str::Chars;
 
@@ -55,20 +56,34 @@ impl<'a> Stream<'a> {
 		self.peek().is_none()
 	}
 
+	/// Returns the entire source, including what's already been taken.
+	#[must_use]
+	pub fn source(&self) -> &'a str {
+		self.source
+	}
+
+	/// Returns how many bytes have been taken.
+	#[must_use]
+	pub fn index(&self) -> usize {
+		self.source.len() - self.chars.as_str().len()
+	}
+
 	/// Returns a tuple of `(current line, current column)`.
 	#[must_use]
 	pub fn source_position(&self) -> SourcePosition {
-		let source = std::str::from_utf8(
-			&self.source.as_bytes()[..self.source.len() - self.chars.as_str().len()],
-		)
-		.expect("the slicing didn't work?");
-		SourcePosition::at_end(source)
+		self.source_position_at(self.index())
+	}
+
+	/// Returns the position of the byte `index` into the source.
+	#[must_use]
+	pub fn source_position_at(&self, index: usize) -> SourcePosition {
+		SourcePosition::at_end(&self.source[..index])
 	}
 
 	/// Creates a `kind` [`Error`] that happens at the current location.
 	#[must_use]
-	pub fn error(&self, kind: ErrorKind) -> Error {
-		Error::new(kind, self.source_position())
+	pub fn error(&self, kind: ParseError) -> Error {
+		Error::new(kind, self.source_position(), self.index()..self.index())
 	}
 
 	/// Returns the next character, if it exists.
@@ -85,7 +100,6 @@ impl<'a> Stream<'a> {
 	/// "Untake"s the character `chr` from the source. Note that it must be the last character
 	/// yielded from `.take()`.
 	pub fn untake(&mut self, chr: char) {
-		let chrlen = chr.len_utf8();
 		let source_without_remainder = &self.source[..self.source.len() - self.chars.as_str().len()];
 		let (last_index, _) = source_without_remainder.char_indices().next_back().unwrap();
 		self.chars = self.source[last_index..].chars();
@@ -100,6 +114,11 @@ impl<'a> Stream<'a> {
 		self.chars.as_str()
 	}
 
+	/// Skips the next `len` bytes, which must end on a character boundary.
+	pub fn advance_by(&mut self, len: usize) {
+		self.chars = self.remainder()[len..].chars();
+	}
+
 	pub fn take_while(&mut self, mut cond: impl FnMut(char) -> bool) -> Option<&'a str> {
 		let start = self.remainder();
 		while self.take_if(&mut cond).is_some() {
@@ -130,7 +149,7 @@ impl TakeIf<'_> for char {
 impl<F: FnOnce(char) -> bool> TakeIf<'_> for F {
 	type Output = char;
 	fn take_if(self, stream: &mut Stream<'_>) -> Option<Self::Output> {
-		if stream.peek().map_or(false, self) {
+		if stream.peek().is_some_and(self) {
 			Some(stream.take().unwrap())
 		} else {
 			None
@@ -140,13 +159,14 @@ impl<F: FnOnce(char) -> bool> TakeIf<'_> for F {
 
 impl<'a> TakeIf<'a> for &'_ str {
 	type Output = &'a str;
-	fn take_if(self, stream: &mut Stream<'_>) -> Option<Self::Output> {
-		todo!()
-		// if stream.peek().map_or(false, self) {
-		// 	Some(stream.take().unwrap())
-		// } else {
-		// 	None
-		// }
+	fn take_if(self, stream: &mut Stream<'a>) -> Option<Self::Output> {
+		let remainder = stream.remainder();
+		if !remainder.starts_with(self) {
+			return None;
+		}
+
+		stream.advance_by(self.len());
+		Some(&remainder[..self.len()])
 	}
 }
 
@@ -182,9 +202,9 @@ mod tests {
 
 		macro_rules! assert_err {
 			() => {
-				let err = stream.error(ErrorKind::InvalidEscape);
+				let err = stream.error(ParseError::Eof);
 				assert_eq!(stream.source_position(), err.whence());
-				assert!(matches!(err.kind(), ErrorKind::InvalidEscape));
+				assert!(matches!(err.kind(), ParseError::Eof));
 			};
 		}
 
@@ -218,6 +238,15 @@ mod tests {
 		assert_eq!(None, stream.peek());
 	}
 
+	#[test]
+	fn take_if_str() {
+		let mut stream = Stream::new("a😊b");
+		assert_eq!(None, stream.take_if("b"));
+		assert_eq!(Some("a😊"), stream.take_if("a😊"));
+		assert_eq!(5, stream.index());
+		assert_eq!(Some('b'), stream.take());
+	}
+
 	#[test]
 	fn untake() {
 		let mut stream = Stream::new("a😊❦🎲f🀄");
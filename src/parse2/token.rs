@@ -1,10 +1,19 @@
+use crate::filesize::{FileSize, Suffix};
+use crate::parse::ParseError;
 use crate::parse2::parser::Phase;
-use crate::parse2::{ErrorKind, Parser, Result, Stream};
-
-// we could use the whole "span" scheme, but that seems overkill, as all we're doing
+use crate::parse2::{Parser, Result, Stream};
+use crate::DateTime;
+use std::borrow::Cow;
+
+/// A token of source code.
+///
+/// Compound literals (strings, regexes, and paths) are lexed as a start token, followed by any
+/// amount of raw data, `$` escapes, and interpolations, and then an end token. Which of these are
+/// lexed next depends on the [`Phase`] the parser's in.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token<'a> {
 	// Compound Tokens
-	RawData(&'a str),
+	RawData(Cow<'a, [u8]>), // only borrowed if there were no escapes.
 	StartString,
 	EndString,
 	StartRegex,
@@ -16,8 +25,15 @@ pub enum Token<'a> {
 
 	// Simpler Tokens
 	Identifier(&'a str),
+	CliArg(isize),   // `$1`, `${-1}`
+	EnvVar(&'a str), // `$HOME`, `${HOME}`
+	Number(f64),
+	DateTime(DateTime),
+	FileSize { fs: FileSize, precision: u8 },
 
 	// Symbols
+	BeginBlock,   // `^{`
+	EndBlock,     // `${`
 	Comma,        // `,`
 	Semicolon,    // `;`
 	LeftParen,    // `(`
@@ -37,6 +53,7 @@ pub enum Token<'a> {
 	Elif,     // `elif`
 	While,    // `while`
 	For,      // `for`
+	In,       // `in`
 	Break,    // `break`
 	Continue, // `continue`
 	Return,   // `return`
@@ -56,8 +73,6 @@ pub enum Token<'a> {
 	GreaterThan,        // `>`
 
 	// Short-circuit keywords.
-	Undef,        // `//`
-	UndefAssign,  // `//=`
 	OrOr,         // `||`
 	OrOrAssign,   // `||=`
 	AndAnd,       // `&&`
@@ -67,357 +82,631 @@ pub enum Token<'a> {
 	Add,      // `+`
 	Subtract, // `-`
 	Multiply, // `*`
-	Divide,   // `/` (surrounded by non-token chars on each side)
-	Modulo,   // `%`
-	Pow,      // `^`
+	Divide,   // `div`, as `/` always starts a path
+	Modulo,   // `mod`
 
 	// Math Assignment keywords
-	Increment,      // `++`
-	Decrement,      // `--`
 	AddAssign,      // `+=`
 	SubtractAssign, // `-=`
 	MultiplyAssign, // `*=`
-	DivideAssign,   // `/=` (surrounded by non-token chars on each side)
-	ModuloAssign,   // `%=`
-	PowAssign,      // `^=`
+	DivideAssign,   // `div=`
+	ModuloAssign,   // `mod=`
 }
 
-impl<'a> Token<'a> {
-	pub fn parse_binary(parser: &mut Parser<'a>) -> Result<Option<Self>> {
-		let mut stream = parser.stream_mut();
-		Self::strip_whitespace_and_comments(stream);
+fn is_ascii_alphanumeric_or_underscore(c: char) -> bool {
+	c.is_ascii_alphanumeric() || c == '_'
+}
 
-		macro_rules! ifeq {
-			($ifeq:ident, $ifneq:ident) => {
-				Ok(Some(if stream.take_if('=').is_some() { Self::$ifeq } else { Self::$ifneq }))
-			};
+fn is_dollar_sign_escape_start(c: char) -> bool {
+	is_ascii_alphanumeric_or_underscore(c) || c == '{'
+}
+
+fn is_path_literal_character(c: char) -> bool {
+	!c.is_whitespace() && !",();&|".contains(c)
+}
+
+fn is_path_start(byte: u8) -> bool {
+	byte.is_ascii_alphanumeric() || b"_/.+*[?".contains(&byte)
+}
+
+fn is_path_end(byte: u8) -> bool {
+	b",();&|".contains(&byte) || byte.is_ascii_whitespace()
+}
+
+/// Finds the `}` which closes the `{` that `rest` starts with.
+fn matching_brace(rest: &[u8]) -> Option<usize> {
+	let mut depth = 0;
+	for (idx, &byte) in rest.iter().enumerate() {
+		match byte {
+			b'{' => depth += 1,
+			b'}' if depth == 1 => return Some(idx),
+			b'}' => depth -= 1,
+			_ => {}
 		}
+	}
 
-		match stream.take() {
-			// Matching
-			Some('!') if stream.take_if('~').is_some() => Ok(Some(Self::NotMatches)),
-			Some('=') if stream.take_if('~').is_some() => Ok(Some(Self::Matches)),
+	None
+}
 
-			// Increment/Decrement
-			Some('+') if stream.take_if('+').is_some() => Ok(Some(Self::Increment)),
-			Some('-') if stream.take_if('-').is_some() => Ok(Some(Self::Decrement)),
+// This doesn't do real parsing, so it's fooled by things like `["a/b"]` or interpolations which
+// contain spaces. That's how paths have always been found, though, so it's kept for compatibility.
+//
+// `nested` is whether there's an enclosing `{`, in which case a `}` without a matching `{` ends
+// the path, so that `{dir/x}` and `$/{x}/` work.
+fn is_path_next(rest: &[u8], nested: bool) -> bool {
+	// These all unambiguously start a path, unless it's `*` or `+` as an operator.
+	if b"*.+/~".contains(&rest[0]) {
+		return !(b"*+".contains(&rest[0])
+			&& rest.get(1).is_some_and(|&c| c == b'=' || c.is_ascii_whitespace()));
+	}
 
-			// Short circuit assignment
-			Some('/') if stream.take_if('/').is_some() => ifeq!(UndefAssign, Undef),
-			Some('&') if stream.take_if('&').is_some() => ifeq!(AndAndAssign, AndAnd),
-			Some('|') if stream.take_if('|').is_some() => ifeq!(OrOrAssign, OrOr),
+	// `${` also starts end blocks, so it's only a path when something follows the `}` directly,
+	// such as `${dir}/*.txt`.
+	if rest.starts_with(b"${") {
+		return matching_brace(&rest[1..])
+			.and_then(|end| rest.get(end + 2))
+			.is_some_and(|&c| is_path_literal_character(c as char) && !(nested && c == b'}'));
+	}
 
-			// Logic
-			Some('!') => ifeq!(NotEqual, Not),
-			Some('=') => ifeq!(Equal, Assign),
-			Some('<') => ifeq!(LessThanOrEqual, LessThan),
-			Some('>') => ifeq!(GreaterThanOrEqual, GreaterThan),
+	if !is_path_start(rest[0]) {
+		return false;
+	}
 
-			// Math
-			Some('+') => ifeq!(AddAssign, Add),
-			Some('-') => ifeq!(SubtractAssign, Subtract),
-			Some('*') => ifeq!(MultiplyAssign, Multiply),
-			Some('/') => ifeq!(DivideAssign, Divide),
-			Some('%') => ifeq!(ModuloAssign, Modulo),
-			Some('^') => ifeq!(PowAssign, Pow),
-
-			None => Ok(None),
-			Some(other) => {
-				stream.untake(other);
-				Ok(None)
-			}
+	let mut depth = 0;
+	for &byte in rest {
+		if byte as char == std::path::MAIN_SEPARATOR {
+			return true;
 		}
-	}
 
-	pub fn parse_non_binary(parser: &mut Parser<'a>) -> Result<Option<Self>> {
-		match parser.phase() {
-			Some(Phase::Text) => Self::parse_phase_text(parser).map(Some),
-			Some(Phase::Regex) => Self::parse_phase_regex(parser).map(Some),
-			Some(Phase::Path) => Self::parse_phase_path(parser),
-			None => Self::parse_no_phase(parser),
+		match byte {
+			b'{' => depth += 1,
+			b'}' if depth == 0 && nested => break,
+			b'}' => depth -= 1,
+			_ if is_path_end(byte) => break,
+			_ => {}
 		}
 	}
 
-	fn parse_phase_until(stream: &mut Stream<'a>, end: char) -> Option<Self> {
-		// We only stop parsing when we hit a `{` or an `end`, without `\`s in front.
-		let mut last_was_backslash = false;
-		stream
-			.take_while(|c| {
-				if last_was_backslash {
-					last_was_backslash = false;
-					true
-				} else if c == '\\' {
-					last_was_backslash = true;
-					true
-				} else {
-					c != end && c != '{'
-				}
-			})
-			.map(Self::RawData)
+	false
+}
+
+fn parse_hex(stream: &mut Stream, len: usize) -> std::result::Result<u32, ParseError> {
+	let digits = stream.remainder().get(..len).ok_or(ParseError::BadEscape("too few hex digits"))?;
+	let number = u32::from_str_radix(digits, 16)
+		.ok()
+		.filter(|_| digits.bytes().all(|c| c.is_ascii_hexdigit()))
+		.ok_or(ParseError::BadEscape("not a hex digit"))?;
+
+	stream.advance_by(len);
+	Ok(number)
+}
+
+impl<'a> Stream<'a> {
+	/// Parses out an escape sequence after the `\`, appending what it stands for to `buf`.
+	fn parse_escape(
+		&mut self,
+		is_path: bool,
+		buf: &mut Vec<u8>,
+	) -> std::result::Result<(), ParseError> {
+		let chr = match self.take().ok_or(ParseError::BadEscape("nothing after backslash"))? {
+			c @ ('\\' | '\"' | '\'' | '$' | '{') => c,
+			c @ ('*' | '?' | '[') if is_path => c,
+			'n' => '\n',
+			't' => '\t',
+			'r' => '\r',
+			'0' => '\0',
+			'x' => {
+				buf.push(parse_hex(self, 2)? as u8);
+				return Ok(());
+			}
+			'u' => char::from_u32(parse_hex(self, 4)?)
+				.ok_or(ParseError::BadEscape("invalid `\\u` escape"))?,
+			other => return Err(ParseError::InvalidEscape(other)),
+		};
+
+		buf.extend_from_slice(chr.encode_utf8(&mut [0; 4]).as_bytes());
+		Ok(())
 	}
 
-	pub fn parse_phase_text(parser: &mut Parser<'a>) -> Result<Self> {
-		debug_assert_eq!(parser.phase(), Some(Phase::Text));
-		if let Some(raw) = Self::parse_phase_until(parser.stream_mut(), '"') {
-			return Ok(raw);
+	/// Parses out `[0-9][0-9_]*`
+	fn parse_digits(&mut self, into: &mut String) -> bool {
+		// Make sure we start with just a digit, not a `_`
+		if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+			return false;
 		}
 
-		match parser.stream_mut().take() {
-			Some('"') => {
-				parser.leave_phase(Phase::Text);
-				Ok(Self::EndString)
+		let digits = self.take_while(|c| c.is_ascii_digit() || c == '_').unwrap_or_default();
+		into.extend(digits.chars().filter(|&c| c != '_'));
+		true
+	}
+
+	/// Parses out `YYYY-MM-DD`, optionally followed by `THH:MM` or `THH:MM:SS`.
+	fn parse_datetime(&mut self) -> std::result::Result<Option<DateTime>, ParseError> {
+		fn number(digits: &[u8]) -> Option<u32> {
+			digits
+				.iter()
+				.try_fold(0, |acc, c| c.is_ascii_digit().then(|| acc * 10 + (c - b'0') as u32))
+		}
+
+		let rest = self.remainder().as_bytes();
+		let Some(date) = rest.get(..10).filter(|date| date[4] == b'-' && date[7] == b'-') else {
+			return Ok(None);
+		};
+		let (Some(year), Some(month), Some(day)) =
+			(number(&date[..4]), number(&date[5..7]), number(&date[8..]))
+		else {
+			return Ok(None);
+		};
+
+		let (mut len, mut hour, mut min, mut sec) = (date.len(), 0, 0, 0);
+		if let Some(time) =
+			rest.get(10..16).filter(|time| b"Tt".contains(&time[0]) && time[3] == b':')
+		{
+			if let (Some(h), Some(m)) = (number(&time[1..3]), number(&time[4..])) {
+				(len, hour, min) = (16, h, m);
+
+				if let Some(s) = rest.get(16..19).filter(|s| s[0] == b':').and_then(|s| number(&s[1..]))
+				{
+					(len, sec) = (19, s);
+				}
 			}
-			Some('{') => Ok(Self::StartInterpolation),
-			Some(_) => unreachable!(),
-			None => Err(parser.stream_mut().error(ErrorKind::UnterminatedString)),
 		}
+
+		let datetime = DateTime::from_ymd_hms(year.into(), month, day, hour, min, sec)
+			.ok_or(ParseError::InvalidDateTime)?;
+		self.advance_by(len);
+		Ok(Some(datetime))
+	}
+
+	/// Parses out `0x...`, `0o...`, and `0b...` integers. (`0b` on its own is zero bytes.)
+	fn parse_base_integer(&mut self) -> std::result::Result<Option<u64>, ParseError> {
+		let radix = match self.remainder().as_bytes() {
+			[b'0', b'x' | b'X', ..] => 16,
+			[b'0', b'o' | b'O', ..] => 8,
+			[b'0', b'b' | b'B', c, ..] if c.is_ascii_digit() => 2,
+			_ => return Ok(None),
+		};
+		self.advance_by(2);
+
+		let digits = self.take_while(|c| c.is_digit(radix) || c == '_').unwrap_or_default();
+		let digits = digits.chars().filter(|&c| c != '_').collect::<String>();
+		u64::from_str_radix(&digits, radix).map(Some).or(Err(ParseError::BadFloat))
 	}
 
-	pub fn parse_phase_regex(parser: &mut Parser<'a>) -> Result<Self> {
-		debug_assert_eq!(parser.phase(), Some(Phase::Regex));
+	fn parse_float(&mut self) -> std::result::Result<(f64, u8), ParseError> {
+		let mut buf = String::new();
 
-		if let Some(raw) = Self::parse_phase_until(parser.stream_mut(), '"') {
-			return Ok(raw);
+		if !self.parse_digits(&mut buf) {
+			return Err(ParseError::BadFloat);
 		}
 
-		match parser.stream_mut().take() {
-			Some('/') => {
-				parser.leave_phase(Phase::Regex);
-				Ok(Self::EndRegex(
-					parser.stream_mut().take_while(char::is_alphanumeric).unwrap_or_default(),
-				))
+		// Trailing `0`s after the `.` (eg `1.500`) are the precision of the number, which is used
+		// with `=~` to roughly match file sizes.
+		let precision = if self.take_if('.').is_some() {
+			buf.push('.');
+
+			if !self.parse_digits(&mut buf) {
+				return Err(ParseError::BadFloat);
+			}
+
+			buf.bytes().rev().take_while(|&c| c == b'0').count() as u8
+		} else {
+			0
+		};
+
+		if self.take_if(|c| c == 'e' || c == 'E').is_some() {
+			buf.push('e');
+
+			if let Some(sign) = self.take_if(|c| c == '-' || c == '+') {
+				buf.push(sign);
+			}
+
+			if !self.parse_digits(&mut buf) {
+				return Err(ParseError::BadFloat);
+			}
+		}
+
+		let float = buf.parse().or(Err(ParseError::BadFloat))?;
+		Ok((float, precision))
+	}
+
+	fn strip_whitespace_and_comments(&mut self) {
+		loop {
+			if self.take_while(char::is_whitespace).is_some() {
+				continue;
+			}
+
+			// `#` starts a comment, which goes until the end of the line.
+			if self.take_if('#').is_some() {
+				let _ = self.take_while(|c| c != '\n');
+				continue;
 			}
-			Some('{') => Ok(Self::StartInterpolation),
-			Some(_) => unreachable!(),
-			None => Err(parser.stream_mut().error(ErrorKind::UnterminatedRegex)),
+
+			break;
 		}
 	}
+}
 
-	pub fn parse_phase_path(parser: &mut Parser<'a>) -> Result<Option<Self>> {
-		todo!();
+impl<'a> Token<'a> {
+	/// Lexes the next token, or returns `None` if the end of the source was reached.
+	pub fn parse(parser: &mut Parser<'a>) -> Result<Option<Self>> {
+		match parser.phase() {
+			Some(Phase::Text) => Self::parse_within_string(parser).map(Some),
+			Some(Phase::Regex) => Self::parse_within_regex(parser).map(Some),
+			Some(Phase::Path) => Self::parse_within_path(parser).map(Some),
+			Some(Phase::Interpolation | Phase::Braces) | None => Self::parse_normal(parser),
+		}
 	}
 
-	// Regex for path start: `~?[-\w_*+.]*/`
-	fn parse_path_start(stream: &Stream<'_>) -> bool {
-		let mut peek_stream = stream.clone();
+	/// Takes raw data up until `is_end` returns true for the remainder of the source, or the
+	/// source ends. `\`s are handled by `escape`, which is given the stream after the `\`.
+	fn parse_raw(
+		parser: &mut Parser<'a>,
+		mut is_end: impl FnMut(&str) -> bool,
+		escape: impl Fn(&mut Stream<'a>, &mut Vec<u8>) -> std::result::Result<(), ParseError>,
+	) -> Result<Self> {
+		let start = parser.stream().remainder();
+		let mut escaped = None::<Vec<u8>>;
 
-		// a leading `~` is allowed, but doesn't guarantee that we're at start of path.
-		let _ = peek_stream.take_if('~');
-		while let Some(c) = peek_stream.take() {
-			match c {
-				// All the allowed chars don't tell us if we're a path or not.
-				'-' | '*' | '.' | '+' | '_' => continue,
-				c if c.is_alphanumeric() => continue,
+		loop {
+			let stream = parser.stream_mut();
+			let before = stream.remainder();
+			if before.is_empty() || is_end(before) {
+				break;
+			}
 
-				// If we hit a `/`, we know we've found a path (as)
-				'/' => return true,
+			let chr = stream.take().unwrap();
+			if chr != '\\' {
+				if let Some(ref mut buf) = escaped {
+					buf.extend_from_slice(&before.as_bytes()[..chr.len_utf8()]);
+				}
+				continue;
+			}
 
-				// Anything else before hitting a `/` tells us it's not a path start.
-				_ => return false,
+			let buf =
+				escaped.get_or_insert_with(|| start.as_bytes()[..start.len() - before.len()].to_vec());
+			if let Err(err) = escape(stream, buf) {
+				return Err(parser.error(err));
 			}
 		}
 
-		false // we reached end of stream before finding a `/`.
+		let rest = parser.stream().remainder();
+		Ok(Self::RawData(match escaped {
+			Some(buf) => Cow::Owned(buf),
+			None => Cow::Borrowed(&start.as_bytes()[..start.len() - rest.len()]),
+		}))
 	}
 
-	// Compound literals are strings, regexes, and paths. They're more complicated than "simple"
-	// literals, such as identifiers or filesizes.
-	fn parse_compound_literal(parser: &mut Parser<'a>) -> Result<Option<Self>> {
-		if parser.stream_mut().take_if('"').is_some() {
-			parser.enter_phase(Phase::Text);
-			return Ok(Some(Self::StartString));
-		}
+	fn parse_within_string(parser: &mut Parser<'a>) -> Result<Self> {
+		let stream = parser.stream_mut();
 
-		if parser.stream_mut().take_if("$/").is_some() {
-			parser.enter_phase(Phase::Regex);
-			return Ok(Some(Self::StartRegex));
+		match stream.peek() {
+			None => Err(parser.error(ParseError::MissingEndQuote)),
+			Some('"') => {
+				stream.take();
+				parser.leave_phase(Phase::Text);
+				Ok(Self::EndString)
+			}
+			Some('{') => {
+				stream.take();
+				parser.enter_phase(Phase::Interpolation);
+				Ok(Self::StartInterpolation)
+			}
+			Some('$') => {
+				stream.take();
+				Self::parse_dollar_sign(parser)
+			}
+			Some(_) => Self::parse_raw(
+				parser,
+				|rest| rest.starts_with(['"', '{', '$']),
+				|stream, buf| stream.parse_escape(false, buf),
+			),
 		}
+	}
 
-		if Self::parse_path_start(parser.stream()) {
-			parser.enter_phase(Phase::Path);
-			return Ok(Some(Self::StartPath));
+	fn parse_within_regex(parser: &mut Parser<'a>) -> Result<Self> {
+		// A `$` not followed by a name is the regex end-of-line anchor.
+		fn is_dollar_sign_escape(rest: &str) -> bool {
+			rest.strip_prefix('$').is_some_and(|rest| rest.starts_with(is_dollar_sign_escape_start))
 		}
 
-		Ok(None) // no compound literal found
+		let stream = parser.stream_mut();
+		match stream.peek() {
+			None => Err(parser.error(ParseError::MissingEndRegex)),
+			Some('/') => {
+				stream.take();
+				let flags = stream.take_while(|c| c.is_ascii_alphabetic()).unwrap_or_default();
+				parser.leave_phase(Phase::Regex);
+				Ok(Self::EndRegex(flags))
+			}
+			Some('{') => {
+				stream.take();
+				parser.enter_phase(Phase::Interpolation);
+				Ok(Self::StartInterpolation)
+			}
+			Some('$') if is_dollar_sign_escape(stream.remainder()) => {
+				stream.take();
+				Self::parse_dollar_sign(parser)
+			}
+
+			// `\/` is a literal `/`; every other escape is left for the regex engine to handle,
+			// which also lets `\{` and `\$` be used literally.
+			Some(_) => Self::parse_raw(
+				parser,
+				|rest| rest.starts_with(['/', '{']) || is_dollar_sign_escape(rest),
+				|stream, buf| match stream.take() {
+					Some('/') => {
+						buf.push(b'/');
+						Ok(())
+					}
+					Some(other) => {
+						buf.push(b'\\');
+						buf.extend_from_slice(other.encode_utf8(&mut [0; 4]).as_bytes());
+						Ok(())
+					}
+					None => Err(ParseError::MissingEndRegex),
+				},
+			),
+		}
 	}
 
-	fn parse_integer(stream: &mut Stream<'_>) -> Result<(u64, bool)> {
-		let mut int: u64 = 0;
-		let mut underscore = false;
-
-		while let Some(c) = stream.take_if(|c: char| c.is_ascii_digit()) {
-			if let Some(new) =
-				int.checked_mul(10).and_then(|int| int.checked_add((c as u8 - b'0') as u64))
-			{
-				int = new;
-			} else {
-				return Err(stream.error(ErrorKind::IntegerLiteralTooLarge));
+	fn parse_within_path(parser: &mut Parser<'a>) -> Result<Self> {
+		let nested = parser.is_within_braces();
+		let stream = parser.stream_mut();
+
+		match stream.peek() {
+			Some('$') => {
+				stream.take();
+				if stream.take_if('{').is_some() {
+					parser.enter_phase(Phase::Interpolation);
+					Ok(Self::StartInterpolation)
+				} else {
+					Self::parse_dollar_sign(parser)
+				}
 			}
 
-			underscore |= stream.take_while(|c| c == '_').is_some();
-		}
+			// Whitespace as well as `,();&|` indicate end of a path, as does a `}` which closes an
+			// enclosing `{`.
+			Some(c) if is_path_literal_character(c) && !(nested && c == '}') => {
+				// Within `{...}` globs, nothing but `$` ends the raw data.
+				let mut in_brace = false;
+				Self::parse_raw(
+					parser,
+					|rest| {
+						let c = rest.chars().next().unwrap();
+						if c == '$' || (!in_brace && !is_path_literal_character(c)) {
+							return true;
+						}
+
+						if nested && !in_brace && c == '}' {
+							return true;
+						}
+
+						if c == '{' {
+							in_brace = true;
+						} else if c == '}' {
+							in_brace = false;
+						}
+						false
+					},
+					|stream, buf| stream.parse_escape(true, buf),
+				)
+			}
 
-		Ok((int, underscore))
+			_ => {
+				parser.leave_phase(Phase::Path);
+				Ok(Self::EndPath)
+			}
+		}
 	}
 
-	fn parse_number(stream: &mut Stream<'a>) -> Result<Self> {
-		let (integer, underscore) = Self::parse_integer(steram)?;
+	/// Parses `$1`, `$-1`, `$HOME`, and their braced versions (`${1}`), after the `$`.
+	fn parse_dollar_sign(parser: &mut Parser<'a>) -> Result<Self> {
+		let stream = parser.stream_mut();
+		let braced = stream.take_if('{').is_some();
+
+		let token = match stream.peek() {
+			Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => {
+				let mut buf = String::new();
+				if let Some(sign) = stream.take_if(|c| c == '-' || c == '+') {
+					buf.push(sign);
+				}
 
+				if !stream.parse_digits(&mut buf) {
+					return Err(parser.error(ParseError::CliArgMissing));
+				}
 
-		if integer <= 9999 && 
-		todo!()
+				let pos = buf.parse().or(Err(ParseError::CliArgTooLarge));
+				Self::CliArg(pos.map_err(|err| parser.error(err))?)
+			}
+			Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+				Self::EnvVar(stream.take_while(is_ascii_alphanumeric_or_underscore).unwrap())
+			}
+			_ => return Err(parser.error(ParseError::InvalidDollarSign)),
+		};
+
+		if braced && parser.stream_mut().take_if('}').is_none() {
+			return Err(parser.error(ParseError::MissingEndingBrace));
+		}
+
+		Ok(token)
 	}
 
-	fn parse_identifier(stream: &mut Stream<'a>) -> Result<Self> {
-		let name = stream.take_while(|c| c.is_alphanumeric() || c == '_').unwrap();
-
-		match name {
-			"if" => Ok(Self::If),
-			"do" => Ok(Self::Do),
-			"else" => Ok(Self::Else),
-			"elif" => Ok(Self::Elif),
-			"while" => Ok(Self::While),
-			"for" => Ok(Self::For),
-			"break" => Ok(Self::Break),
-			"continue" => Ok(Self::Continue),
-			"return" => Ok(Self::Return),
-			"fn" => Ok(Self::Fn),
-			"true" => Ok(Self::True),
-			"false" => Ok(Self::False),
-			"div" => Ok(if stream.take_if("=").is_some() { Self::DivideAssign } else { Self::Divide }),
-			_ => Ok(Self::Identifier(name)),
+	fn parse_identifier(stream: &mut Stream<'a>) -> Self {
+		// Identifiers can end with a single `?`, such as `file?`.
+		let mut was_last_questionmark = false;
+		let ident = stream
+			.take_while(|c| {
+				if was_last_questionmark {
+					false
+				} else if c == '?' {
+					was_last_questionmark = true;
+					true
+				} else {
+					is_ascii_alphanumeric_or_underscore(c)
+				}
+			})
+			.unwrap();
+
+		let is_assign = |stream: &mut Stream| stream.take_if('=').is_some();
+		match ident {
+			"div" if is_assign(stream) => Self::DivideAssign,
+			"div" => Self::Divide,
+			"mod" if is_assign(stream) => Self::ModuloAssign,
+			"mod" => Self::Modulo,
+
+			"if" => Self::If,
+			"do" => Self::Do,
+			"elif" => Self::Elif,
+			"else" => Self::Else,
+			"while" => Self::While,
+			"for" => Self::For,
+			"in" => Self::In,
+			"break" => Self::Break,
+			"continue" => Self::Continue,
+			"return" => Self::Return,
+			"fn" => Self::Fn,
+			"true" => Self::True,
+			"false" => Self::False,
+			_ => Self::Identifier(ident),
 		}
 	}
 
-	fn strip_whitespace_and_comments(stream: &mut Stream<'_>) {
-		loop {
-			// Strip leading whitespace, if any.
-			let _ = stream.take_while(char::is_whitespace);
+	fn parse_number(stream: &mut Stream<'a>) -> std::result::Result<Self, ParseError> {
+		if let Some(datetime) = stream.parse_datetime()? {
+			return Ok(Self::DateTime(datetime));
+		}
 
-			// If it's a comment, parse that out, otherwise exit.
-			if stream.take_if('#').is_some() {
-				let _ = stream.take_while(|c| c != '\n');
-			} else {
-				break;
+		let (num, precision) = if let Some(integer) = stream.parse_base_integer()? {
+			(integer as f64, 0)
+		} else {
+			stream.parse_float()?
+		};
+
+		let Some(suffix) = stream.take_while(|c| c.is_ascii_alphabetic()) else {
+			return Ok(Self::Number(num));
+		};
+
+		if let Some(suffix) = Suffix::from_bytes(suffix.as_bytes()) {
+			let fs = FileSize::new(num, suffix).ok_or(ParseError::FileSizeLiteralTooLarge)?;
+			return Ok(Self::FileSize { fs, precision });
+		}
+
+		DateTime::relative(num, suffix.as_bytes())
+			.map(Self::DateTime)
+			.ok_or_else(|| ParseError::UnknownNumberSuffix(suffix.to_owned()))
+	}
+
+	fn close_brace(parser: &mut Parser<'a>) -> Self {
+		match parser.phase() {
+			Some(Phase::Interpolation) => {
+				parser.leave_phase(Phase::Interpolation);
+				Self::EndInterpolation
+			}
+			Some(Phase::Braces) => {
+				parser.leave_phase(Phase::Braces);
+				Self::RightBrace
 			}
+			_ => Self::RightBrace,
 		}
 	}
 
-	pub fn parse_no_phase(parser: &mut Parser<'a>) -> Result<Option<Self>> {
-		Self::strip_whitespace_and_comments(parser.stream_mut());
+	fn parse_normal(parser: &mut Parser<'a>) -> Result<Option<Self>> {
+		parser.stream_mut().strip_whitespace_and_comments();
+		parser.start_token();
 
-		if let Some(compound) = Self::parse_compound_literal(parser)? {
-			return Ok(Some(compound));
+		let nested = parser.is_within_braces();
+		let stream = parser.stream_mut();
+		let rest = stream.remainder();
+		if rest.is_empty() {
+			return match parser.phase() {
+				Some(Phase::Interpolation) => Err(parser.error(ParseError::MissingEndingBrace)),
+				_ => Ok(None),
+			};
 		}
 
-		let stream = parser.stream_mut();
+		if is_path_next(rest.as_bytes(), nested) {
+			parser.enter_phase(Phase::Path);
+			return Ok(Some(Self::StartPath));
+		}
 
 		macro_rules! ifeq {
-			($ifeq:ident, $ifneq:ident) => {
-				Ok(Some(if stream.take_if('=').is_some() { Self::$ifeq } else { Self::$ifneq }))
+			($if_eq:ident, $if_not:ident) => {
+				if stream.take_if('=').is_some() {
+					Self::$if_eq
+				} else {
+					Self::$if_not
+				}
 			};
 		}
 
-		match stream.take() {
-			Some(',') => Ok(Some(Token::Comma)),
-			Some(';') => Ok(Some(Self::Semicolon)),
-			Some('(') => Ok(Some(Self::LeftParen)),
-			Some(')') => Ok(Some(Self::RightParen)),
-			Some('{') => Ok(Some(Self::LeftBrace)),
-			Some('}') => Ok(Some(Self::RightBrace)),
-			Some('[') => Ok(Some(Self::LeftBracket)),
-			Some(']') => Ok(Some(Self::RightBracket)),
-
-			Some('?') => Ok(Some(Self::Question)),
-			Some(':') => Ok(Some(Self::Colon)),
-
-			// Matching
-			Some('!') if stream.take_if('~').is_some() => Ok(Some(Self::NotMatches)),
-			Some('=') if stream.take_if('~').is_some() => Ok(Some(Self::Matches)),
-
-			// Increment/Decrement
-			Some('+') if stream.take_if('+').is_some() => Ok(Some(Self::Increment)),
-			Some('-') if stream.take_if('-').is_some() => Ok(Some(Self::Decrement)),
-
-			// Short circuit assignment
-			Some('/') if stream.take_if('/').is_some() => ifeq!(UndefAssign, Undef),
-			Some('&') if stream.take_if('&').is_some() => ifeq!(AndAndAssign, AndAnd),
-			Some('|') if stream.take_if('|').is_some() => ifeq!(OrOrAssign, OrOr),
-
-			// Logic
-			Some('!') => ifeq!(NotEqual, Not),
-			Some('=') => ifeq!(Equal, Assign),
-			Some('<') => ifeq!(LessThanOrEqual, LessThan),
-			Some('>') => ifeq!(GreaterThanOrEqual, GreaterThan),
+		let token = match stream.take().unwrap() {
+			// Start of compound literals
+			'"' => {
+				parser.enter_phase(Phase::Text);
+				Self::StartString
+			}
+			'$' if stream.take_if('/').is_some() => {
+				parser.enter_phase(Phase::Regex);
+				Self::StartRegex
+			}
+
+			// Blocks and delimiters
+			'$' if stream.take_if('{').is_some() => {
+				parser.enter_phase(Phase::Braces);
+				Self::EndBlock
+			}
+			'^' if stream.take_if('{').is_some() => {
+				parser.enter_phase(Phase::Braces);
+				Self::BeginBlock
+			}
+			'{' => {
+				parser.enter_phase(Phase::Braces);
+				Self::LeftBrace
+			}
+			'}' => Self::close_brace(parser),
+			'(' => Self::LeftParen,
+			')' => Self::RightParen,
+			'[' => Self::LeftBracket,
+			']' => Self::RightBracket,
+
+			// Control Characters
+			'?' => Self::Question,
+			':' => Self::Colon,
+			',' => Self::Comma,
+			';' => Self::Semicolon,
+			'&' if stream.take_if('&').is_some() => ifeq!(AndAndAssign, AndAnd),
+			'|' if stream.take_if('|').is_some() => ifeq!(OrOrAssign, OrOr),
 
 			// Math
-			Some('+') => ifeq!(AddAssign, Add),
-			Some('-') => ifeq!(SubtractAssign, Subtract),
-			Some('*') => ifeq!(MultiplyAssign, Multiply),
-			Some('/') => ifeq!(DivideAssign, Divide),
-			Some('%') => ifeq!(ModuloAssign, Modulo),
-			Some('^') => ifeq!(PowAssign, Pow),
-
-			Some(c @ '0'..='9') => {
+			'+' => ifeq!(AddAssign, Add),
+			'*' => ifeq!(MultiplyAssign, Multiply),
+			'-' => ifeq!(SubtractAssign, Subtract),
+
+			// Logic; `!~/` is the negation of a path, not `!~` and then a path.
+			'!' if stream.remainder().starts_with('~') && !stream.remainder().starts_with("~/") => {
+				stream.take();
+				Self::NotMatches
+			}
+			'!' => ifeq!(NotEqual, Not),
+			'=' if stream.take_if('~').is_some() => Self::Matches,
+			'=' => ifeq!(Equal, Assign),
+			'<' => ifeq!(LessThanOrEqual, LessThan),
+			'>' => ifeq!(GreaterThanOrEqual, GreaterThan),
+
+			'$' => Self::parse_dollar_sign(parser)?,
+			c if c.is_ascii_alphabetic() || c == '_' => {
 				stream.untake(c);
-				Self::parse_number(stream).map(Some)
+				Self::parse_identifier(stream)
 			}
-
-			Some(c) if c.is_alphabetic() => {
+			c if c.is_ascii_digit() => {
 				stream.untake(c);
-				Self::parse_identifier(stream).map(Some)
+				Self::parse_number(stream).map_err(|err| parser.error(err))?
 			}
 
-			None => Ok(None),
-			Some(other) => {
-				stream.untake(other);
-				Ok(None)
-			}
-		}
+			other => return Err(parser.error(ParseError::UnknownTokenStart(other))),
+		};
+
+		Ok(Some(token))
 	}
 }
-/*
-
-\w+/...
-[~.*+\w]?[^/]*\/
-
-
-*/
-/*
-	// misc
-	Raw(Vec<u8>),
-	CliArg(isize),
-	EnvVar(OsString),
-	Variable(String),
-	Number(f64),
-	DateTime(crate::DateTime),
-	FileSize { fs: crate::FileSize, precision: u8 },
-
-	// Keywords
-
-
-	// Begin / end pairs
-	BeginPath,
-	EndPath,
-	BeginString,
-	EndString,
-	BeginRegex,
-	EndRegex(Vec<u8>),
-	BeginBraceEscape, //
-	EndBraceEscape,   //
-
-	// Block delims
-	BeginBlockStart, // `^(`
-	EndBlockStart,   // `$(`
-	LeftParen,       // `(`
-	RightParen,      // `)`
-
-	// control characters
-	Question,  // `?`
-	Colon,     // `:`
-	Comma,     // `,`
-	Semicolon, // `;`
-	And,       // `&&`
-	Or,        // `||`
-	Assign,    // `=`
-*/
@@ -17,6 +17,7 @@ suffix := {normal};
 */
 use os_str_bytes::{OsStrBytes, OsStringBytes};
 use std::ffi::{OsStr, OsString};
+use std::fmt::{self, Display, Formatter};
 use std::ops::RangeInclusive;
 use std::path::{Component, Path, PathBuf};
 
@@ -88,7 +89,7 @@ impl PathGlob {
 
 		let mut components = given.components();
 
-		if components.next().map_or(true, |x| x.as_os_str() != self.start) {
+		if components.next().is_none_or(|x| x.as_os_str() != self.start) {
 			// todo: this might return false positives, b/c of `../` etc.
 			return false;
 		}
@@ -102,6 +103,37 @@ impl PathGlob {
 			.match_globbed_dirs(&self.parts, &components.map(Component::as_os_str).collect::<Vec<_>>())
 	}
 
+	/// Whether anything within the directory `dir` (but not `dir` itself) could match, ie whether
+	/// it's worth traversing into `dir`.
+	pub fn could_match_within(&self, dir: &Path) -> bool {
+		let mut components = dir.components();
+		if components.next().is_none_or(|x| x.as_os_str() != self.start) {
+			return false;
+		}
+
+		self.could_match_below(&self.parts, &components.map(Component::as_os_str).collect::<Vec<_>>())
+	}
+
+	fn could_match_below(&self, parts: &[PathPart], components: &[&OsStr]) -> bool {
+		let Some((part, parts)) = parts.split_first() else {
+			// `dir/` matches everything within `dir`.
+			return self.is_dir;
+		};
+
+		let Some((component, components)) = components.split_first() else {
+			return true; // the rest of the parts could match what's within it
+		};
+
+		match part {
+			PathPart::AnyDirs => true,
+			PathPart::Normal(os) => component == os && self.could_match_below(parts, components),
+			PathPart::Globbed(glob) => {
+				match_globbed_parts(glob, &component.to_raw_bytes())
+					&& self.could_match_below(parts, components)
+			}
+		}
+	}
+
 	fn match_globbed_dirs(&self, parts: &[PathPart], components: &[&OsStr]) -> bool {
 		if parts.is_empty() || components.is_empty() {
 			// if we've exhausted our parts, and we're a dir, then it's a match
@@ -139,6 +171,20 @@ pub enum PathParseError {
 	CantGetPwd(std::io::Error),
 }
 
+impl Display for PathParseError {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Self::NoPathGiven => f.write_str("no path given"),
+			Self::NotAPathStart(chr) => write!(f, "paths can't start with `{chr}`"),
+			Self::PrematureRangeEnd => f.write_str("missing closing `]`"),
+			Self::CantGetHomeDir => f.write_str("can't get the home directory"),
+			Self::InvalidEscape(chr) => write!(f, "unknown escape `\\{chr}`"),
+			Self::PrematureAlternateEnd => f.write_str("missing closing `}`"),
+			Self::CantGetPwd(err) => write!(f, "can't get the current directory: {err}"),
+		}
+	}
+}
+
 #[derive(Debug, Clone, PartialEq)]
 enum PathPart {
 	AnyDirs,
@@ -228,16 +274,16 @@ fn match_globbed_parts(parts: &[Glob], given: &[u8]) -> bool {
 		Glob::Alternative(ref alts) => alts.iter().any(|alt| match alt {
 			Glob::Raw(ref raw) => given
 				.strip_prefix(raw.as_slice())
-				.map_or(false, |rest| match_globbed_parts(&parts[1..], rest)),
+				.is_some_and(|rest| match_globbed_parts(&parts[1..], rest)),
 			_ => todo!("support other alternatives"),
 		}),
 		Glob::Raw(ref raw) => given
 			.strip_prefix(raw.as_slice())
-			.map_or(false, |rest| match_globbed_parts(&parts[1..], rest)),
+			.is_some_and(|rest| match_globbed_parts(&parts[1..], rest)),
 		Glob::SingleChar => {
-			given.get(1..).map_or(false, |rest| match_globbed_parts(&parts[1..], rest))
+			given.get(1..).is_some_and(|rest| match_globbed_parts(&parts[1..], rest))
 		}
-		Glob::Range(ref range) => given.split_first().map_or(false, |(chr, rest)| {
+		Glob::Range(ref range) => given.split_first().is_some_and(|(chr, rest)| {
 			range.is_match(*chr as char) && match_globbed_parts(&parts[1..], rest)
 		}),
 		Glob::ZeroOrMore if parts.len() == 1 => true, // ie if it's the last one.
@@ -270,7 +316,7 @@ impl GlobRange {
 	}
 
 	fn _is_match(&self, given: char) -> bool {
-		self.solitary.iter().any(|&c| c == given)
+		self.solitary.contains(&given)
 			|| self.ranges.iter().any(|rng| rng.contains(&given))
 	}
 
@@ -293,7 +339,7 @@ impl GlobRange {
 				}
 
 				'&' if solitary.last() == Some(&'&')
-					|| ranges.last().map_or(false, |r| *r.end() == '&') =>
+					|| ranges.last().is_some_and(|r| *r.end() == '&') =>
 				{
 					todo!("`&&` within char ranges")
 				}
@@ -328,3 +374,35 @@ impl GlobRange {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::PathGlob;
+	use std::path::Path;
+
+	fn could_match_within(glob: &str, dir: &str) -> bool {
+		PathGlob::parse(Path::new(glob)).unwrap().could_match_within(Path::new(dir))
+	}
+
+	#[test]
+	fn could_match_within_dirs() {
+		assert!(could_match_within("src/*.rs", "."));
+		assert!(could_match_within("src/*.rs", "./src"));
+		assert!(!could_match_within("src/*.rs", "./src/vm"));
+		assert!(!could_match_within("src/*.rs", "./target"));
+		assert!(!could_match_within("src/main.rs", "./src/main.rs"));
+
+		assert!(could_match_within("src/**/*.rs", "./src/vm/block"));
+		assert!(!could_match_within("src/**/*.rs", "./benches"));
+		assert!(could_match_within("**/*.rs", "./target/debug"));
+		assert!(could_match_within("s*/v?/*", "./src/vm"));
+		assert!(!could_match_within("s*/v?/*", "./src/play"));
+		assert!(could_match_within("src/", "./src/vm"));
+
+		assert!(could_match_within("/etc/*.conf", "/etc"));
+		assert!(!could_match_within("/etc/*.conf", "./etc"));
+		assert!(!could_match_within("/etc/*.conf", "/usr"));
+		assert!(could_match_within("../sibling/*", ".."));
+		assert!(!could_match_within("../sibling/*", "."));
+	}
+}
@@ -2,7 +2,7 @@ use crate::play::{PathInfo, PlayResult, Program};
 use crate::Value;
 use std::io;
 use std::path::PathBuf;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct PlayContext<'a> {
@@ -35,8 +35,8 @@ impl<'a> PlayContext<'a> {
 		self.pathinfo.is_file()
 	}
 
-	pub fn contents(&mut self) -> io::Result<Rc<[u8]>> {
-		self.pathinfo.contents()
+	pub fn contents(&mut self) -> io::Result<Arc<[u8]>> {
+		self.pathinfo.contents(u64::MAX)
 	}
 
 	pub fn lookup_var(&mut self, name: &str) -> PlayResult<Value> {
@@ -44,7 +44,7 @@ impl<'a> PlayContext<'a> {
 			"dir?" | "directory?" | "d?" => Ok(self.is_dir().into()),
 			"file?" | "f?" => Ok(self.is_file().into()),
 			"size" | "z" => Ok(self.info().content_size().into()),
-			"contents" | "c" => Ok(self.pathinfo.contents()?.into()),
+			"contents" | "c" => Ok(self.pathinfo.contents(u64::MAX)?.into()),
 			"path" | "p" => Ok(self.pathinfo.path()._rc().clone().into()),
 			_ => Ok(self.program.get_var(name).unwrap_or_default()),
 		}
@@ -19,6 +19,18 @@ impl Env {
 		self.cli.get(position).map(|x| &**x)
 	}
 
+	/// Gets the command line argument `$position`, where `$1` is the first argument and `$-1` is
+	/// the last. There's no `$0`.
+	pub fn get_cli_arg(&self, position: isize) -> Option<&OsStr> {
+		let index = if position > 0 {
+			position as usize - 1
+		} else {
+			self.cli.len().checked_sub(position.unsigned_abs())?
+		};
+
+		self.get_cli(index)
+	}
+
 	/// How many args were passed in the cli.
 	pub fn cli_len(&self) -> usize {
 		self.cli.len()
@@ -1,30 +1,52 @@
-use crate::parse::ParseError;
+use crate::parse::{Diagnostic, ParseError};
 use std::io;
+use std::path::Path;
 
 #[derive(Debug)]
 pub enum PlayError {
 	Io(io::Error),
-	CannotParse(ParseError),
+	CannotParse(Diagnostic),
 	Other(&'static str),
 	RunError(crate::vm::RunError),
 }
 
 pub type PlayResult<T> = Result<T, PlayError>;
 
+impl PlayError {
+	/// Records that the source which errored came from `path`, if it's an error from parsing or
+	/// compiling that doesn't know what file it's from yet.
+	pub fn in_file(self, path: &Path) -> Self {
+		match self {
+			Self::CannotParse(diag) if diag.file().is_none() => Self::CannotParse(diag.in_file(path)),
+			other => other,
+		}
+	}
+}
+
 impl From<io::Error> for PlayError {
 	fn from(err: io::Error) -> Self {
 		Self::Io(err)
 	}
 }
 
+impl From<Diagnostic> for PlayError {
+	fn from(err: Diagnostic) -> Self {
+		Self::CannotParse(err)
+	}
+}
+
 impl From<ParseError> for PlayError {
 	fn from(err: ParseError) -> Self {
-		Self::CannotParse(err)
+		Self::CannotParse(err.into())
 	}
 }
 
 impl From<crate::vm::RunError> for PlayError {
 	fn from(err: crate::vm::RunError) -> Self {
-		Self::RunError(err)
+		// So that `--ignored-errors` applies to them.
+		match err {
+			crate::vm::RunError::Io(err) | crate::vm::RunError::Unreadable(err) => Self::Io(err),
+			other => Self::RunError(other),
+		}
 	}
 }
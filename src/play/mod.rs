@@ -5,6 +5,8 @@ mod path;
 mod pathinfo;
 
 pub mod program;
+pub mod read;
+pub mod sniff;
 
 pub use context::PlayContext;
 pub use env::Env;
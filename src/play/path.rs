@@ -9,9 +9,9 @@ impl Path {
 		unsafe { &*(t as *const RawOsStr as *const Self) }
 	}
 
-	pub fn _rc(&self) -> std::rc::Rc<std::path::Path> {
+	pub fn _rc(&self) -> std::sync::Arc<std::path::Path> {
 		use os_str_bytes::OsStringBytes;
-		std::rc::Rc::from(std::path::PathBuf::from(std::ffi::OsString::assert_from_raw_vec(
+		std::sync::Arc::from(std::path::PathBuf::from(std::ffi::OsString::assert_from_raw_vec(
 			self.0.as_raw_bytes().to_owned(),
 		)))
 	}
@@ -1,32 +1,49 @@
-use crate::FileSize;
+use crate::play::sniff::{Sniff, SNIFF_SIZE};
+use crate::{DateTime, FileSize};
 use os_str_bytes::{OsStrBytes, RawOsString};
 use std::ffi::OsStr;
 use std::fs::Metadata;
 use std::io;
 use std::path::Path;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct PathInfo {
 	path: RawOsString,
 	contents: FileContents,
-	metadata: Metadata,
+	metadata: Metadata, // the target's metadata, or the link's own if the target's missing.
+	symlink_metadata: Metadata, // the metadata of the path itself, without following symlinks.
+	broken: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct FileContents {
-	contents: Option<Rc<[u8]>>,
+	contents: Option<Arc<[u8]>>,
+	sniff: Option<Sniff>,
 }
 
 impl PathInfo {
 	/// Creates a new [`PathInfo`]. Returns an error if there was a problem reading the metadata.
+	///
+	/// Symlinks whose targets don't exist aren't an error; they're just [broken](Self::is_broken).
 	pub fn new<P: AsRef<Path> + ?Sized>(path: &P) -> io::Result<Self> {
 		let path = path.as_ref().to_owned();
-		let metadata = std::fs::metadata(&path)?;
+		let symlink_metadata = std::fs::symlink_metadata(&path)?;
+		let (metadata, broken) = if symlink_metadata.is_symlink() {
+			match std::fs::metadata(&path) {
+				Ok(metadata) => (metadata, false),
+				Err(err) if err.kind() == io::ErrorKind::NotFound => (symlink_metadata.clone(), true),
+				Err(err) => return Err(err),
+			}
+		} else {
+			(symlink_metadata.clone(), false)
+		};
+
 		debug_assert!(!path.as_os_str().is_empty());
 		let path = RawOsString::new(path.into());
+		let contents = FileContents { contents: None, sniff: None };
 
-		Ok(Self { path, metadata, contents: FileContents { contents: None } })
+		Ok(Self { path, contents, metadata, symlink_metadata, broken })
 	}
 
 	/// Fetches the name of the file.
@@ -45,33 +62,135 @@ impl PathInfo {
 		&self.metadata
 	}
 
-	/// Returns whether the file is a directory. (todo: Does it follow symlinks)
+	/// Returns the metadata of the path itself, without following symlinks.
+	pub fn symlink_metadata(&self) -> &Metadata {
+		&self.symlink_metadata
+	}
+
+	/// Returns whether the file is a directory, following symlinks.
 	pub fn is_dir(&self) -> bool {
 		self.metadata.is_dir()
 	}
 
-	/// Returns whether the file is a directory. (todo: Does it follow symlinks)
+	/// Returns whether the file is a file, following symlinks.
 	pub fn is_file(&self) -> bool {
 		self.metadata.is_file()
 	}
 
-	/// Returns the contents of the path.
-	pub fn contents(&mut self) -> io::Result<Rc<[u8]>> {
+	/// Returns whether the path itself is a symlink.
+	pub fn is_symlink(&self) -> bool {
+		self.symlink_metadata.is_symlink()
+	}
+
+	/// Returns whether the path is a symlink whose target doesn't exist.
+	pub fn is_broken(&self) -> bool {
+		self.broken
+	}
+
+	/// Returns whether the file is a file that can be executed, following symlinks.
+	///
+	/// On unix, this is whether any of the executable bits are set. Elsewhere, it's whether the
+	/// file has an executable extension (such as `.exe`).
+	pub fn is_executable(&self) -> bool {
+		if !self.is_file() {
+			return false;
+		}
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			self.metadata.permissions().mode() & 0o111 != 0
+		}
+
+		#[cfg(not(unix))]
+		{
+			self.path()._rc().extension().is_some_and(|ext| {
+				["exe", "bat", "cmd", "com"].iter().any(|exe| ext.eq_ignore_ascii_case(exe))
+			})
+		}
+	}
+
+	/// Uniquely identifies the file (following symlinks) by its device and inode, so that the same
+	/// directory isn't traversed twice. Not all platforms support this.
+	pub fn file_id(&self) -> Option<(u64, u64)> {
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::MetadataExt;
+			Some((self.metadata.dev(), self.metadata.ino()))
+		}
+
+		#[cfg(not(unix))]
+		{
+			None
+		}
+	}
+
+	/// Returns when the file was last modified.
+	pub fn modified(&self) -> io::Result<DateTime> {
+		self.metadata.modified().map(DateTime::from)
+	}
+
+	/// Returns when the file was last accessed.
+	pub fn accessed(&self) -> io::Result<DateTime> {
+		self.metadata.accessed().map(DateTime::from)
+	}
+
+	/// Returns when the file's metadata was last changed. (On windows, this is when it was created.)
+	pub fn changed(&self) -> io::Result<DateTime> {
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::MetadataExt;
+			let seconds = self.metadata.ctime() as i128;
+			Ok(DateTime::Absolute(seconds * 1_000_000_000 + self.metadata.ctime_nsec() as i128))
+		}
+
+		#[cfg(not(unix))]
+		{
+			self.created()
+		}
+	}
+
+	/// Returns when the file was created. Not all platforms support this.
+	pub fn created(&self) -> io::Result<DateTime> {
+		self.metadata.created().map(DateTime::from)
+	}
+
+	/// Returns the first `limit` bytes of the contents of the path.
+	pub fn contents(&mut self, limit: u64) -> io::Result<Arc<[u8]>> {
 		if self.contents.contents.is_none() {
-			self.contents.contents = Some(std::fs::read(self.path()._rc())?.into());
+			self.contents.contents =
+				Some(crate::play::read::contents(&self.path()._rc(), limit)?.into());
 		}
 
 		Ok(self.contents.contents.clone().unwrap())
 	}
 
+	/// Sniffs the start of the file to work out its encoding and mime type.
+	pub fn sniff(&mut self) -> io::Result<Sniff> {
+		if self.contents.sniff.is_none() {
+			self.contents.sniff = Some(match self.contents.contents {
+				Some(ref contents) => Sniff::from_start(&contents[..contents.len().min(SNIFF_SIZE)]),
+				None => Sniff::from_path(&self.path()._rc())?,
+			});
+		}
+
+		Ok(self.contents.sniff.unwrap())
+	}
+
 	/// Returns the size of `contents`.
 	pub fn content_size(&self) -> FileSize {
 		FileSize::from_bytes(self.metadata.len())
 	}
 
-	/// Returns whether `self` contains `slice`.
-	pub fn contents_contains(&mut self, slice: &[u8]) -> io::Result<bool> {
-		Ok(crate::slice_contains(&self.contents()?, slice))
+	/// Returns whether the first `limit` bytes of `self` contain `slice`.
+	///
+	/// If the contents haven't already been read, the file is streamed instead, stopping at the
+	/// first match.
+	pub fn contents_contains(&mut self, slice: &[u8], limit: u64) -> io::Result<bool> {
+		match self.contents.contents {
+			Some(ref contents) => Ok(crate::slice_contains(contents, slice)),
+			None => crate::play::read::contains(&self.path()._rc(), slice, limit),
+		}
 	}
 
 	/// Returns the name of the enclosing directory, working on the string itself.
@@ -80,7 +199,7 @@ impl PathInfo {
 	/// - If the path is a relative path of one component, it'll return `.`
 	/// - If the path is the root directory or a windows prefix, it'll return `self.path()`.
 	/// - Otherwise, it'll return the parent directory.
-	pub fn dirname(&self) -> Rc<Path> {
+	pub fn dirname(&self) -> Arc<Path> {
 		debug_assert_ne!(self.path()._rc().to_str(), Some(""));
 
 		match self.path()._rc().parent() {
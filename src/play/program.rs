@@ -1,24 +1,52 @@
-use crate::ast::Expression;
-use crate::play::{Env, PlayContext, PlayResult};
+use crate::ast::{Expression, Reach, Script};
+use crate::parse2::Parser;
+use crate::play::{Env, PathInfo, PlayContext, PlayError, PlayResult};
+use crate::vm::vm::DryRun;
+use crate::vm::{Block, RunError, Vm};
 use crate::Value;
 use os_str_bytes::OsStrBytes;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 mod config;
+mod lines;
+mod walker;
 pub use config::Config;
+use walker::Walker;
 
 #[derive(Default, Debug)]
 pub struct Program {
 	config: Config,
 	env: Env,
 	vars: HashMap<String, Value>,
+	globals: HashMap<String, usize>,
+	dry_run: Option<Arc<Mutex<DryRun>>>,
+	visited: Mutex<HashSet<(u64, u64)>>, // the directories traversed so far, for `--follow`.
+	num_matches: AtomicUsize,
+	reach: Reach, // where the expression being traversed with could match anything.
+	traversed: bool,
+	exit: Option<Option<u8>>, // the status given to `exit`, once it's been called.
 }
 
 impl Program {
 	pub fn new(config: Config, env: Env) -> Self {
-		Self { config, env, vars: Default::default() }
+		// Every `Vm` shares the same plan, so conflicts between begin, main, and end blocks are found.
+		let dry_run = config.vm_config().is_dry().then(Default::default);
+		Self {
+			config,
+			env,
+			vars: Default::default(),
+			globals: Default::default(),
+			dry_run,
+			visited: Default::default(),
+			num_matches: Default::default(),
+			reach: Reach::Anywhere,
+			traversed: false,
+			exit: None,
+		}
 	}
 
 	pub fn assign_var(&mut self, name: &str, value: Value) {
@@ -37,91 +65,394 @@ impl Program {
 		&self.env
 	}
 
+	/// The status the process should exit with, like `grep`'s: the status given to `exit` if it
+	/// was given one, `1` if the main expression didn't match anything, and `0` otherwise.
+	pub fn exit_status(&self) -> u8 {
+		match self.exit {
+			Some(Some(status)) => status,
+			_ if self.traversed && self.num_matches.load(Ordering::Relaxed) == 0 => 1,
+			_ => 0,
+		}
+	}
+
+	/// Runs the file at `path` once, without traversing anything. This is used for `-I` imports.
+	///
+	/// Any global variables (including functions) the file assigns are visible to everything
+	/// that's played afterwards.
 	pub fn run_file(&mut self, path: &Path) -> PlayResult<()> {
-		self.play_expr(&std::fs::read_to_string(path)?)
+		let source = std::fs::read_to_string(path)?;
+		let expr = Parser::new(&source, &self.env)
+			.parse_toplevel()
+			.map_err(|err| err.diagnose(&source).in_file(path))?;
+		if self.config.dumps() {
+			return self.dump(&path.display().to_string(), expr);
+		}
+
+		let result = self.run_once(expr).map_err(|err| err.in_file(path));
+		self.exits(result).map(drop)
+	}
+
+	/// Runs `expr` once with no current file, then exports the globals it assigned.
+	fn run_once(&mut self, expr: Expression) -> PlayResult<()> {
+		let (mut vm, block) = self.compile(expr)?;
+		vm.set_pathinfo(PathInfo::new(".")?);
+		vm.set_root(Path::new(".").into());
+		let result = block.run(&mut vm);
+		self.export_globals(&vm);
+
+		match result {
+			// There's no path to abandon, so `skip` just stops the block early.
+			Ok(_) | Err(RunError::Skip) => Ok(()),
+			Err(err) => Err(err.into()),
+		}
+	}
+
+	/// Returns whether `result` was stopped by `exit`, remembering its status if so. Any other
+	/// errors are returned.
+	fn exits(&mut self, result: PlayResult<()>) -> PlayResult<bool> {
+		match result {
+			Ok(()) => Ok(false),
+			Err(PlayError::RunError(RunError::Exit(status))) => {
+				self.exit = Some(status);
+				Ok(true)
+			}
+			Err(err) => Err(err),
+		}
+	}
+
+	/// Compiles `expr`, giving its globals the values of any variables we already know about.
+	fn compile(&self, expr: Expression) -> PlayResult<(Vm, Block)> {
+		let (mut vm, block) =
+			Vm::compile_with_globals(self.config.vm_config().clone(), expr, self.globals.clone())?;
+
+		for (name, value) in &self.vars {
+			vm.assign_global(name, value.clone());
+		}
+
+		if let Some(ref dry) = self.dry_run {
+			vm.set_dry_run(dry.clone());
+		}
+
+		Ok((vm, block))
+	}
+
+	/// Prints `expr`'s AST and/or bytecode (depending on the config) instead of running it. `what`
+	/// is which part of the script it is.
+	fn dump(&mut self, what: &str, expr: Expression) -> PlayResult<()> {
+		if self.config.dumps_ast() {
+			println!("{what} ast:\n{expr:#?}");
+		}
+
+		if self.config.dumps_bytecode() {
+			let (vm, block) = self.compile(expr)?;
+			// Later parts are compiled with the same globals, so they're dumped with the same slots.
+			self.globals = vm.global_indices().clone();
+			println!("{what} bytecode:\n{}", block.disassemble(vm.global_indices()));
+		}
+
+		Ok(())
+	}
+
+	/// Makes the globals `vm` assigned visible to everything that's compiled afterwards.
+	fn export_globals(&mut self, vm: &Vm) {
+		for (name, value) in vm.globals() {
+			self.vars.insert(name.to_owned(), value.clone());
+		}
+		self.globals = vm.global_indices().clone();
 	}
 
 	pub fn play_expr(&mut self, source: &str) -> PlayResult<()> {
-		let mut lctx = crate::parse::LexContext::new(source, self);
-		let expr = Expression::parse_toplevel(&mut lctx)?;
-		self.play(&expr)
+		let script =
+			Parser::new(source, &self.env).parse_script().map_err(|err| err.diagnose(source))?;
+		self.play(script)
+	}
+
+	/// Plays the script in the file at `path`, which is what `--file` does.
+	pub fn play_file(&mut self, path: &Path) -> PlayResult<()> {
+		let source = std::fs::read_to_string(path)?;
+		let script = Parser::new(&source, &self.env)
+			.parse_script()
+			.map_err(|err| err.diagnose(&source).in_file(path))?;
+		self.play(script).map_err(|err| err.in_file(path))
 	}
 
+	/// Handles everything within the directory `start`, which is `depth` levels down.
 	fn _play<T: AsRef<Path> + ?Sized>(
 		&mut self,
-		vm: &mut crate::vm::Vm,
-		block: &crate::vm::Block,
+		vm: &mut Vm,
+		block: &Block,
 		start: &T,
-	) -> PlayResult<usize> {
-		let mut num_matches = 0;
+		depth: usize,
+	) -> PlayResult<()> {
+		let entries = match std::fs::read_dir(start.as_ref()) {
+			Ok(entries) => entries,
+			// The expression might've just moved or removed the directory.
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+			Err(err) => return Err(err.into()),
+		};
+
+		if !self.config.is_stable() {
+			for entry in entries {
+				let path = match entry {
+					Ok(entry) => entry.path(),
+					Err(err) => {
+						self.config.handle_error(err.into())?;
+						continue;
+					}
+				};
+
+				if !Self::skips(&self.config, vm, &path) {
+					self.handle_entry(path, vm, block, depth + 1)?;
+				}
+			}
+
+			return Ok(());
+		}
 
-		for entry in std::fs::read_dir(start.as_ref())? {
-			num_matches += self.handle(entry?.path(), vm, block, true)?;
+		// For stable traversals, we have to read the entire directory before sorting it.
+		// Bad entries are handled like above, rather than stopping the whole traversal.
+		let mut paths = Vec::new();
+		for entry in entries {
+			match entry {
+				Ok(entry) => paths.push(entry.path()),
+				Err(err) => self.config.handle_error(err.into())?,
+			}
+		}
+		paths.retain(|path| !Self::skips(&self.config, vm, path));
+		self.config.sort_entries(&mut paths);
+
+		for path in paths {
+			self.handle_entry(path, vm, block, depth + 1)?;
 		}
 
-		Ok(num_matches)
+		Ok(())
+	}
+
+	/// Handles `path`, which is within a directory being traversed. Errors are handled according to
+	/// `--ignored-errors`, so an ignored error doesn't stop the rest of the directory.
+	fn handle_entry(
+		&mut self,
+		path: PathBuf,
+		vm: &mut Vm,
+		block: &Block,
+		depth: usize,
+	) -> PlayResult<()> {
+		match self.handle(path, vm, block, true, depth) {
+			Ok(()) => Ok(()),
+			Err(err) => self.config.handle_error(err),
+		}
 	}
 
 	fn handle(
 		&mut self,
 		name: PathBuf,
-		vm: &mut crate::vm::Vm,
-		block: &crate::vm::Block,
+		vm: &mut Vm,
+		block: &Block,
 		recur: bool,
-	) -> PlayResult<usize> {
+		depth: usize,
+	) -> PlayResult<()> {
 		let ctx = PlayContext::new(self, name)?;
 		let pathinfo = ctx.into_pathinfo();
-		vm.set_pathinfo(pathinfo.clone());
-		let matched = block.run(vm).map_or(false, |x| x.is_truthy());
-
-		// Invert `matched` if given the `!` flag.
-		let matched = if self.config.is_inverted() { !matched } else { matched };
-
-		let mut num_matches = 0;
-		if matched {
-			num_matches += 1;
-		}
 
-		if matched && self.config.should_print() {
-			let mut stdout = std::io::stdout().lock();
-			stdout.write_all(&pathinfo.path()._rc().as_os_str().to_raw_bytes())?;
-			self.config.write_line_ending(stdout)?;
+		if Self::visit(&self.config, vm, block, &pathinfo, depth)? {
+			*self.num_matches.get_mut() += 1;
 		}
 
-		if pathinfo.is_dir() && recur {
+		if recur && Self::enters(&self.config, vm, &self.visited, &self.reach, &pathinfo, depth) {
 			// ensure we take it so the rest of the `pathinfo` struct can be dropped
 			let path = pathinfo.path()._rc().clone();
 			drop(pathinfo);
-			match self._play(vm, block, &path) {
-				Ok(match_count) => num_matches += match_count,
-				Err(err) => self.config.handle_error(err)?,
+			if let Err(err) = self._play(vm, block, &path, depth) {
+				self.config.handle_error(err)?;
 			}
 		}
 
-		Ok(num_matches)
+		Ok(())
+	}
+
+	/// Whether to traverse into `pathinfo`, which was just visited at `depth`. Directories aren't
+	/// traversed if the expression called `prune`, they're at `--max-depth`, or nothing within them
+	/// could match the expression's globs.
+	///
+	/// Symlinks are only traversed with `--follow`, in which case each directory is only traversed
+	/// once, so that symlink loops don't go on forever.
+	fn enters(
+		config: &Config,
+		vm: &mut Vm,
+		visited: &Mutex<HashSet<(u64, u64)>>,
+		reach: &Reach,
+		pathinfo: &PathInfo,
+		depth: usize,
+	) -> bool {
+		if vm.take_pruned() || !pathinfo.is_dir() || config.max_depth() <= depth {
+			return false;
+		}
+
+		if !reach.could_enter(&pathinfo.path()._rc()) {
+			return false;
+		}
+
+		if !config.follows_symlinks() {
+			return !pathinfo.is_symlink();
+		}
+
+		pathinfo.file_id().is_none_or(|id| visited.lock().unwrap().insert(id))
+	}
+
+	/// Whether `path` shouldn't be traversed, because it's ignored and we're given `--respect-ignore`.
+	fn skips(config: &Config, vm: &Vm, path: &Path) -> bool {
+		config.respects_ignore() && vm.ignores().is_ignored(path, path.is_dir())
 	}
 
-	pub fn play(&mut self, expr: &Expression) -> PlayResult<()> {
-		let start_positions = match expr.begin_position() {
-			x if x.is_empty() => vec![".".into()],
-			x => x,
+	/// Runs `block` for `pathinfo`, printing the path out if it matched. Returns whether it matched.
+	/// Paths shallower than `--min-depth` are never run, and never match.
+	///
+	/// With `--lines`, the lines it matched are printed instead. Either way, the output is written
+	/// with a single `write_all` so that paths from different jobs never interleave.
+	fn visit(
+		config: &Config,
+		vm: &mut Vm,
+		block: &Block,
+		pathinfo: &PathInfo,
+		depth: usize,
+	) -> PlayResult<bool> {
+		if depth < config.min_depth() {
+			return Ok(false);
+		}
+
+		vm.set_pathinfo(pathinfo.clone());
+
+		// Files whose contents can't be read (such as directories) simply don't match. Any other
+		// error, such as `rm` failing, is reported.
+		let matched = match block.run(vm) {
+			Ok(value) => value.is_truthy(),
+			Err(RunError::Unreadable(_)) => false,
+			// `skip` abandons the path entirely, so it's not printed even with `--invert`.
+			Err(RunError::Skip) => return Ok(false),
+			Err(err) => return Err(err.into()),
 		};
 
-		let (mut vm, block) =
-			crate::vm::Vm::compile(Default::default(), expr.clone()).expect("bad expr");
+		// Invert `matched` if given the `!` flag.
+		let matched = if config.is_inverted() { !matched } else { matched };
 
-		let mut num_matches = 0;
-		if self.config.run_once() {
-			num_matches += self.handle(".".into(), &mut vm, &block, false)?;
-		} else {
-			for start in start_positions {
-				num_matches += self.handle(start.clone(), &mut vm, &block, false)?;
-				num_matches += self._play(&mut vm, &block, &start)?;
+		if matched && config.should_print() {
+			let matches = vm.take_matches();
+			if config.prints_lines() && !matches.is_empty() {
+				let contents = vm.contents()?;
+				lines::print_lines(config, &pathinfo.path()._rc(), &contents, matches)?;
+			} else {
+				let mut line = pathinfo.path()._rc().as_os_str().to_raw_bytes().into_owned();
+				config.write_line_ending(&mut line)?;
+				std::io::stdout().lock().write_all(&line)?;
 			}
 		}
 
+		Ok(matched)
+	}
+
+	/// Runs the begin blocks, then traverses with the main expression, then runs the end blocks.
+	///
+	/// All three share variables: begin blocks can set up variables for the main expression, and
+	/// end blocks can see everything the main expression assigned.
+	///
+	/// Calling `exit` stops whatever's running, a-la awk: exiting before the traversal skips it,
+	/// but the end blocks are always run unless one of them is what exited.
+	///
+	/// With `--dump-ast` or `--dump-bytecode`, each part is printed instead, and nothing is run.
+	pub fn play(&mut self, script: Script) -> PlayResult<()> {
+		if self.config.dumps() {
+			let parts = script.begin.into_iter().map(|begin| ("begin", begin));
+			let parts = parts.chain(script.main.map(|main| ("main", main)));
+			for (what, expr) in parts.chain(script.end.into_iter().map(|end| ("end", end))) {
+				self.dump(what, expr)?;
+			}
+			return Ok(());
+		}
+
+		for begin in script.begin {
+			if self.exit.is_some() {
+				break;
+			}
+
+			let result = self.run_once(begin);
+			self.exits(result)?;
+		}
+
+		if let Some(main) = script.main.filter(|_| self.exit.is_none()) {
+			// Jobs don't share variables, so end blocks wouldn't see what the main expression did.
+			let single_job = !script.end.is_empty();
+			self.traverse(main, single_job)?;
+		}
+
+		for end in script.end {
+			let result = self.run_once(end);
+			if self.exits(result)? {
+				break;
+			}
+		}
+
+		if let Some(ref dry) = self.dry_run {
+			match dry.lock().unwrap().conflicts() {
+				0 => {}
+				1 => eprintln!("1 conflict found"),
+				n => eprintln!("{n} conflicts found"),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Traverses with `expr`. If it calls `exit`, the traversal stops, but matches are still
+	/// counted and its globals are still exported.
+	fn traverse(&mut self, expr: Expression, single_job: bool) -> PlayResult<()> {
+		// If the expression can never match, there's nothing to traverse at all.
+		self.reach = expr.reach(self.config.is_inverted());
+		let mut start_positions = self.reach.roots().into_paths();
+		self.config.sort_entries(&mut start_positions);
+
+		let (mut vm, block) = self.compile(expr)?;
+
+		// Prompts from different jobs would take turns at random, so they'd be impossible to answer.
+		let prompt = self.config.should_prompt();
+		let single_job = single_job
+			|| block.prompts(prompt)
+			|| vm
+				.globals()
+				.any(|(_, value)| matches!(value, Value::Function(func) if func.prompts(prompt)));
+
+		self.traversed = true;
+		let result = self.walk(&mut vm, &block, start_positions, single_job);
+		self.exits(result)?;
+
 		if self.config.is_counting() {
-			println!("{num_matches}");
+			println!("{}", self.num_matches.get_mut());
+		}
+
+		self.export_globals(&vm);
+		Ok(())
+	}
+
+	fn walk(
+		&mut self,
+		vm: &mut Vm,
+		block: &Block,
+		start_positions: Vec<PathBuf>,
+		single_job: bool,
+	) -> PlayResult<()> {
+		if self.config.run_once() {
+			vm.set_root(Path::new(".").into());
+			return self.handle(".".into(), vm, block, false, 0);
+		}
+
+		if self.config.jobs() > 1 && !single_job {
+			return Walker::new(&self.config, block, &self.visited, &self.reach, &self.num_matches)
+				.run(vm, start_positions);
+		}
+
+		// Start positions needn't be directories when there's more than one (eg `a/x || b/y`).
+		for start in start_positions {
+			vm.set_root(start.as_path().into());
+			self.handle(start, vm, block, true, 0)?;
 		}
 
 		Ok(())
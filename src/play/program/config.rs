@@ -1,31 +1,39 @@
+use crate::cli::SortOrder;
 use crate::play::{PlayError, PlayResult};
 use std::io::{self, Write};
+use std::path::PathBuf;
 
-#[derive(Default, Debug)]
+mod sort;
+
+#[derive(Default, Debug, Clone)]
 pub struct Config {
 	dont_print: bool,
 	run_once: bool,
+	dump_ast: bool,
+	dump_bytecode: bool,
 	count: bool,
 	print0: bool,
 	invert: bool,
 	stable: bool,
+	sort: SortOrder,
+	dirs_first: bool,
+	respect_ignore: bool,
+	follow: bool,
+	min_depth: usize,
+	max_depth: Option<usize>,
 	jobs: usize,
 	ignore_permission_errors: bool,
 	ignore_os_errors: bool,
 	ignore_subcommand_errors: bool,
 	prompt: bool,
+	lines: bool,
+	before_context: usize,
+	after_context: usize,
 	colour: bool,
+	vm_config: crate::vm::vm::Config,
 }
 
 fn check_for_unimplemented_features(args: &crate::cli::Args) {
-	macro_rules! check {
-		($($t:ident)*) => {$(
-			if args.$t != Default::default() {
-				unimplemented!("unimplemented option: {}", stringify!($t));
-			}
-		)*};
-	}
-	check!(stable jobs prompt interactive force color);
 	if args.ignored_errors.contains(&crate::cli::IgnoreErrors::Subcommand) {
 		unimplemented!("unimplemented option: ignore subcommands");
 	}
@@ -35,28 +43,51 @@ impl From<&crate::cli::Args> for Config {
 	fn from(args: &crate::cli::Args) -> Self {
 		use crate::cli::{Colour, IgnoreErrors, Prompt};
 		check_for_unimplemented_features(args);
+		let stable = args.stable || args.sort.is_some() || args.dirs_first;
 
 		Self {
-			dont_print: args.dont_print || args.count,
+			dont_print: args.dont_print || args.count || args.dry,
 			count: args.count,
 			print0: args.print0,
 			run_once: args.run_once,
+			dump_ast: args.dump_ast,
+			dump_bytecode: args.dump_bytecode,
 			invert: args.invert,
-			stable: args.stable,
-			jobs: args.jobs.unwrap_or(1),
+			stable,
+			sort: args.sort.unwrap_or_default(),
+			dirs_first: args.dirs_first,
+			respect_ignore: args.respect_ignore,
+			follow: args.follow,
+			min_depth: args.min_depth.unwrap_or(0),
+			max_depth: args.max_depth,
+			jobs: if stable || args.interactive || args.run_once {
+				1 // none of these can be done with more than one job.
+			} else {
+				args.jobs.unwrap_or_else(num_cpus::get).max(1)
+			},
 			ignore_os_errors: args.ignored_errors.contains(&IgnoreErrors::Os),
 			ignore_permission_errors: args.ignored_errors.contains(&IgnoreErrors::Permission),
 			ignore_subcommand_errors: args.ignored_errors.contains(&IgnoreErrors::Subcommand),
-			prompt: match args.prompt {
-				Prompt::Auto => atty::is(atty::Stream::Stdin),
-				Prompt::Always => true,
-				Prompt::Never => true,
+			prompt: if args.interactive {
+				true
+			} else if args.force {
+				false
+			} else {
+				match args.prompt {
+					Prompt::Auto => atty::is(atty::Stream::Stdin),
+					Prompt::Always => true,
+					Prompt::Never => false,
+				}
 			},
+			lines: args.prints_lines(),
+			before_context: args.before_context.or(args.context).unwrap_or(0),
+			after_context: args.after_context.or(args.context).unwrap_or(0),
 			colour: match args.color {
 				Colour::Auto => atty::is(atty::Stream::Stdout),
 				Colour::Always => true,
-				Colour::Never => true,
+				Colour::Never => false,
 			},
+			vm_config: args.into(),
 		}
 	}
 }
@@ -82,21 +113,88 @@ impl Config {
 		self.stable
 	}
 
+	/// Sorts `entries` into the order they should be traversed in. Does nothing unless stable.
+	pub fn sort_entries(&self, entries: &mut [PathBuf]) {
+		if self.stable {
+			sort::sort_entries(entries, self.sort, self.dirs_first);
+		}
+	}
+
+	/// Whether paths ignored by `.gitignore`s should be skipped when traversing.
+	#[must_use]
+	pub fn respects_ignore(&self) -> bool {
+		self.respect_ignore
+	}
+
+	/// Whether symlinked directories should be traversed into.
+	#[must_use]
+	pub fn follows_symlinks(&self) -> bool {
+		self.follow
+	}
+
+	/// How many directories below the start positions paths have to be to be matched.
+	#[must_use]
+	pub fn min_depth(&self) -> usize {
+		self.min_depth
+	}
+
+	/// How many directories below the start positions can be traversed into.
+	#[must_use]
+	pub fn max_depth(&self) -> usize {
+		self.max_depth.unwrap_or(usize::MAX)
+	}
+
 	#[must_use]
 	pub fn should_prompt(&self) -> bool {
 		self.prompt
 	}
 
+	/// Whether matched lines should be printed instead of just paths.
+	#[must_use]
+	pub fn prints_lines(&self) -> bool {
+		self.lines
+	}
+
+	/// How many lines of context to print before and after each matched line.
+	#[must_use]
+	pub fn context(&self) -> (usize, usize) {
+		(self.before_context, self.after_context)
+	}
+
 	#[must_use]
 	pub fn should_colour(&self) -> bool {
 		self.colour
 	}
 
+	/// The config that's given to each [`Vm`](crate::vm::Vm) that's created.
+	#[must_use]
+	pub fn vm_config(&self) -> &crate::vm::vm::Config {
+		&self.vm_config
+	}
+
 	#[must_use]
 	pub fn run_once(&self) -> bool {
 		self.run_once
 	}
 
+	/// Whether the parsed expressions should be printed instead of being run.
+	#[must_use]
+	pub fn dumps_ast(&self) -> bool {
+		self.dump_ast
+	}
+
+	/// Whether the compiled bytecode should be printed instead of being run.
+	#[must_use]
+	pub fn dumps_bytecode(&self) -> bool {
+		self.dump_bytecode
+	}
+
+	/// Whether anything is dumped, in which case nothing is run.
+	#[must_use]
+	pub fn dumps(&self) -> bool {
+		self.dump_ast || self.dump_bytecode
+	}
+
 	/// how many jobs to spawn
 	#[must_use]
 	pub fn jobs(&self) -> usize {
@@ -108,13 +206,13 @@ impl Config {
 		debug_assert!(!self.dont_print);
 
 		if self.print0 {
-			return out.write_all(&[b'\0']);
+			return out.write_all(b"\0");
 		}
 
 		if cfg!(windows) {
-			out.write_all(&[b'\r', b'\n'])
+			out.write_all(b"\r\n")
 		} else {
-			out.write_all(&[b'\n'])
+			out.write_all(b"\n")
 		}
 	}
 
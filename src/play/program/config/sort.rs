@@ -0,0 +1,104 @@
+use crate::cli::SortOrder;
+use os_str_bytes::RawOsStr;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+/// Sorts `entries` by `order`, optionally putting directories before everything else.
+///
+/// Entries are compared by their entire path, so this can be used both for the children of a
+/// single directory and for the start positions of a search.
+pub fn sort_entries(entries: &mut [PathBuf], order: SortOrder, dirs_first: bool) {
+	if !dirs_first {
+		entries.sort_by(|lhs, rhs| compare(lhs, rhs, order));
+		return;
+	}
+
+	// `is_dir` hits the filesystem, so only do it once per entry.
+	let mut keyed =
+		entries.iter_mut().map(|path| (!path.is_dir(), std::mem::take(path))).collect::<Vec<_>>();
+	keyed.sort_by(|(lfile, lhs), (rfile, rhs)| {
+		lfile.cmp(rfile).then_with(|| compare(lhs, rhs, order))
+	});
+
+	for (entry, (_, path)) in entries.iter_mut().zip(keyed) {
+		*entry = path;
+	}
+}
+
+fn compare(lhs: &Path, rhs: &Path, order: SortOrder) -> Ordering {
+	let lhs = RawOsStr::new(lhs.as_os_str());
+	let rhs = RawOsStr::new(rhs.as_os_str());
+
+	match order {
+		SortOrder::Bytes => lhs.cmp(&rhs),
+		SortOrder::Natural => natural_cmp(lhs.as_raw_bytes(), rhs.as_raw_bytes()),
+	}
+}
+
+/// Compares `lhs` and `rhs` bytewise, except that runs of digits are compared by their value.
+///
+/// If two names only differ in their leading zeros (eg `a01` and `a1`), then they're ordered
+/// bytewise so that the ordering is still total.
+fn natural_cmp(lhs: &[u8], rhs: &[u8]) -> Ordering {
+	let (mut l, mut r) = (lhs, rhs);
+
+	while let (Some(&lc), Some(&rc)) = (l.first(), r.first()) {
+		if !lc.is_ascii_digit() || !rc.is_ascii_digit() {
+			match lc.cmp(&rc) {
+				Ordering::Equal => {
+					l = &l[1..];
+					r = &r[1..];
+					continue;
+				}
+				other => return other,
+			}
+		}
+
+		let ldigits = l.iter().take_while(|c| c.is_ascii_digit()).count();
+		let rdigits = r.iter().take_while(|c| c.is_ascii_digit()).count();
+		let lnum = trim_leading_zeros(&l[..ldigits]);
+		let rnum = trim_leading_zeros(&r[..rdigits]);
+
+		// Without leading zeros, a longer run of digits is always a larger number.
+		match lnum.len().cmp(&rnum.len()).then_with(|| lnum.cmp(rnum)) {
+			Ordering::Equal => {
+				l = &l[ldigits..];
+				r = &r[rdigits..];
+			}
+			other => return other,
+		}
+	}
+
+	l.len().cmp(&r.len()).then_with(|| lhs.cmp(rhs))
+}
+
+fn trim_leading_zeros(digits: &[u8]) -> &[u8] {
+	let zeros = digits.iter().take_while(|&&c| c == b'0').count();
+	&digits[zeros..]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn natural_orders_numbers_by_value() {
+		let mut names = ["file10", "file2", "file1", "file", "file02", "a100b", "a20b"];
+		names.sort_by(|l, r| natural_cmp(l.as_bytes(), r.as_bytes()));
+		assert_eq!(names, ["a20b", "a100b", "file", "file1", "file02", "file2", "file10"]);
+	}
+
+	#[test]
+	fn natural_is_total() {
+		assert_eq!(Ordering::Less, natural_cmp(b"a01", b"a1"));
+		assert_eq!(Ordering::Greater, natural_cmp(b"a1", b"a01"));
+		assert_eq!(Ordering::Equal, natural_cmp(b"a1", b"a1"));
+	}
+
+	#[test]
+	fn bytes_ordering() {
+		let mut paths = ["./b", "./B", "./a10", "./a2"].map(PathBuf::from);
+		sort_entries(&mut paths, SortOrder::Bytes, false);
+		assert_eq!(paths, ["./B", "./a10", "./a2", "./b"].map(PathBuf::from));
+	}
+}
@@ -0,0 +1,131 @@
+//! Printing out matched lines for `--lines`, in the same format as ripgrep.
+use super::Config;
+use os_str_bytes::OsStrBytes;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+
+const PATH_COLOUR: &[u8] = b"\x1b[35m";
+const LINE_NUMBER_COLOUR: &[u8] = b"\x1b[32m";
+const MATCH_COLOUR: &[u8] = b"\x1b[1;31m";
+const RESET: &[u8] = b"\x1b[0m";
+
+/// A line within the contents, along with the parts of it that were matched.
+struct Line {
+	span: Range<usize>,
+	matches: Vec<Range<usize>>, // relative to the start of `span`.
+}
+
+/// Splits `contents` into lines, and assigns each of the `matches` to the line it starts on.
+fn lines_with_matches(contents: &[u8], mut matches: Vec<Range<usize>>) -> Vec<Line> {
+	matches.sort_by_key(|range| (range.start, range.end));
+	matches.dedup();
+
+	let mut lines = Vec::new();
+	let mut start = 0;
+	for end in newlines(contents).chain((contents.last() != Some(&b'\n')).then_some(contents.len()))
+	{
+		lines.push(Line { span: start..end, matches: Vec::new() });
+		start = end + 1;
+	}
+
+	let mut idx = 0;
+	for range in matches {
+		while lines[idx].span.end < range.start && idx + 1 < lines.len() {
+			idx += 1;
+		}
+
+		// Matches that span multiple lines are only highlighted on the first one.
+		let line = &mut lines[idx];
+		let end = range.end.min(line.span.end);
+		line.matches.push(range.start - line.span.start..end - line.span.start);
+	}
+
+	lines
+}
+
+fn newlines(contents: &[u8]) -> impl Iterator<Item = usize> + '_ {
+	contents.iter().enumerate().filter(|(_, &c)| c == b'\n').map(|(idx, _)| idx)
+}
+
+/// Prints every line of `contents` which was matched, along with any context around them.
+pub fn print_lines(
+	config: &Config,
+	path: &Path,
+	contents: &[u8],
+	matches: Vec<Range<usize>>,
+) -> io::Result<()> {
+	let (before, after) = config.context();
+	let colour = config.should_colour();
+	let path = path.as_os_str().to_raw_bytes();
+	let lines = lines_with_matches(contents, matches);
+
+	let mut out = Vec::new();
+	let mut next_unprinted = 0; // so context lines aren't printed twice
+	let mut printed_any = false;
+
+	for (idx, line) in lines.iter().enumerate() {
+		if line.matches.is_empty() {
+			continue;
+		}
+
+		let first = idx.saturating_sub(before).max(next_unprinted);
+		if printed_any && first != next_unprinted && (before != 0 || after != 0) {
+			out.extend_from_slice(b"--\n");
+		}
+
+		// Context after a matched line stops at the next matched line, which prints its own.
+		let last = (idx + after).min(lines.len() - 1);
+		let last = (idx + 1..=last).find(|&i| !lines[i].matches.is_empty()).map_or(last, |i| i - 1);
+
+		for (num, line) in lines.iter().enumerate().take(last + 1).skip(first) {
+			let text = &contents[line.span.clone()];
+			let text = text.strip_suffix(b"\r").unwrap_or(text);
+			let separator = if num == idx { b':' } else { b'-' };
+
+			write_coloured(&mut out, colour, PATH_COLOUR, &path);
+			out.push(separator);
+			write_coloured(&mut out, colour, LINE_NUMBER_COLOUR, (num + 1).to_string().as_bytes());
+			out.push(separator);
+
+			if num == idx {
+				write!(out, "{}:", line.matches[0].start + 1)?;
+				write_highlighted(&mut out, colour, text, &line.matches);
+			} else {
+				out.extend_from_slice(text);
+			}
+			out.push(b'\n');
+		}
+
+		next_unprinted = last + 1;
+		printed_any = true;
+	}
+
+	io::stdout().lock().write_all(&out)
+}
+
+fn write_coloured(out: &mut Vec<u8>, colour: bool, code: &[u8], text: &[u8]) {
+	if colour {
+		out.extend_from_slice(code);
+		out.extend_from_slice(text);
+		out.extend_from_slice(RESET);
+	} else {
+		out.extend_from_slice(text);
+	}
+}
+
+/// Writes `text`, highlighting each of `matches` (which are sorted) in it.
+fn write_highlighted(out: &mut Vec<u8>, colour: bool, text: &[u8], matches: &[Range<usize>]) {
+	let mut written = 0;
+	for range in matches {
+		let (start, end) = (range.start.max(written).min(text.len()), range.end.min(text.len()));
+		if end <= start {
+			continue;
+		}
+
+		out.extend_from_slice(&text[written..start]);
+		write_coloured(out, colour, MATCH_COLOUR, &text[start..end]);
+		written = end;
+	}
+	out.extend_from_slice(&text[written..]);
+}
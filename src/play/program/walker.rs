@@ -0,0 +1,197 @@
+use crate::ast::Reach;
+use crate::play::program::{Config, Program};
+use crate::play::{PathInfo, PlayError, PlayResult};
+use crate::vm::{Block, Vm};
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A work-stealing directory walker, used when more than one job is requested.
+///
+/// Every job owns a queue of directories which have yet to be read. Jobs pop from the back of
+/// their own queue (so each job traverses roughly depth-first), and when their queue is empty,
+/// they steal from the front of another job's queue. Each job runs the same compiled [`Block`]
+/// with its own copy of the [`Vm`], so variables assigned in one job aren't visible in others.
+#[derive(Debug)]
+pub struct Walker<'a> {
+	config: &'a Config,
+	block: &'a Block,
+	visited: &'a Mutex<HashSet<(u64, u64)>>,
+	reach: &'a Reach,
+	queues: Vec<Mutex<VecDeque<Dir>>>,
+	pending: AtomicUsize, // how many directories are either queued or currently being read.
+	num_matches: &'a AtomicUsize,
+	failed: AtomicBool,
+	error: Mutex<Option<PlayError>>,
+}
+
+/// A directory that's yet to be read.
+#[derive(Debug)]
+struct Dir {
+	path: PathBuf,
+	root: Arc<Path>, // the start position it's within.
+	depth: usize,
+}
+
+impl<'a> Walker<'a> {
+	pub fn new(
+		config: &'a Config,
+		block: &'a Block,
+		visited: &'a Mutex<HashSet<(u64, u64)>>,
+		reach: &'a Reach,
+		num_matches: &'a AtomicUsize,
+	) -> Self {
+		Self {
+			config,
+			block,
+			visited,
+			reach,
+			queues: (0..config.jobs()).map(|_| Mutex::default()).collect(),
+			pending: AtomicUsize::new(0),
+			num_matches,
+			failed: AtomicBool::new(false),
+			error: Mutex::new(None),
+		}
+	}
+
+	/// Traverses everything within each of `start_positions`, adding how many paths matched to
+	/// `num_matches`.
+	///
+	/// The start positions themselves are run on the current thread before any jobs are spawned,
+	/// which mirrors how the single-threaded traversal works.
+	pub fn run(self, vm: &Vm, start_positions: Vec<PathBuf>) -> PlayResult<()> {
+		let mut vm = vm.clone();
+		for (idx, start) in start_positions.into_iter().enumerate() {
+			let root = Arc::<Path>::from(start.as_path());
+			vm.set_root(root.clone());
+
+			let pathinfo = PathInfo::new(&start)?;
+			if Program::visit(self.config, &mut vm, self.block, &pathinfo, 0)? {
+				self.num_matches.fetch_add(1, Ordering::Relaxed);
+			}
+
+			if Program::enters(self.config, &mut vm, self.visited, self.reach, &pathinfo, 0) {
+				self.enqueue(idx % self.queues.len(), Dir { path: start, root, depth: 0 });
+			}
+		}
+
+		std::thread::scope(|scope| {
+			for id in 0..self.queues.len() {
+				let mut vm = vm.clone();
+				let walker = &self;
+				scope.spawn(move || walker.work(id, &mut vm));
+			}
+		});
+
+		if let Some(err) = self.error.into_inner().unwrap() {
+			return Err(err);
+		}
+
+		Ok(())
+	}
+
+	fn enqueue(&self, id: usize, dir: Dir) {
+		self.pending.fetch_add(1, Ordering::SeqCst);
+		self.queues[id].lock().unwrap().push_back(dir);
+	}
+
+	fn work(&self, id: usize, vm: &mut Vm) {
+		while let Some(dir) = self.next_dir(id) {
+			if let Err(err) = self.read_dir(id, &dir, vm) {
+				self.fail(err);
+			}
+
+			self.pending.fetch_sub(1, Ordering::SeqCst);
+		}
+	}
+
+	/// Gets the next directory for job `id` to read, stealing from other jobs if need be. Returns
+	/// `None` once every queue is empty and no job is still reading a directory.
+	fn next_dir(&self, id: usize) -> Option<Dir> {
+		let amount = self.queues.len();
+
+		loop {
+			if self.failed.load(Ordering::Relaxed) {
+				return None;
+			}
+
+			if let Some(dir) = self.queues[id].lock().unwrap().pop_back() {
+				return Some(dir);
+			}
+
+			for other in (1..amount).map(|offset| (id + offset) % amount) {
+				if let Some(dir) = self.queues[other].lock().unwrap().pop_front() {
+					return Some(dir);
+				}
+			}
+
+			// Nothing's queued, and nobody's reading a directory which could enqueue more.
+			if self.pending.load(Ordering::SeqCst) == 0 {
+				return None;
+			}
+
+			std::thread::yield_now();
+		}
+	}
+
+	fn read_dir(&self, id: usize, dir: &Dir, vm: &mut Vm) -> PlayResult<()> {
+		vm.set_root(dir.root.clone());
+		let depth = dir.depth + 1;
+
+		let entries = match std::fs::read_dir(&dir.path) {
+			Ok(entries) => entries,
+			// The expression might've just moved or removed the directory.
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+			Err(err) => return self.config.handle_error(err.into()),
+		};
+
+		// A bad entry is handled like the single-threaded traversal does, so that it doesn't stop
+		// every other job with it.
+		for entry in entries {
+			let path = match entry {
+				Ok(entry) => entry.path(),
+				Err(err) => {
+					self.config.handle_error(err.into())?;
+					continue;
+				}
+			};
+
+			if Program::skips(self.config, vm, &path) {
+				continue;
+			}
+
+			if let Err(err) = self.visit(id, dir, path, depth, vm) {
+				self.config.handle_error(err)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn visit(
+		&self,
+		id: usize,
+		dir: &Dir,
+		path: PathBuf,
+		depth: usize,
+		vm: &mut Vm,
+	) -> PlayResult<()> {
+		let pathinfo = PathInfo::new(&path)?;
+
+		if Program::visit(self.config, vm, self.block, &pathinfo, depth)? {
+			self.num_matches.fetch_add(1, Ordering::Relaxed);
+		}
+
+		if Program::enters(self.config, vm, self.visited, self.reach, &pathinfo, depth) {
+			self.enqueue(id, Dir { path, root: dir.root.clone(), depth });
+		}
+
+		Ok(())
+	}
+
+	fn fail(&self, err: PlayError) {
+		self.failed.store(true, Ordering::Relaxed);
+		self.error.lock().unwrap().get_or_insert(err);
+	}
+}
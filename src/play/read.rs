@@ -0,0 +1,164 @@
+//! Reading the contents of files without reading more of them than is needed.
+//!
+//! Every function takes a `limit` of how many bytes can be read, for `--max-read`. Anything past
+//! the limit is treated as if it wasn't there, so files are effectively cut off after `limit`
+//! bytes. (For [`tail`], this means it's the last lines of the first `limit` bytes.)
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// How much of a file is read at once when streaming through it.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads the first `limit` bytes of `path`.
+pub fn contents(path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+	let file = File::open(path)?;
+	let capacity = file.metadata().map_or(0, |metadata| metadata.len().min(limit));
+
+	let mut contents = Vec::with_capacity(capacity as usize);
+	file.take(limit).read_to_end(&mut contents)?;
+	Ok(contents)
+}
+
+/// Returns whether the first `limit` bytes of `path` contain `needle`.
+///
+/// The file is read a chunk at a time, and reading stops as soon as `needle` is found. Each chunk
+/// starts with the end of the previous one, so matches which straddle two chunks are still found.
+pub fn contains(path: &Path, needle: &[u8], limit: u64) -> io::Result<bool> {
+	let mut file = File::open(path)?.take(limit);
+	let overlap = needle.len().saturating_sub(1);
+	let mut buf = vec![0; CHUNK_SIZE + overlap];
+	let mut start = 0;
+
+	loop {
+		let read = match file.read(&mut buf[start..]) {
+			Ok(read) => read,
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+			Err(err) => return Err(err),
+		};
+
+		let end = start + read;
+		if crate::slice_contains(&buf[..end], needle) {
+			return Ok(true);
+		}
+
+		if read == 0 {
+			return Ok(false);
+		}
+
+		start = overlap.min(end);
+		buf.copy_within(end - start..end, 0);
+	}
+}
+
+/// Reads the first `count` lines of `path`, including their line endings.
+pub fn head(path: &Path, count: usize, limit: u64) -> io::Result<Vec<u8>> {
+	let mut reader = BufReader::new(File::open(path)?.take(limit));
+	let mut head = Vec::new();
+
+	for _ in 0..count {
+		if reader.read_until(b'\n', &mut head)? == 0 {
+			break;
+		}
+	}
+
+	Ok(head)
+}
+
+/// Reads the last `count` lines of the first `limit` bytes of `path`, including their line endings.
+///
+/// The file is read backwards from the end (or from `limit`) a chunk at a time, until enough lines
+/// have been found.
+pub fn tail(path: &Path, count: usize, limit: u64) -> io::Result<Vec<u8>> {
+	let mut file = File::open(path)?;
+	let mut tail = Vec::new();
+	let mut pos = file.seek(SeekFrom::End(0))?.min(limit);
+
+	while count != 0 && pos != 0 {
+		let size = pos.min(CHUNK_SIZE as u64) as usize;
+		pos -= size as u64;
+
+		let mut chunk = vec![0; size];
+		file.seek(SeekFrom::Start(pos))?;
+		file.read_exact(&mut chunk)?;
+		chunk.append(&mut tail);
+		tail = chunk;
+
+		// The file's trailing newline doesn't start a new line.
+		let body = tail.strip_suffix(b"\n").unwrap_or(&tail);
+		if let Some(idx) = nth_line_start(body, count) {
+			return Ok(tail.split_off(idx));
+		}
+	}
+
+	if count == 0 {
+		tail.clear();
+	}
+
+	Ok(tail)
+}
+
+/// Finds where the `count`th-from-last line of `body` starts, if `body` has that many lines.
+fn nth_line_start(body: &[u8], count: usize) -> Option<usize> {
+	body.iter().enumerate().rev().filter(|(_, &c)| c == b'\n').nth(count - 1).map(|(idx, _)| idx + 1)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn chunked_reads() {
+		let path = std::env::temp_dir().join(format!("ff-read-{}", std::process::id()));
+		let mut contents = vec![b'a'; CHUNK_SIZE - 2];
+		contents.extend_from_slice(b"NEEDLE\nsecond\nthird\n");
+		std::fs::write(&path, &contents).unwrap();
+
+		assert!(contains(&path, b"NEEDLE", u64::MAX).unwrap());
+		assert!(!contains(&path, b"NEEDLE", CHUNK_SIZE as u64).unwrap());
+		assert_eq!(b"second\nthird\n", &tail(&path, 2, u64::MAX).unwrap()[..]);
+		assert_eq!(b"third\n", &tail(&path, 1, u64::MAX).unwrap()[..]);
+		assert_eq!(CHUNK_SIZE + 5, head(&path, 1, u64::MAX).unwrap().len());
+		assert_eq!(b"aaa", &head(&path, 1, 3).unwrap()[..]);
+
+		std::fs::remove_file(path).unwrap();
+	}
+
+	// Files are read as if they ended after `limit` bytes.
+	#[test]
+	fn limits() {
+		let path = std::env::temp_dir().join(format!("ff-read-limits-{}", std::process::id()));
+		let mut contents = b"one\ntwo\nthree\n".to_vec();
+		contents.extend(std::iter::repeat_n(b'x', CHUNK_SIZE * 2));
+		contents.extend_from_slice(b"\nlast\n");
+		std::fs::write(&path, &contents).unwrap();
+		let len = contents.len() as u64;
+
+		assert_eq!(b"one\ntw", &super::contents(&path, 6).unwrap()[..]);
+		assert_eq!(b"", &super::contents(&path, 0).unwrap()[..]);
+		assert_eq!(contents, super::contents(&path, u64::MAX).unwrap());
+		assert_eq!(contents, super::contents(&path, len).unwrap());
+
+		assert!(contains(&path, b"two", 7).unwrap());
+		assert!(!contains(&path, b"two", 6).unwrap());
+		assert!(!contains(&path, b"last", len - 2).unwrap());
+
+		assert_eq!(b"one\ntwo\n", &head(&path, 2, 8).unwrap()[..]);
+		assert_eq!(b"one\ntw", &head(&path, 2, 6).unwrap()[..]);
+		assert_eq!(b"one\n", &head(&path, 5, 4).unwrap()[..]);
+
+		assert_eq!(b"two\nthree\n", &tail(&path, 2, 14).unwrap()[..]);
+		assert_eq!(b"xx", &tail(&path, 1, 16).unwrap()[..]);
+		assert_eq!(b"one\ntw", &tail(&path, 5, 6).unwrap()[..]);
+		assert_eq!(b"", &tail(&path, 5, 0).unwrap()[..]);
+		assert_eq!(b"last\n", &tail(&path, 1, u64::MAX).unwrap()[..]);
+
+		// Lines longer than a chunk are still read in full.
+		let long = tail(&path, 1, len - b"last\n".len() as u64).unwrap();
+		assert_eq!(CHUNK_SIZE * 2 + 1, long.len());
+		assert_eq!(b"three\n", &tail(&path, 2, len - 6).unwrap()[..6]);
+
+		std::fs::remove_file(path).unwrap();
+	}
+}
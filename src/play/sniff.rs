@@ -0,0 +1,251 @@
+//! Working out what kind of file something is by looking at the start of it, a-la `file(1)`.
+
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::Path;
+
+/// How much of the start of a file is looked at.
+pub const SNIFF_SIZE: usize = 8 * 1024;
+
+/// What a file is, according to its first [`SNIFF_SIZE`] bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sniff {
+	pub encoding: Encoding,
+	pub mime: &'static str,
+}
+
+/// How a file's text is encoded, or [`Encoding::Binary`] if it isn't text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+	/// The file's empty.
+	Empty,
+	Ascii,
+	Utf8,
+	Utf16Le,
+	Utf16Be,
+
+	/// Not valid UTF-8, but without anything that suggests it's binary; probably a legacy encoding
+	/// such as latin-1.
+	Unknown,
+	Binary,
+}
+
+impl Encoding {
+	/// Works out the encoding of `start`, which is the start of a file. `truncated` is whether the
+	/// file continues past `start`, in which case the last character might've been cut off.
+	pub fn detect(start: &[u8], truncated: bool) -> Self {
+		if start.is_empty() {
+			return Self::Empty;
+		}
+
+		if let Some(rest) = start.strip_prefix(b"\xEF\xBB\xBF") {
+			return if is_utf8(rest, truncated) { Self::Utf8 } else { Self::Unknown };
+		}
+
+		match start {
+			[0xFF, 0xFE, ..] => return Self::Utf16Le,
+			[0xFE, 0xFF, ..] => return Self::Utf16Be,
+			_ => {}
+		}
+
+		if start.contains(&0) {
+			return utf16_without_bom(start).unwrap_or(Self::Binary);
+		}
+
+		if start.is_ascii() {
+			Self::Ascii
+		} else if is_utf8(start, truncated) {
+			Self::Utf8
+		} else {
+			Self::Unknown
+		}
+	}
+
+	pub fn is_binary(self) -> bool {
+		self == Self::Binary
+	}
+
+	pub fn is_text(self) -> bool {
+		!matches!(self, Self::Empty | Self::Binary)
+	}
+
+	pub const fn to_str(self) -> &'static str {
+		match self {
+			Self::Empty => "empty",
+			Self::Ascii => "ascii",
+			Self::Utf8 => "utf-8",
+			Self::Utf16Le => "utf-16le",
+			Self::Utf16Be => "utf-16be",
+			Self::Unknown => "unknown",
+			Self::Binary => "binary",
+		}
+	}
+}
+
+impl Display for Encoding {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		f.write_str(self.to_str())
+	}
+}
+
+fn is_utf8(source: &[u8], truncated: bool) -> bool {
+	match std::str::from_utf8(source) {
+		Ok(_) => true,
+		// `error_len` is `None` when the input ends partway through a character.
+		Err(err) => truncated && err.error_len().is_none(),
+	}
+}
+
+/// UTF-16 text that's mostly ASCII has a NUL in every other byte.
+fn utf16_without_bom(start: &[u8]) -> Option<Encoding> {
+	let pairs = start.chunks_exact(2);
+	let len = pairs.len();
+	let (mut evens, mut odds) = (0, 0);
+
+	for pair in pairs {
+		match *pair {
+			[0, c] if c != 0 => evens += 1,
+			[c, 0] if c != 0 => odds += 1,
+			_ => {}
+		}
+	}
+
+	// Allow for a handful of non-ASCII characters.
+	if len != 0 && odds * 10 >= len * 9 {
+		Some(Encoding::Utf16Le)
+	} else if len != 0 && evens * 10 >= len * 9 {
+		Some(Encoding::Utf16Be)
+	} else {
+		None
+	}
+}
+
+/// Magic bytes, the offset they're at, and the mime type of files which have them.
+const MAGIC: &[(usize, &[u8], &str)] = &[
+	(0, b"\x7FELF", "application/x-elf"),
+	(0, b"\xCF\xFA\xED\xFE", "application/x-mach-binary"),
+	(0, b"\xCE\xFA\xED\xFE", "application/x-mach-binary"),
+	(0, b"\xCA\xFE\xBA\xBE", "application/java-vm"),
+	(0, b"\0asm", "application/wasm"),
+	(0, b"\x89PNG\r\n\x1A\n", "image/png"),
+	(0, b"\xFF\xD8\xFF", "image/jpeg"),
+	(0, b"GIF87a", "image/gif"),
+	(0, b"GIF89a", "image/gif"),
+	(0, b"\0\0\x01\0", "image/x-icon"),
+	(0, b"II*\0", "image/tiff"),
+	(0, b"MM\0*", "image/tiff"),
+	(8, b"WEBP", "image/webp"),
+	(8, b"WAVE", "audio/wav"),
+	(8, b"AVI ", "video/x-msvideo"),
+	(0, b"ID3", "audio/mpeg"),
+	(0, b"OggS", "audio/ogg"),
+	(0, b"fLaC", "audio/flac"),
+	(4, b"ftyp", "video/mp4"),
+	(0, b"\x1A\x45\xDF\xA3", "video/webm"),
+	(0, b"%PDF-", "application/pdf"),
+	(0, b"%!PS", "application/postscript"),
+	(0, b"PK\x03\x04", "application/zip"),
+	(0, b"PK\x05\x06", "application/zip"),
+	(0, b"\x1F\x8B", "application/gzip"),
+	(0, b"BZh", "application/x-bzip2"),
+	(0, b"\xFD7zXZ\0", "application/x-xz"),
+	(0, b"\x28\xB5\x2F\xFD", "application/zstd"),
+	(0, b"7z\xBC\xAF\x27\x1C", "application/x-7z-compressed"),
+	(0, b"Rar!\x1A\x07", "application/vnd.rar"),
+	(257, b"ustar", "application/x-tar"),
+	(0, b"SQLite format 3\0", "application/vnd.sqlite3"),
+	(0, b"MZ", "application/vnd.microsoft.portable-executable"),
+	(0, b"wOFF", "font/woff"),
+	(0, b"wOF2", "font/woff2"),
+	(0, b"\0\x01\0\0\0", "font/ttf"),
+	(0, b"OTTO", "font/otf"),
+];
+
+/// Prefixes of text files, matched case-insensitively after any leading whitespace.
+const TEXT_MAGIC: &[(&[u8], &str)] = &[
+	(b"<!doctype html", "text/html"),
+	(b"<html", "text/html"),
+	(b"<svg", "image/svg+xml"),
+	(b"<?xml", "application/xml"),
+	(b"#!", "text/x-script"),
+];
+
+impl Sniff {
+	const DIRECTORY: Self = Self { encoding: Encoding::Empty, mime: "inode/directory" };
+
+	/// Sniffs `start`, which is the first [`SNIFF_SIZE`] bytes of a file (or all of it, if it's
+	/// shorter than that).
+	pub fn from_start(start: &[u8]) -> Self {
+		let encoding = Encoding::detect(start, start.len() >= SNIFF_SIZE);
+		Self { encoding, mime: mime(start, encoding) }
+	}
+
+	/// Sniffs the file at `path`. Directories are never binary, and have the mime type
+	/// `inode/directory`.
+	pub fn from_path(path: &Path) -> io::Result<Self> {
+		if path.is_dir() {
+			return Ok(Self::DIRECTORY);
+		}
+
+		Ok(Self::from_start(&crate::play::read::contents(path, SNIFF_SIZE as u64)?))
+	}
+}
+
+fn mime(start: &[u8], encoding: Encoding) -> &'static str {
+	if encoding == Encoding::Empty {
+		return "inode/x-empty";
+	}
+
+	let has_magic = |&&(offset, magic, _): &&(usize, &[u8], &str)| {
+		start.get(offset..).is_some_and(|rest| rest.starts_with(magic))
+	};
+
+	// Short magic bytes which could just as well start a text file (such as `MZ` or `BZh`) only
+	// count in binary files.
+	if let Some((_, magic, mime)) = MAGIC.iter().find(has_magic) {
+		if !encoding.is_text() || !magic.is_ascii() || magic.len() >= 4 {
+			return mime;
+		}
+	}
+
+	if !encoding.is_text() {
+		return "application/octet-stream";
+	}
+
+	let trimmed = start.trim_ascii_start();
+	for (prefix, mime) in TEXT_MAGIC {
+		if trimmed.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(prefix)) {
+			return mime;
+		}
+	}
+
+	"text/plain"
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sniffs() {
+		let sniff = |start: &[u8]| Sniff::from_start(start);
+		assert_eq!(Encoding::Empty, sniff(b"").encoding);
+		assert_eq!(Encoding::Ascii, sniff(b"hello\n").encoding);
+		assert_eq!(Encoding::Utf8, sniff("héllo".as_bytes()).encoding);
+		assert_eq!(Encoding::Unknown, sniff(b"caf\xE9").encoding);
+		assert_eq!(Encoding::Utf16Le, sniff(b"h\0i\0\n\0").encoding);
+		assert_eq!(Encoding::Utf16Be, sniff(b"\xFE\xFF\0h").encoding);
+		assert_eq!(Encoding::Binary, sniff(b"\x7FELF\x02\x01\0\0").encoding);
+
+		assert_eq!("image/png", sniff(b"\x89PNG\r\n\x1A\n\0\0").mime);
+		assert_eq!("application/x-elf", sniff(b"\x7FELF\x02\x01\0\0").mime);
+		assert_eq!("text/html", sniff(b"  <!DOCTYPE html>").mime);
+		assert_eq!("text/plain", sniff(b"MZ is also text").mime);
+		assert_eq!("application/octet-stream", sniff(b"\x01\x02\0\0\x03").mime);
+
+		// The last character being cut off shouldn't make it invalid.
+		let mut start = vec![b'a'; SNIFF_SIZE - 1];
+		start.push(0xC3);
+		assert_eq!(Encoding::Utf8, sniff(&start).encoding);
+	}
+}
@@ -14,6 +14,12 @@ impl PartialEq for Regex {
 #[derive(Debug)]
 pub struct RegexParseError(fancy_regex::Error);
 
+impl Display for RegexParseError {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		Display::fmt(&self.0, f)
+	}
+}
+
 impl Regex {
 	pub fn new(source: &[u8], flags: &[u8]) -> Result<Self, RegexParseError> {
 		let source = std::str::from_utf8(source).expect("todo: non-utf8 regexes");
@@ -36,6 +42,15 @@ impl Regex {
 		self.0.is_match(source).unwrap_or(false)
 	}
 
+	/// Finds the byte ranges of every match in `source`.
+	pub fn find_all(&self, source: &[u8]) -> Vec<std::ops::Range<usize>> {
+		let Ok(source) = std::str::from_utf8(source) else {
+			return Vec::new(); // todo: handle non-`&[u8]` regexes
+		};
+
+		self.0.find_iter(source).filter_map(Result::ok).map(|m| m.range()).collect()
+	}
+
 	// pub fn capture_names(&self) -> impl Iterator<Item = &str> {
 	// 	self.0.capture_names().skip(1).filter_map(std::convert::identity)
 	// }
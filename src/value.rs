@@ -1,23 +1,90 @@
-use crate::vm::{RunError, RunResult, Vm};
-use crate::{FileSize, PathGlob, Regex};
+use crate::vm::{Block, RunError, RunResult, Vm};
+use crate::{DateTime, FileSize, PathGlob, Regex};
 use os_str_bytes::OsStrBytes;
 use os_str_bytes::RawOsStr;
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::ffi::OsStr;
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::path::Path;
-use std::rc::Rc;
+use std::sync::Arc;
 
 mod assoc_array;
 pub use assoc_array::AssocArray;
 
+/// The longest string that can be created by repeating a string, such as `"a" * 1e18`.
+pub const MAX_REPEAT_LEN: usize = 1 << 30;
+
+/// The most numbers `range` can create, as they're all created up front.
+pub const MAX_RANGE_LEN: usize = 1 << 22;
+
+/// Returns `divisor`, unless it's zero.
+fn nonzero<T: Default + PartialEq>(divisor: T) -> RunResult<T> {
+	if divisor == T::default() {
+		return Err(RunError::DivisionByZero);
+	}
+
+	Ok(divisor)
+}
+
+/// A relative time of `nanos`, which was computed by `func`, unless it's too far away to be one.
+fn relative_time(nanos: f64, func: &'static str) -> RunResult<Value> {
+	if nanos.abs() >= i128::MAX as f64 {
+		return Err(RunError::TooLarge { func });
+	}
+
+	Ok(DateTime::Relative(nanos as i128).into())
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
 	AssocArray(AssocArray),
-	Text(Rc<[u8]>),
+	Text(Arc<[u8]>),
 	Number(f64),
-	Path(Rc<Path>),
+	Path(Arc<Path>),
 	PathGlob(PathGlob),
 	FileSize { fs: FileSize, precision: u8 },
+	DateTime(DateTime),
 	Regex(Regex),
+	Function(Arc<Block>),
+}
+
+// Numbers are the only values which aren't reflexive (`NaN != NaN`), but that's fine for keys.
+impl Eq for Value {}
+
+impl Hash for Value {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		std::mem::discriminant(self).hash(state);
+
+		match self {
+			Self::Text(text) => text.hash(state),
+			Self::Number(num) => (num + 0.0).to_bits().hash(state), // `+ 0.0` converts `-0.0` to `0.0`
+			Self::Path(path) => path.hash(state),
+			Self::FileSize { fs, precision } => (fs, precision).hash(state),
+			Self::DateTime(datetime) => datetime.hash(state),
+
+			// These are rarely used as keys, so hashing just what kind of value they are is enough.
+			Self::AssocArray(_) | Self::PathGlob(_) | Self::Regex(_) | Self::Function(_) => {}
+		}
+	}
+}
+
+impl Display for Value {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Self::AssocArray(ary) => Display::fmt(ary, f),
+			Self::Text(text) => write!(f, "{}", String::from_utf8_lossy(text)),
+			Self::Number(num) if num.fract() == 0.0 && num.abs() < 1e15 => write!(f, "{num:.0}"),
+			Self::Number(num) => write!(f, "{num}"),
+			Self::Path(path) => write!(f, "{}", path.display()),
+			Self::PathGlob(glob) => write!(f, "{glob:?}"),
+			Self::FileSize { fs, .. } => Display::fmt(fs, f),
+			Self::DateTime(datetime) => Display::fmt(datetime, f),
+			Self::Regex(regex) => write!(f, "$/{regex}/"),
+			Self::Function(_) => f.write_str("<function>"),
+		}
+	}
 }
 
 impl Default for Value {
@@ -33,20 +100,45 @@ impl Value {
 			Self::Number(v) => *v != 0.0,
 			Self::AssocArray(ary) => !ary.is_empty(),
 			Self::FileSize { fs, precision: _ } => !fs.is_empty(),
-			Self::Path(_path) => todo!(),
-			Self::PathGlob(_glob) => todo!(),
-			Self::Regex(_regex) => todo!(),
+			Self::Path(path) => !path.as_os_str().is_empty(),
+			Self::DateTime(_) | Self::PathGlob(_) | Self::Regex(_) | Self::Function(_) => true,
 		}
 	}
 
 	pub fn logical(&self, vm: &mut Vm) -> RunResult<bool> {
+		if matches!(self, Self::Text(_) | Self::Regex(_)) && vm.skips_contents()? {
+			return Ok(false);
+		}
+
 		match self {
-			Self::Text(v) => Ok(vm.info_mut().contents_contains(v)?),
+			Self::Text(v) if vm.config().should_record_matches() => {
+				let contents = vm.contents()?;
+				let mut found = false;
+				for range in crate::slice_find_all(&contents, v) {
+					vm.record_match(range);
+					found = true;
+				}
+				Ok(found)
+			}
+			Self::Text(v) => Ok(vm.contents_contains(v)?),
 			Self::PathGlob(glob) => Ok(glob.is_match(&vm.info().path()._rc())),
+			Self::Path(path) => Ok(PathGlob::parse(path)?.is_match(&vm.info().path()._rc())),
 			Self::FileSize { fs, precision } => {
 				Ok(fs.fuzzy_matches(vm.info().content_size(), *precision))
 			}
-			Self::Regex(regex) => Ok(regex.is_match(&vm.info_mut().contents()?)),
+			// Unlike strings, regexes are matched against the whole of the contents rather than
+			// streaming through them: a match can span any amount of the file, and fancy-regex
+			// (which is needed for lookaround and backreferences) only searches whole `&str`s.
+			// `--max-read` is what bounds how much gets buffered.
+			Self::Regex(regex) if vm.config().should_record_matches() => {
+				let matches = regex.find_all(&vm.contents()?);
+				let found = !matches.is_empty();
+				for range in matches {
+					vm.record_match(range);
+				}
+				Ok(found)
+			}
+			Self::Regex(regex) => Ok(regex.is_match(&vm.contents()?)),
 			_other => Ok(self.is_truthy()),
 		}
 	}
@@ -56,13 +148,24 @@ impl Value {
 			(Self::FileSize { fs: lhs, precision }, Self::FileSize { fs: rhs, .. }) => {
 				Ok(lhs.fuzzy_matches(*rhs, *precision))
 			}
-			(Self::Regex(regex), Self::Text(rhs)) => Ok(regex.is_match(&rhs)),
-			(Self::Text(needle), Self::Text(haystack)) => Ok(crate::slice_contains(haystack, needle)),
-			(Self::PathGlob(glob), Self::Path(path)) => Ok(glob.is_match(&path)),
+			(Self::Regex(regex), Self::Text(rhs)) | (Self::Text(rhs), Self::Regex(regex)) => {
+				Ok(regex.is_match(rhs))
+			}
+			(Self::Text(haystack), Self::Text(needle)) => Ok(crate::slice_contains(haystack, needle)),
+			(Self::PathGlob(glob), Self::Path(path)) => Ok(glob.is_match(path)),
 			(Self::PathGlob(glob), Self::Text(path)) => {
 				Ok(glob.is_match(std::path::Path::new(&OsStr::assert_from_raw_bytes(path.as_ref()))))
 			}
-			_ => todo!(),
+			_ => Err(self.invalid_types("=~", rhs)),
+		}
+	}
+
+	/// Converts `self` to the bytes it's interpolated as.
+	pub fn to_bytes(&self) -> Cow<'_, [u8]> {
+		match self {
+			Self::Text(text) => Cow::Borrowed(text),
+			Self::Path(path) => path.as_os_str().to_raw_bytes(),
+			other => Cow::Owned(other.to_string().into_bytes()),
 		}
 	}
 
@@ -74,53 +177,179 @@ impl Value {
 			Self::Path(_) => "path",
 			Self::PathGlob(_) => "pathglob",
 			Self::FileSize { .. } => "filesize",
+			Self::DateTime(_) => "datetime",
 			Self::Regex(_) => "regex",
+			Self::Function(_) => "function",
 		}
 	}
 
 	pub fn negate(&self) -> RunResult<Self> {
 		match self {
 			Self::Number(num) => Ok(Self::Number(-num)),
+			Self::DateTime(DateTime::Relative(offset)) => {
+				let offset = offset.checked_neg().ok_or(RunError::TooLarge { func: "unary-" })?;
+				Ok(DateTime::Relative(offset).into())
+			}
 			_ => Err(RunError::InvalidType { func: "unary-", given: self.typename() }),
 		}
 	}
 
+	/// The error for when `func` doesn't support `self` and `rhs` as its operands.
+	fn invalid_types(&self, func: &'static str, rhs: &Self) -> RunError {
+		RunError::InvalidTypes { func, lhs: self.typename(), rhs: rhs.typename() }
+	}
+
 	pub fn add(&self, rhs: &Self) -> RunResult<Self> {
 		match (self, rhs) {
 			(Self::Number(lhs), Self::Number(rhs)) => Ok((lhs + rhs).into()),
-			_ => todo!("{:?} {:?}", self, rhs),
+			(Self::Text(lhs), Self::Text(rhs)) => Ok(Self::Text([&lhs[..], rhs].concat().into())),
+			(Self::FileSize { fs: lhs, .. }, Self::FileSize { fs: rhs, .. }) => {
+				Ok(FileSize::from_bytes(lhs.bytes().saturating_add(rhs.bytes())).into())
+			}
+			(Self::DateTime(lhs), Self::DateTime(datetime)) => match lhs.checked_add(*datetime) {
+				Some(sum) => Ok(sum.into()),
+				None if lhs.is_relative() || datetime.is_relative() => {
+					Err(RunError::TooLarge { func: "+" })
+				}
+				None => Err(self.invalid_types("+", rhs)),
+			},
+
+			// `path + "/sub"` and `path + "sub"` are both `path/sub`.
+			(Self::Path(lhs), Self::Text(_) | Self::Path(_)) => {
+				let rhs = rhs.to_bytes();
+				let rhs = rhs.strip_prefix(std::path::MAIN_SEPARATOR_STR.as_bytes()).unwrap_or(&rhs);
+				Ok(lhs.join(OsStr::assert_from_raw_bytes(rhs)).as_path().into())
+			}
+			_ => Err(self.invalid_types("+", rhs)),
 		}
 	}
 
-	pub fn subtract(&self, _rhs: &Self) -> RunResult<Self> {
-		todo!()
+	pub fn subtract(&self, rhs: &Self) -> RunResult<Self> {
+		match (self, rhs) {
+			(Self::Number(lhs), Self::Number(rhs)) => Ok((lhs - rhs).into()),
+			(Self::FileSize { fs: lhs, .. }, Self::FileSize { fs: rhs, .. }) => {
+				Ok(FileSize::from_bytes(lhs.bytes().saturating_sub(rhs.bytes())).into())
+			}
+			(Self::DateTime(lhs), Self::DateTime(datetime)) => match lhs.checked_sub(*datetime) {
+				Some(difference) => Ok(difference.into()),
+				None if !lhs.is_relative() || datetime.is_relative() => {
+					Err(RunError::TooLarge { func: "-" })
+				}
+				None => Err(self.invalid_types("-", rhs)),
+			},
+			_ => Err(self.invalid_types("-", rhs)),
+		}
 	}
 
-	pub fn multiply(&self, _rhs: &Self) -> RunResult<Self> {
-		todo!()
+	pub fn multiply(&self, rhs: &Self) -> RunResult<Self> {
+		match (self, rhs) {
+			(Self::Number(lhs), Self::Number(rhs)) => Ok((lhs * rhs).into()),
+			(Self::FileSize { fs, .. }, Self::Number(num))
+			| (Self::Number(num), Self::FileSize { fs, .. }) => {
+				Ok(FileSize::from_bytes((fs.bytes() as f64 * num) as u64).into())
+			}
+			(Self::DateTime(DateTime::Relative(offset)), Self::Number(num))
+			| (Self::Number(num), Self::DateTime(DateTime::Relative(offset))) => {
+				relative_time(*offset as f64 * num, "*")
+			}
+
+			// `"ab" * 3` is `"ababab"`.
+			(Self::Text(text), Self::Number(num)) | (Self::Number(num), Self::Text(text)) => {
+				let count = num.max(0.0) as usize;
+				if text.len().saturating_mul(count) > MAX_REPEAT_LEN {
+					return Err(RunError::TooLarge { func: "*" });
+				}
+				Ok(Self::Text(text.repeat(count).into()))
+			}
+			_ => Err(self.invalid_types("*", rhs)),
+		}
 	}
 
-	pub fn divide(&self, _rhs: &Self) -> RunResult<Self> {
-		todo!()
+	pub fn divide(&self, rhs: &Self) -> RunResult<Self> {
+		match (self, rhs) {
+			(Self::Number(lhs), Self::Number(rhs)) => Ok((lhs / nonzero(*rhs)?).into()),
+			(Self::FileSize { fs, .. }, Self::Number(num)) => {
+				Ok(FileSize::from_bytes((fs.bytes() as f64 / nonzero(*num)?) as u64).into())
+			}
+			// `size / 1mb` is how many megabytes `size` is.
+			(Self::FileSize { fs: lhs, .. }, Self::FileSize { fs: rhs, .. }) => {
+				Ok((lhs.bytes() as f64 / nonzero(rhs.bytes())? as f64).into())
+			}
+			(Self::DateTime(DateTime::Relative(offset)), Self::Number(num)) => {
+				relative_time(*offset as f64 / nonzero(*num)?, "/")
+			}
+			(Self::DateTime(DateTime::Relative(lhs)), Self::DateTime(DateTime::Relative(rhs))) => {
+				Ok((*lhs as f64 / nonzero(*rhs)? as f64).into())
+			}
+			_ => Err(self.invalid_types("/", rhs)),
+		}
 	}
 
-	pub fn modulo(&self, _rhs: &Self) -> RunResult<Self> {
-		todo!()
+	pub fn modulo(&self, rhs: &Self) -> RunResult<Self> {
+		match (self, rhs) {
+			(Self::Number(lhs), Self::Number(rhs)) => Ok((lhs % nonzero(*rhs)?).into()),
+			(Self::FileSize { fs: lhs, .. }, Self::FileSize { fs: rhs, .. }) => {
+				Ok(FileSize::from_bytes(lhs.bytes() % nonzero(rhs.bytes())?).into())
+			}
+			(Self::DateTime(DateTime::Relative(lhs)), Self::DateTime(DateTime::Relative(rhs))) => {
+				// `i128::MIN % -1` overflows, even though the remainder is zero.
+				Ok(DateTime::Relative(lhs.checked_rem(nonzero(*rhs)?).unwrap_or(0)).into())
+			}
+			_ => Err(self.invalid_types("%", rhs)),
+		}
 	}
 
-	pub fn compare(&self, rhs: &Self) -> RunResult<std::cmp::Ordering> {
+	pub fn compare(&self, rhs: &Self) -> RunResult<Ordering> {
 		match (self, rhs) {
+			// `NaN`s are only comparable to themselves.
 			(Self::Number(lhs), Self::Number(rhs)) => {
-				Ok(lhs.partial_cmp(&rhs).expect("todo: handle NaN <=> NaN"))
+				Ok(lhs.partial_cmp(rhs).unwrap_or_else(|| lhs.total_cmp(rhs)))
 			}
-			(Self::FileSize { fs: lhs, .. }, Self::FileSize { fs: rhs, .. }) => Ok(lhs.cmp(&rhs)),
+			(Self::FileSize { fs: lhs, .. }, Self::FileSize { fs: rhs, .. }) => Ok(lhs.cmp(rhs)),
+			(Self::FileSize { fs, .. }, Self::Number(num)) => Ok((fs.bytes() as f64).total_cmp(num)),
+			(Self::Number(num), Self::FileSize { fs, .. }) => Ok(num.total_cmp(&(fs.bytes() as f64))),
 			(Self::Text(lhs), Self::Text(rhs)) => Ok(lhs.cmp(rhs)),
-			_ => todo!("{:?} {:?}", self, rhs),
+			(Self::Path(lhs), Self::Path(rhs)) => Ok(lhs.cmp(rhs)),
+			(Self::Path(_), Self::Text(_)) | (Self::Text(_), Self::Path(_)) => {
+				Ok(self.to_bytes().cmp(&rhs.to_bytes()))
+			}
+			(Self::DateTime(lhs), Self::DateTime(rhs)) => Ok(lhs.compare(*rhs)),
+			_ => Err(self.invalid_types("<=>", rhs)),
+		}
+	}
+
+	/// Whether `self` and `rhs` are equal. Unlike [`compare`](Self::compare), values that can't be
+	/// compared are simply not equal.
+	pub fn equals(&self, rhs: &Self) -> bool {
+		match self.compare(rhs) {
+			Ok(ordering) => ordering.is_eq(),
+			Err(_) => self == rhs,
 		}
 	}
 
-	pub fn call(&self, _args: &[Self]) -> RunResult<Self> {
-		todo!();
+	/// Gets the value at `key`; like variables, missing keys are the default value.
+	pub fn index(&self, key: &Self) -> RunResult<Self> {
+		match self {
+			Self::AssocArray(ary) => Ok(ary.get(key).unwrap_or_default()),
+			_ => Err(RunError::InvalidType { func: "[]", given: self.typename() }),
+		}
+	}
+
+	pub fn set_index(&self, key: Self, value: Self) -> RunResult<()> {
+		match self {
+			Self::AssocArray(ary) => {
+				ary.insert(key, value);
+				Ok(())
+			}
+			_ => Err(RunError::InvalidType { func: "[]=", given: self.typename() }),
+		}
+	}
+
+	pub fn call(&self, vm: &mut Vm, args: Vec<Self>) -> RunResult<Self> {
+		match self {
+			Self::Function(block) => block.call(vm, args),
+			_ => Err(RunError::InvalidType { func: "call", given: self.typename() }),
+		}
 	}
 }
 
@@ -140,27 +369,33 @@ impl From<FileSize> for Value {
 	}
 }
 
-impl From<Rc<[u8]>> for Value {
-	fn from(text: Rc<[u8]>) -> Self {
+impl From<DateTime> for Value {
+	fn from(datetime: DateTime) -> Self {
+		Self::DateTime(datetime)
+	}
+}
+
+impl From<Arc<[u8]>> for Value {
+	fn from(text: Arc<[u8]>) -> Self {
 		Self::Text(text)
 	}
 }
 
-impl From<Rc<Path>> for Value {
-	fn from(path: Rc<Path>) -> Self {
+impl From<Arc<Path>> for Value {
+	fn from(path: Arc<Path>) -> Self {
 		Self::Path(path)
 	}
 }
 
 impl From<&Path> for Value {
 	fn from(path: &Path) -> Self {
-		Self::Path(Rc::from(path.to_owned()))
+		Self::Path(Arc::from(path.to_owned()))
 	}
 }
 
 impl From<&OsStr> for Value {
 	fn from(osstr: &OsStr) -> Self {
-		Self::Text(osstr.to_raw_bytes().to_owned().into())
+		Self::Text(osstr.to_raw_bytes().into_owned().into())
 	}
 }
 
@@ -170,8 +405,118 @@ impl From<&RawOsStr> for Value {
 	}
 }
 
+impl From<AssocArray> for Value {
+	fn from(ary: AssocArray) -> Self {
+		Self::AssocArray(ary)
+	}
+}
+
 impl From<f64> for Value {
 	fn from(num: f64) -> Self {
 		Self::Number(num)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn num(num: f64) -> Value {
+		Value::Number(num)
+	}
+
+	fn text(text: &str) -> Value {
+		Value::Text(text.as_bytes().into())
+	}
+
+	fn size(bytes: u64) -> Value {
+		FileSize::from_bytes(bytes).into()
+	}
+
+	fn relative(offset: i128) -> Value {
+		DateTime::Relative(offset).into()
+	}
+
+	#[test]
+	fn is_truthy() {
+		assert!(!num(0.0).is_truthy() && num(-1.0).is_truthy());
+		assert!(!text("").is_truthy() && text("0").is_truthy());
+		assert!(!size(0).is_truthy() && size(1).is_truthy());
+		assert!(Value::Regex(Regex::new(b"", b"").unwrap()).is_truthy());
+		assert!(Value::PathGlob(PathGlob::parse(Path::new("*.rs")).unwrap()).is_truthy());
+	}
+
+	#[test]
+	fn matches() {
+		let regex = Value::Regex(Regex::new(b"^a+$", b"").unwrap());
+		assert!(regex.matches(&text("aaa")).unwrap());
+		assert!(!text("ab").matches(&regex).unwrap());
+		assert!(text("abc").matches(&text("b")).unwrap());
+
+		assert!(matches!(
+			num(1.0).matches(&num(2.0)),
+			Err(RunError::InvalidTypes { func: "=~", lhs: "number", rhs: "number" })
+		));
+		assert!(matches!(text("a").matches(&size(1)), Err(RunError::InvalidTypes { .. })));
+	}
+
+	#[test]
+	fn repeat() {
+		assert_eq!(text("ababab"), text("ab").multiply(&num(3.0)).unwrap());
+		assert_eq!(text("ab"), num(1.5).multiply(&text("ab")).unwrap());
+		assert_eq!(text(""), text("ab").multiply(&num(-1.0)).unwrap());
+		assert_eq!(text(""), text("").multiply(&num(1e18)).unwrap());
+
+		for count in [1e18, f64::INFINITY, (MAX_REPEAT_LEN + 1) as f64] {
+			assert!(matches!(text("a").multiply(&num(count)), Err(RunError::TooLarge { func: "*" })));
+		}
+	}
+
+	// Dividing by zero is always an error, rather than infinity or `NaN` for numbers.
+	#[test]
+	fn division_by_zero() {
+		let both = [
+			(num(1.0), num(0.0)),
+			(num(0.0), num(-0.0)),
+			(size(10), size(0)),
+			(relative(10), relative(0)),
+		];
+		for (lhs, rhs) in both {
+			assert!(matches!(lhs.divide(&rhs), Err(RunError::DivisionByZero)), "{lhs} div {rhs}");
+			assert!(matches!(lhs.modulo(&rhs), Err(RunError::DivisionByZero)), "{lhs} mod {rhs}");
+		}
+
+		// Only division is supported for these.
+		for (lhs, rhs) in [(size(10), num(0.0)), (relative(10), num(0.0))] {
+			assert!(matches!(lhs.divide(&rhs), Err(RunError::DivisionByZero)), "{lhs} div {rhs}");
+		}
+
+		assert_eq!(num(2.5), num(5.0).divide(&num(2.0)).unwrap());
+		assert_eq!(num(1.0), num(7.0).modulo(&num(3.0)).unwrap());
+		assert_eq!(size(5), size(10).divide(&num(2.0)).unwrap());
+		assert_eq!(num(2.0), size(10).divide(&size(5)).unwrap());
+		assert_eq!(size(1), size(10).modulo(&size(3)).unwrap());
+		assert_eq!(relative(1), relative(10).modulo(&relative(3)).unwrap());
+	}
+
+	// Times too far apart to represent are errors, rather than panicking or wrapping around.
+	#[test]
+	fn datetime_overflow() {
+		let date = Value::from(DateTime::from_ymd_hms(2024, 1, 1, 0, 0, 0).unwrap());
+		let too_large = |result: RunResult<Value>| matches!(result, Err(RunError::TooLarge { .. }));
+
+		assert!(too_large(date.add(&relative(i128::MAX))));
+		assert!(too_large(relative(i128::MAX).add(&relative(1))));
+		assert!(too_large(date.subtract(&relative(i128::MIN))));
+		assert!(too_large(relative(i128::MIN).subtract(&relative(1))));
+		assert!(too_large(relative(i128::MIN).negate()));
+		assert!(too_large(relative(1 << 100).multiply(&num(1e30))));
+		assert!(too_large(num(-1e30).multiply(&relative(1 << 100))));
+		assert!(too_large(relative(1 << 100).divide(&num(1e-30))));
+
+		// Mixing up absolute and relative times is still a type error.
+		assert!(matches!(date.add(&date), Err(RunError::InvalidTypes { .. })));
+		assert!(matches!(relative(1).subtract(&date), Err(RunError::InvalidTypes { .. })));
+		assert_eq!(relative(3 << 100), relative(1 << 100).multiply(&num(3.0)).unwrap());
+	}
+}
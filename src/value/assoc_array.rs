@@ -1,14 +1,46 @@
 use crate::Value;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex, MutexGuard};
 
+/// Both arrays and hashmaps: Arrays are just assoc arrays whose keys are `0`, `1`, ...
+///
+/// Entries are kept in the order they're inserted, so that iterating over arrays is in order.
+/// Like most scripting languages, assoc arrays are shared, not copied, when assigned.
 #[derive(Default, Debug, Clone)]
-pub struct AssocArray(Rc<RefCell<HashMap<Value, Value>>>);
+pub struct AssocArray(Arc<Mutex<Entries>>);
+
+#[derive(Default, Debug)]
+struct Entries {
+	pairs: Vec<(Value, Value)>,
+
+	// Keys which are arrays aren't in here, as comparing them locks them, and they might contain
+	// (or be) the array whose entries are already locked. They're searched for linearly instead.
+	indices: HashMap<Value, usize>,
+}
+
+thread_local! {
+	// The arrays which are currently being displayed or compared, so that arrays which contain
+	// themselves (eg after `push(ary, ary)`) don't recurse forever.
+	static VISITING: RefCell<Vec<(usize, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `body` with `key` marked as being visited, or returns `None` if it already is.
+fn visit<T>(key: (usize, usize), body: impl FnOnce() -> T) -> Option<T> {
+	if VISITING.with_borrow(|visiting| visiting.contains(&key)) {
+		return None;
+	}
+
+	VISITING.with_borrow_mut(|visiting| visiting.push(key));
+	let result = body();
+	VISITING.with_borrow_mut(|visiting| visiting.pop());
+	Some(result)
+}
 
 impl PartialEq for AssocArray {
 	fn eq(&self, rhs: &Self) -> bool {
-		if Rc::ptr_eq(&self.0, &rhs.0) {
+		if Arc::ptr_eq(&self.0, &rhs.0) {
 			return true;
 		}
 
@@ -16,9 +48,12 @@ impl PartialEq for AssocArray {
 			return false;
 		}
 
-		// let mut v = self.keys().collect::<Vec<_>>();
-
-		todo!()
+		// If we're already comparing these two further up, then they're equal as long as
+		// everything else is, which is what that comparison is checking.
+		visit((self.addr(), rhs.addr()), || {
+			self.entries().into_iter().all(|(key, value)| rhs.get(&key).is_some_and(|x| x == value))
+		})
+		.unwrap_or(true)
 	}
 }
 
@@ -27,13 +62,245 @@ impl AssocArray {
 		Self::default()
 	}
 
+	/// Creates an array whose keys are `0`, `1`, ... for each of the `values`.
+	pub fn from_values(values: impl IntoIterator<Item = Value>) -> Self {
+		let array = Self::new();
+		for value in values {
+			array.push(value);
+		}
+		array
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Entries> {
+		self.0.lock().unwrap()
+	}
+
+	fn addr(&self) -> usize {
+		Arc::as_ptr(&self.0) as usize
+	}
+
 	pub fn len(&self) -> usize {
-		self.0.borrow().len()
+		self.lock().pairs.len()
 	}
 
 	pub fn is_empty(&self) -> bool {
-		self.0.borrow().is_empty()
+		self.lock().pairs.is_empty()
+	}
+
+	pub fn get(&self, key: &Value) -> Option<Value> {
+		if let Value::AssocArray(_) = key {
+			return self.entries().into_iter().find(|(existing, _)| existing == key).map(|(_, x)| x);
+		}
+
+		let entries = self.lock();
+		entries.indices.get(key).map(|&idx| entries.pairs[idx].1.clone())
+	}
+
+	/// Sets `key` to `value`, returning the old value if there was one.
+	pub fn insert(&self, key: Value, value: Value) -> Option<Value> {
+		if let Value::AssocArray(_) = key {
+			let found = self.keys().iter().position(|existing| *existing == key);
+			let mut entries = self.lock();
+			if let Some(idx) = found {
+				return Some(std::mem::replace(&mut entries.pairs[idx].1, value));
+			}
+
+			entries.pairs.push((key, value));
+			return None;
+		}
+
+		let mut entries = self.lock();
+
+		if let Some(&idx) = entries.indices.get(&key) {
+			return Some(std::mem::replace(&mut entries.pairs[idx].1, value));
+		}
+
+		let idx = entries.pairs.len();
+		entries.indices.insert(key.clone(), idx);
+		entries.pairs.push((key, value));
+		None
+	}
+
+	/// Adds `value` to the end of `self`, with its length as the key.
+	pub fn push(&self, value: Value) {
+		let len = self.len();
+		self.insert(Value::Number(len as f64), value);
+	}
+
+	/// Gets the value of the `idx`th entry, in the order they were inserted.
+	pub fn value_at(&self, idx: usize) -> Option<Value> {
+		self.lock().pairs.get(idx).map(|(_, value)| value.clone())
+	}
+
+	pub fn keys(&self) -> Vec<Value> {
+		self.lock().pairs.iter().map(|(key, _)| key.clone()).collect()
 	}
 
-	// pub fn insert(&self, )
+	pub fn values(&self) -> Vec<Value> {
+		self.lock().pairs.iter().map(|(_, value)| value.clone()).collect()
+	}
+
+	/// Returns a copy of each key and value, so that `self` can be modified while iterating.
+	pub fn entries(&self) -> Vec<(Value, Value)> {
+		self.lock().pairs.clone()
+	}
+
+	/// Whether the keys are exactly `0`, `1`, ..., in order.
+	pub fn is_array(&self) -> bool {
+		self.lock().pairs.iter().enumerate().all(|(idx, (key, _))| *key == Value::Number(idx as f64))
+	}
+}
+
+impl Display for AssocArray {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		let is_array = self.is_array();
+
+		// Arrays which contain themselves are written like `[1, [...]]`.
+		visit((self.addr(), 0), || self.fmt_entries(is_array, f))
+			.unwrap_or_else(|| f.write_str(if is_array { "[...]" } else { "{...}" }))
+	}
+}
+
+impl AssocArray {
+	fn fmt_entries(&self, is_array: bool, f: &mut Formatter) -> fmt::Result {
+		let entries = self.entries();
+
+		if is_array {
+			f.write_str("[")?;
+			for (idx, (_, value)) in entries.iter().enumerate() {
+				if idx != 0 {
+					f.write_str(", ")?;
+				}
+				write!(f, "{value}")?;
+			}
+			return f.write_str("]");
+		}
+
+		f.write_str("{")?;
+		for (idx, (key, value)) in entries.iter().enumerate() {
+			if idx != 0 {
+				f.write_str(", ")?;
+			}
+			write!(f, "{key}: {value}")?;
+		}
+		f.write_str("}")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn num(num: f64) -> Value {
+		Value::Number(num)
+	}
+
+	fn text(text: &str) -> Value {
+		Value::Text(text.as_bytes().into())
+	}
+
+	#[test]
+	fn indexing_and_insertion() {
+		let ary = AssocArray::from_values([num(10.0), num(20.0)]);
+		assert_eq!(Some(num(20.0)), ary.get(&num(1.0)));
+		assert_eq!(None, ary.get(&num(2.0)));
+		assert_eq!(None, ary.get(&text("1")));
+		assert_eq!(Some(num(10.0)), ary.value_at(0));
+
+		ary.push(num(30.0));
+		assert_eq!((3, true), (ary.len(), ary.is_array()));
+		assert_eq!(Some(num(20.0)), ary.insert(num(1.0), num(25.0)));
+		assert_eq!(vec![num(10.0), num(25.0), num(30.0)], ary.values());
+
+		// Entries stay in the order they were first inserted.
+		let map = AssocArray::new();
+		assert_eq!(None, map.insert(text("b"), num(1.0)));
+		assert_eq!(None, map.insert(text("a"), num(2.0)));
+		assert_eq!(Some(num(1.0)), map.insert(text("b"), num(3.0)));
+		assert_eq!(vec![text("b"), text("a")], map.keys());
+		assert_eq!(vec![(text("b"), num(3.0)), (text("a"), num(2.0))], map.entries());
+		assert!(!map.is_array());
+
+		// They're shared, not copied.
+		let shared = map.clone();
+		shared.insert(text("c"), num(4.0));
+		assert_eq!(Some(num(4.0)), map.get(&text("c")));
+	}
+
+	#[test]
+	fn equality() {
+		let values = || AssocArray::from_values([num(1.0), text("two")]);
+		assert_eq!(values(), values());
+		assert_ne!(values(), AssocArray::from_values([num(1.0)]));
+		assert_ne!(values(), AssocArray::from_values([num(1.0), text("three")]));
+		assert_ne!(values(), AssocArray::from_values([text("two"), num(1.0)]));
+
+		// Maps are equal regardless of the order of their keys.
+		let (lhs, rhs) = (AssocArray::new(), AssocArray::new());
+		lhs.insert(text("a"), num(1.0));
+		lhs.insert(text("b"), num(2.0));
+		rhs.insert(text("b"), num(2.0));
+		rhs.insert(text("a"), num(1.0));
+		assert_eq!(lhs, rhs);
+
+		let nested = || AssocArray::from_values([values().into(), AssocArray::new().into()]);
+		assert_eq!(nested(), nested());
+	}
+
+	#[test]
+	fn array_keys() {
+		let map = AssocArray::new();
+		map.insert(AssocArray::from_values([num(1.0)]).into(), num(1.0));
+		assert_eq!(Some(num(1.0)), map.get(&AssocArray::from_values([num(1.0)]).into()));
+		assert_eq!(None, map.get(&AssocArray::new().into()));
+
+		// Arrays can be looked up in themselves, or in arrays they contain, without deadlocking.
+		assert_eq!(None, map.get(&map.clone().into()));
+		assert_eq!(None, map.insert(map.clone().into(), num(2.0)));
+		assert_eq!(Some(num(2.0)), map.insert(map.clone().into(), num(3.0)));
+		assert_eq!(Some(num(3.0)), map.get(&map.clone().into()));
+
+		let key = AssocArray::from_values([map.clone().into()]);
+		assert_eq!(None, map.insert(key.clone().into(), num(4.0)));
+		assert_eq!(Some(num(4.0)), map.get(&key.into()));
+		assert_eq!(3, map.len());
+	}
+
+	#[test]
+	fn display() {
+		assert_eq!("[]", AssocArray::new().to_string());
+		assert_eq!("[1, two]", AssocArray::from_values([num(1.0), text("two")]).to_string());
+
+		let map = AssocArray::new();
+		map.insert(text("a"), AssocArray::from_values([num(1.0)]).into());
+		map.insert(num(2.0), num(3.0));
+		assert_eq!("{a: [1], 2: 3}", map.to_string());
+	}
+
+	#[test]
+	fn cycles() {
+		let ary = AssocArray::from_values([num(1.0)]);
+		ary.push(ary.clone().into());
+		assert_eq!("[1, [...]]", ary.to_string());
+		assert_eq!(ary, ary.clone());
+
+		let map = AssocArray::new();
+		map.insert(text("self"), map.clone().into());
+		assert_eq!("{self: {...}}", map.to_string());
+
+		// Mutually recursive ones too.
+		let (lhs, rhs) = (AssocArray::new(), AssocArray::new());
+		lhs.push(rhs.clone().into());
+		rhs.push(lhs.clone().into());
+		assert_eq!("[[[...]]]", lhs.to_string());
+		assert_eq!(lhs, rhs);
+
+		let (lhs, rhs) = (AssocArray::from_values([num(1.0)]), AssocArray::from_values([num(1.0)]));
+		lhs.push(lhs.clone().into());
+		rhs.push(rhs.clone().into());
+		assert_eq!(lhs, rhs);
+		rhs.push(num(2.0));
+		lhs.push(num(3.0));
+		assert_ne!(lhs, rhs);
+	}
 }
@@ -1,55 +1,138 @@
+use crate::play::sniff::Sniff;
 use crate::play::PathInfo;
-use crate::vm::{Opcode, RunError, Vm};
+use crate::value::{AssocArray, MAX_RANGE_LEN};
+use crate::vm::instruction::{self, Instruction, OpKind};
+use crate::vm::{RunError, Vm};
 use crate::Value;
 use core::cmp::Ordering;
 use os_str_bytes::OsStrBytes;
+use std::borrow::Cow;
 use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
 mod builder;
+mod disassemble;
 pub use builder::*;
+pub use disassemble::Disassembly;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Block {
-	code: Vec<Opcode>,
+	code: Vec<Instruction>,
 	consts: Vec<Value>,
 	args: Vec<String>, // names are given for fun.
+	max_stack: usize,  // roughly how many values are on the stack at once.
 }
 
+/// A function that's being called, and where to go back to once it returns.
+#[derive(Debug, Clone)]
+pub(super) struct Frame {
+	block: Arc<Block>,
+	return_ip: usize,
+	caller_base: usize,
+}
+
+/// Runs a block, and any functions it calls.
+///
+/// Every function shares the same stack: a function's arguments are the values its caller pushed
+/// for it, and everything above them is its own. Calls don't recurse, so deeply nested functions
+/// don't overflow the native stack.
 #[derive(Debug)]
-struct Stackframe<'b, 'v> {
-	block: &'b Block,
+struct Interpreter<'b, 'v> {
+	top: &'b Block,
 	vm: &'v mut Vm,
-	ip: usize,
 	stack: Vec<Value>,
-	args: Vec<Value>,
+	frames: Vec<Frame>,
+	ip: usize,
+	base: usize, // where the current function's arguments start on the stack.
 }
 
 impl Block {
 	pub fn run(&self, vm: &mut Vm) -> Result<Value, RunError> {
-		Stackframe { block: self, vm, ip: 0, stack: Vec::new(), args: vec![] }.run()
+		self.call(vm, Vec::new())
 	}
-}
 
-impl Stackframe<'_, '_> {
-	fn jump_to(&mut self, idx: usize) {
-		debug_assert!(idx <= self.block.code.len(), "{self:?}");
-		self.ip = idx;
-	}
+	/// Calls `self` as a function, with `args` bound to its arguments.
+	pub fn call(&self, vm: &mut Vm, args: Vec<Value>) -> Result<Value, RunError> {
+		if args.len() != self.args.len() {
+			return Err(RunError::ArgumentCount { expected: self.args.len(), given: args.len() });
+		}
+
+		let (mut stack, frames) = vm.take_stack();
+		stack.reserve(self.max_stack + args.len());
+		stack.extend(args);
 
-	fn next_opcode(&mut self) -> Option<Opcode> {
-		let &op = self.block.code.get(self.ip)?;
-		self.ip += 1;
-		Some(op)
+		let mut interpreter = Interpreter { top: self, vm, stack, frames, ip: 0, base: 0 };
+		let result = interpreter.run();
+		interpreter.vm.restore_stack(interpreter.stack, interpreter.frames);
+		result
 	}
 
-	fn run(mut self) -> Result<Value, RunError> {
-		while let Some(opcode) = self.next_opcode() {
-			if let Some(return_value) = self.run_opcode(opcode)? {
-				return Ok(return_value);
+	/// Whether `self`, or any function it declares, could prompt the user: either it calls a
+	/// builtin which always prompts (eg `mvi` or `ok?`), or `prompt` is set and it calls one which
+	/// prompts by default (eg `mv`).
+	pub fn prompts(&self, prompt: bool) -> bool {
+		use OpKind::*;
+
+		let mut ip = 0;
+		while let Some((kind, _)) = instruction::fetch(&self.code, &mut ip) {
+			let prompts = match kind {
+				IsOk => true,
+				Mv | MvI | Rm | RmI | RmR | RmRI | Cp | CpI | Ln | LnI | LnS | LnSI => prompt,
+				_ => kind.force() == Some(false),
+			};
+
+			if prompts {
+				return true;
 			}
 		}
 
-		Ok(self.stack.pop().unwrap_or_default())
+		self.consts.iter().any(|value| matches!(value, Value::Function(func) if func.prompts(prompt)))
+	}
+}
+
+/// Concatenates the parts of an interpolated value.
+fn concat(parts: &[Value]) -> Vec<u8> {
+	let mut bytes = Vec::new();
+	for part in parts {
+		bytes.extend_from_slice(&part.to_bytes());
+	}
+	bytes
+}
+
+/// Converts `value` to a path for `kind`, which only accepts paths and strings.
+fn to_path(kind: OpKind, value: &Value) -> Result<PathBuf, RunError> {
+	match value {
+		Value::Text(text) => Ok(OsStr::assert_from_raw_bytes(text.as_ref()).into_owned().into()),
+		Value::Path(path) => Ok(path.to_path_buf()),
+		other => Err(invalid_type(kind, other)),
+	}
+}
+
+fn invalid_type(kind: OpKind, given: &Value) -> RunError {
+	RunError::InvalidType { func: kind.name(), given: given.typename() }
+}
+
+/// How many directories `path` is below `root`. It's an error if `path` isn't within `root`.
+fn path_depth(path: &Path, root: &Path) -> std::io::Result<usize> {
+	let relative = match path.strip_prefix(root) {
+		Ok(relative) => relative.to_path_buf(),
+		Err(_) => crate::absolute_path(path)?
+			.strip_prefix(crate::absolute_path(root)?)
+			.map_err(|_| {
+				let message = format!("{} isn't within {}", path.display(), root.display());
+				std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+			})?
+			.to_path_buf(),
+	};
+
+	Ok(relative.components().filter(|component| *component != Component::CurDir).count())
+}
+
+impl Interpreter<'_, '_> {
+	fn block(&self) -> &Block {
+		self.frames.last().map_or(self.top, |frame| &frame.block)
 	}
 
 	fn push(&mut self, value: Value) {
@@ -60,134 +143,480 @@ impl Stackframe<'_, '_> {
 		self.stack.pop().expect("<internal error: popped from end of stack>")
 	}
 
-	fn run_opcode(&mut self, opcode: Opcode) -> Result<Option<Value>, RunError> {
-		use Opcode::*;
-		let mut args = (0..opcode.arity()).map(|_| self.pop()).collect::<Vec<_>>();
+	/// Pops the top `N` values, in the order they were pushed.
+	fn pop_n<const N: usize>(&mut self) -> [Value; N] {
+		let mut values = self.stack.drain(self.stack.len() - N..);
+		std::array::from_fn(|_| values.next().unwrap())
+	}
 
-		macro_rules! info {
-			($cond:ident) => {
-				info!($cond, 0)
-			};
-			($cond:ident, $pos:literal) => {
-				if $cond {
-					std::borrow::Cow::Borrowed(self.vm.info())
-				} else if let Value::Text(ref name) = args[$pos] {
-					std::borrow::Cow::Owned(PathInfo::new(&std::path::Path::new(
-						&OsStr::assert_from_raw_bytes(name.as_ref()),
-					))?)
-				} else {
-					panic!("todo: error the argument isnt a path");
+	/// Where the top `amount` values start on the stack.
+	fn top_start(&self, amount: usize) -> usize {
+		self.stack.len() - amount
+	}
+
+	/// Pops the path argument, or gets the current path if `kind` is implicit.
+	fn path_arg(&mut self, kind: OpKind) -> Result<PathBuf, RunError> {
+		if kind.has_implicit_path() {
+			Ok(self.vm.info().path()._rc().to_path_buf())
+		} else {
+			to_path(kind, &self.pop())
+		}
+	}
+
+	/// Pops the path argument and gets its info, or uses the current path if `kind` is implicit.
+	fn info(&mut self, kind: OpKind) -> Result<Cow<'_, PathInfo>, RunError> {
+		if kind.has_implicit_path() {
+			Ok(Cow::Borrowed(self.vm.info()))
+		} else {
+			Ok(Cow::Owned(PathInfo::new(&to_path(kind, &self.pop())?)?))
+		}
+	}
+
+	/// Pops the path argument and sniffs it, or sniffs the current path if `kind` is implicit.
+	fn sniff(&mut self, kind: OpKind) -> Result<Sniff, RunError> {
+		if kind.has_implicit_path() {
+			self.vm.info_mut().sniff().map_err(RunError::Unreadable)
+		} else {
+			Sniff::from_path(&to_path(kind, &self.pop())?).map_err(RunError::Unreadable)
+		}
+	}
+
+	fn run(&mut self) -> Result<Value, RunError> {
+		loop {
+			let block = self.frames.last().map_or(self.top, |frame| &frame.block);
+			let Some((kind, offset)) = instruction::fetch(&block.code, &mut self.ip) else {
+				// Running off the end returns the last value, if there is one.
+				let locals = self.base + block.args.len();
+				let value = if self.stack.len() > locals { self.pop() } else { Value::default() };
+				match self.leave(value) {
+					Some(value) => return Ok(value),
+					None => continue,
 				}
-				.as_ref()
 			};
+
+			if let Some(return_value) = self.run_opcode(kind, offset)? {
+				return Ok(return_value);
+			}
+		}
+	}
+
+	/// Calls the function on top of the stack with the `argc` arguments below it.
+	fn call(&mut self, argc: usize) -> Result<(), RunError> {
+		let block = match self.pop() {
+			Value::Function(block) => block,
+			other => return Err(RunError::InvalidType { func: "call", given: other.typename() }),
+		};
+
+		if argc != block.args.len() {
+			return Err(RunError::ArgumentCount { expected: block.args.len(), given: argc });
 		}
 
-		let topush = match opcode {
-			Illegal => unreachable!(),
-			LoadConstant(idx) => self.block.consts[idx].clone(),
-			LoadArgument(idx) => self.args[idx].clone(),
-			LoadVariable(idx) => self.vm.get_variable(idx).unwrap_or_default(), // todo: is default correct?
-			StoreArgument(idx) => {
-				self.args[idx] = args.remove(0);
+		if self.frames.len() == Vm::MAX_CALL_DEPTH {
+			return Err(RunError::StackTooDeep);
+		}
+
+		self.stack.reserve(block.max_stack);
+		let base = self.top_start(argc);
+		self.frames.push(Frame { block, return_ip: self.ip, caller_base: self.base });
+		self.ip = 0;
+		self.base = base;
+		Ok(())
+	}
+
+	/// Returns `value` from the current function. If it's the outermost one, `value` is returned.
+	fn leave(&mut self, value: Value) -> Option<Value> {
+		self.stack.truncate(self.base);
+
+		let Some(frame) = self.frames.pop() else {
+			return Some(value);
+		};
+
+		self.ip = frame.return_ip;
+		self.base = frame.caller_base;
+		self.push(value);
+		None
+	}
+
+	fn run_opcode(&mut self, kind: OpKind, offset: usize) -> Result<Option<Value>, RunError> {
+		use OpKind::*;
+
+		let topush = match kind {
+			Wide => unreachable!("<internal error: wide instructions are read with the next one>"),
+
+			LoadConstant => self.block().consts[offset].clone(),
+			LoadArgument => self.stack[self.base + offset].clone(),
+			LoadVariable => self.vm.get_variable(offset).unwrap_or_default(), // todo: is default correct?
+			StoreArgument => {
+				let value = self.pop();
+				self.stack[self.base + offset] = value;
 				return Ok(None);
 			}
-			StoreVariable(idx) => {
-				self.vm.store_variable(idx, args.remove(0));
+			StoreVariable => {
+				let value = self.pop();
+				self.vm.store_variable(offset, value);
 				return Ok(None);
 			}
 
-			Dup => {
-				self.push(args[0].clone());
-				args.remove(0)
+			Dup => self.stack.last().expect("<internal error: popped from end of stack>").clone(),
+			Dup2 => {
+				let start = self.top_start(2);
+				self.stack.extend_from_within(start..);
+				return Ok(None);
+			}
+			Pop => {
+				self.pop();
+				return Ok(None);
 			}
-			Pop => return Ok(None),
 
-			GenericCall(_amnt) => args[0].call(&args[1..])?,
+			GenericCall => {
+				self.call(offset)?;
+				return Ok(None);
+			}
 
-			CreatePath(_usize) => todo!(),
-			CreateRegex(_usize) => todo!(),
-			CreateString(_usize) => todo!(),
+			CreatePath => {
+				let start = self.top_start(offset);
+				let path = concat(&self.stack[start..]);
+				self.stack.truncate(start);
+				let is_glob = path.iter().any(|c| b"*?[".contains(c));
+				let path = OsStr::assert_from_raw_bytes(&path).into_owned();
 
-			Return => return Ok(Some(args.remove(0))),
-			Jump(position) => {
-				self.jump_to(position);
+				// Only globs need to be path globs; plain paths can be given to `mv` and friends.
+				if is_glob {
+					Value::PathGlob(crate::PathGlob::parse(path.as_ref())?)
+				} else {
+					Path::new(&path).into()
+				}
+			}
+			CreateRegex => {
+				// The flags are pushed before the parts.
+				let start = self.top_start(offset);
+				let source = concat(&self.stack[start..]);
+				self.stack.truncate(start);
+				let flags = self.pop().to_bytes().into_owned();
+				Value::Regex(self.vm.regex(source, flags)?)
+			}
+			CreateString => {
+				let start = self.top_start(offset);
+				let text = concat(&self.stack[start..]);
+				self.stack.truncate(start);
+				Value::Text(text.into())
+			}
+			CreateArray => {
+				let start = self.top_start(offset);
+				AssocArray::from_values(self.stack.drain(start..)).into()
+			}
+			CreateAssocArray => {
+				let start = self.top_start(offset);
+				let ary = AssocArray::new();
+				let mut pairs = self.stack.drain(start..);
+				while let (Some(key), Some(value)) = (pairs.next(), pairs.next()) {
+					ary.insert(key, value);
+				}
+				ary.into()
+			}
+
+			Index => {
+				let [ary, index] = self.pop_n();
+				ary.index(&index)?
+			}
+			SetIndex => {
+				let [ary, index, value] = self.pop_n();
+				ary.set_index(index, value.clone())?;
+				value
+			}
+
+			Return => {
+				let value = self.pop();
+				return Ok(self.leave(value));
+			}
+			Jump => {
+				self.ip = offset;
 				return Ok(None);
 			}
-			JumpIf(position) | JumpUnless(position) => {
-				if args[0].logical(self.vm)? == matches!(opcode, Opcode::JumpIf(_)) {
-					self.jump_to(position);
+			JumpIf | JumpUnless => {
+				if self.pop().logical(self.vm)? == (kind == JumpIf) {
+					self.ip = offset;
 				}
 				return Ok(None);
 			}
+			ForEach => {
+				// The array and the index of its next element stay on the stack while looping.
+				let len = self.stack.len();
+				let Value::Number(idx) = self.stack[len - 1] else { unreachable!() };
+				let next = match self.stack[len - 2] {
+					Value::AssocArray(ref ary) => ary.value_at(idx as usize),
+					ref other => {
+						return Err(RunError::InvalidType { func: "for", given: other.typename() })
+					}
+				};
+
+				match next {
+					Some(value) => {
+						self.stack[len - 1] = (idx + 1.0).into();
+						value
+					}
+					None => {
+						self.ip = offset;
+						return Ok(None);
+					}
+				}
+			}
 
-			Not => (!args[0].logical(self.vm)?).into(),
-			Negate => args[0].negate()?.into(),
+			Not => (!self.pop().logical(self.vm)?).into(),
+			Negate => self.pop().negate()?,
 			UPositive => todo!(),
-			ForcedLogical => args[0].logical(self.vm)?.into(),
-
-			Add => args[1].add(&args[0])?,
-			Subtract => args[1].subtract(&args[0])?,
-			Multiply => args[1].multiply(&args[0])?,
-			Divide => args[1].divide(&args[0])?,
-			Modulo => args[1].modulo(&args[0])?,
-
-			Matches => args[1].matches(&args[0])?.into(),
-			NotMatches => (!args[1].matches(&args[0])?).into(),
-			Equal => (args[1].compare(&args[0])? == Ordering::Equal).into(),
-			NotEqual => (args[1].compare(&args[0])? != Ordering::Equal).into(),
-			LessThan => (args[1].compare(&args[0])? < Ordering::Equal).into(),
-			LessThanOrEqual => (args[1].compare(&args[0])? <= Ordering::Equal).into(),
-			GreaterThan => (args[1].compare(&args[0])? > Ordering::Equal).into(),
-			GreaterThanOrEqual => (args[1].compare(&args[0])? >= Ordering::Equal).into(),
+			ForcedLogical => self.pop().logical(self.vm)?.into(),
+
+			Add | Subtract | Multiply | Divide | Modulo => {
+				let [lhs, rhs] = self.pop_n();
+				match kind {
+					Add => lhs.add(&rhs)?,
+					Subtract => lhs.subtract(&rhs)?,
+					Multiply => lhs.multiply(&rhs)?,
+					Divide => lhs.divide(&rhs)?,
+					_ => lhs.modulo(&rhs)?,
+				}
+			}
+
+			Matches => {
+				let [lhs, rhs] = self.pop_n();
+				lhs.matches(&rhs)?.into()
+			}
+			NotMatches => {
+				let [lhs, rhs] = self.pop_n();
+				(!lhs.matches(&rhs)?).into()
+			}
+			Equal => {
+				let [lhs, rhs] = self.pop_n();
+				lhs.equals(&rhs).into()
+			}
+			NotEqual => {
+				let [lhs, rhs] = self.pop_n();
+				(!lhs.equals(&rhs)).into()
+			}
+			LessThan | LessThanOrEqual | GreaterThan | GreaterThanOrEqual => {
+				let [lhs, rhs] = self.pop_n();
+				let ordering = lhs.compare(&rhs)?;
+				match kind {
+					LessThan => ordering < Ordering::Equal,
+					LessThanOrEqual => ordering <= Ordering::Equal,
+					GreaterThan => ordering > Ordering::Equal,
+					_ => ordering >= Ordering::Equal,
+				}
+				.into()
+			}
 
 			// Querying
-			IsFile { implicit } => info!(implicit).is_file().into(),
-			IsDirectory { implicit } => info!(implicit).is_dir().into(),
-			IsExecutable { implicit: _ } => todo!(),
-			IsSymlink { implicit: _ } => todo!(),
-			IsBinary { implicit: _ } => todo!(),
-			IsHidden { implicit } => info!(implicit).is_hidden().into(),
-			IsGitIgnored { implicit: _ } => todo!(),
-			IsOk(_usize) => todo!(),
+			IsFile | IsFileI => self.info(kind)?.is_file().into(),
+			IsDirectory | IsDirectoryI => self.info(kind)?.is_dir().into(),
+			IsExecutable | IsExecutableI => self.info(kind)?.is_executable().into(),
+			IsSymlink | IsSymlinkI => self.info(kind)?.is_symlink().into(),
+			IsBroken | IsBrokenI => self.info(kind)?.is_broken().into(),
+			IsBinary | IsBinaryI => self.sniff(kind)?.encoding.is_binary().into(),
+			IsHidden | IsHiddenI => self.info(kind)?.is_hidden().into(),
+			IsGitIgnored | IsGitIgnoredI => {
+				let path = self.path_arg(kind)?;
+				let is_dir =
+					if kind.has_implicit_path() { self.vm.info().is_dir() } else { path.is_dir() };
+				self.vm.ignores().is_ignored(&path, is_dir).into()
+			}
+			Mime | MimeI => Value::Text(self.sniff(kind)?.mime.as_bytes().into()),
+			Encoding | EncodingI => Value::Text(self.sniff(kind)?.encoding.to_str().as_bytes().into()),
+			IsOk => todo!(),
 
 			// Path-related funcitons
-			FileSize { implicit } => info!(implicit).content_size().into(),
+			FileSize | FileSizeI => self.info(kind)?.content_size().into(),
+			ModifyTime | ModifyTimeI => self.info(kind)?.modified()?.into(),
+			AccessTime | AccessTimeI => self.info(kind)?.accessed()?.into(),
+			ChangeTime | ChangeTimeI => self.info(kind)?.changed()?.into(),
+			BirthTime | BirthTimeI => self.info(kind)?.created()?.into(),
 			PushRoot => self.vm.root().clone().into(),
 			PushPath => self.vm.info().path()._rc().clone().into(),
 			PushPwd => todo!(),
-			Dirname { implicit } => info!(implicit).path().parent().into(),
-			Extname { implicit } => info!(implicit).path().extension().into(),
-			ExtnameDot { implicit } => info!(implicit).extnamedot().into(),
-			Basename { implicit } => info!(implicit).path().base().into(),
-			Stemname { implicit } => info!(implicit).path().stem().into(),
+			Dirname | DirnameI => self.info(kind)?.path().parent().into(),
+			Extname | ExtnameI => self.info(kind)?.path().extension().into(),
+			ExtnameDot | ExtnameDotI => self.info(kind)?.extnamedot().into(),
+			Basename | BasenameI => self.info(kind)?.path().base().into(),
+			Stemname | StemnameI => self.info(kind)?.path().stem().into(),
+			// A link's target is its contents, so (like other contents) non-links just don't match.
+			ReadLink | ReadLinkI => {
+				let path = self.path_arg(kind)?;
+				std::fs::read_link(path).map_err(RunError::Unreadable)?.as_path().into()
+			}
+
+			// Arrays
+			Len => match self.pop() {
+				Value::AssocArray(ary) => (ary.len() as f64).into(),
+				Value::Text(text) => (text.len() as f64).into(),
+				other => return Err(invalid_type(kind, &other)),
+			},
+			Keys | Values => match self.pop() {
+				Value::AssocArray(ary) if kind == Keys => AssocArray::from_values(ary.keys()).into(),
+				Value::AssocArray(ary) => AssocArray::from_values(ary.values()).into(),
+				other => return Err(invalid_type(kind, &other)),
+			},
+			Push => {
+				let [ary, value] = self.pop_n();
+				match ary {
+					Value::AssocArray(ref inner) => inner.push(value),
+					ref other => return Err(invalid_type(kind, other)),
+				}
+				ary
+			}
+			Get => {
+				let [ary, key, default] = self.pop_n();
+				match ary {
+					Value::AssocArray(ary) => ary.get(&key).unwrap_or(default),
+					other => return Err(invalid_type(kind, &other)),
+				}
+			}
+
+			Range => {
+				let mut bounds = [0.0; 2];
+				let start = self.top_start(offset);
+				for (bound, arg) in bounds[2 - offset..].iter_mut().zip(&self.stack[start..]) {
+					let Value::Number(num) = *arg else {
+						return Err(invalid_type(kind, arg));
+					};
+					*bound = num;
+				}
+				self.stack.truncate(start);
+
+				let [start, stop] = bounds;
+				let len = (stop - start).ceil().max(0.0);
+				if len > MAX_RANGE_LEN as f64 {
+					return Err(RunError::TooLarge { func: "range" });
+				}
+
+				let len = len as usize;
+				AssocArray::from_values((0..len).map(|offset| Value::Number(start + offset as f64)))
+					.into()
+			}
+			Lines | LinesI => {
+				let path = self.path_arg(kind)?;
+				let contents = crate::play::read::contents(&path, self.vm.config().max_read())
+					.map_err(RunError::Unreadable)?;
+				let mut lines = contents.split(|&c| c == b'\n').collect::<Vec<_>>();
+				if lines.last().is_some_and(|line| line.is_empty()) {
+					lines.pop(); // the last line ends with a newline
+				}
+
+				AssocArray::from_values(
+					lines
+						.into_iter()
+						.map(|line| Value::Text(line.strip_suffix(b"\r").unwrap_or(line).into())),
+				)
+				.into()
+			}
+			Children | ChildrenI => {
+				let dir = self.path_arg(kind)?;
+				let mut children = std::fs::read_dir(dir)?
+					.map(|entry| Ok(entry?.path()))
+					.collect::<std::io::Result<Vec<_>>>()?;
+				children.sort();
+
+				AssocArray::from_values(children.iter().map(|child| child.as_path().into())).into()
+			}
+			Head | HeadI | Tail | TailI => {
+				let count = match self.pop() {
+					Value::Number(count) => count,
+					other => return Err(invalid_type(kind, &other)),
+				};
+
+				let path = self.path_arg(kind)?;
+				let (count, limit) = (count.max(0.0) as usize, self.vm.config().max_read());
+				let text = if matches!(kind, Head | HeadI) {
+					crate::play::read::head(&path, count, limit)
+				} else {
+					crate::play::read::tail(&path, count, limit)
+				};
+
+				Value::Text(text.map_err(RunError::Unreadable)?.into())
+			}
 
 			// Misc
-			Print(_usize) | Write(_usize) => {
-				for arg in args.iter().rev() {
+			Print | Write => {
+				// Build the whole line first, so output from different jobs doesn't interleave.
+				let start = self.top_start(offset);
+				let mut line = Vec::new();
+				for arg in &self.stack[start..] {
 					match arg {
-						Value::Text(txt) => print!("{}", String::from_utf8_lossy(&txt)),
-						other => print!("{other:?}"),
+						Value::Text(txt) => line.extend_from_slice(txt),
+						other => write!(line, "{other}")?,
 					}
 				}
-				if matches!(opcode, Print(_)) {
-					println!(); // todo: println `\0`?
+				if kind == Print {
+					line.push(b'\n'); // todo: println `\0`?
+				}
+				std::io::stdout().lock().write_all(&line)?;
+
+				// It returns its last argument.
+				let last = if offset == 0 { Value::default() } else { self.pop() };
+				self.stack.truncate(start);
+				last
+			}
+			Skip => return Err(RunError::Skip),
+			QuitI => return Err(RunError::Exit(None)),
+			Quit => match self.pop() {
+				Value::Number(status) if (0.0..=255.0).contains(&status) && status.fract() == 0.0 => {
+					return Err(RunError::Exit(Some(status as u8)));
 				}
-				args.remove(0)
+				Value::Number(status) => return Err(RunError::InvalidExitStatus(status)),
+				other => return Err(invalid_type(kind, &other)),
+			},
+			Depth => {
+				let start = self.top_start(offset);
+				let path = match offset {
+					0 => self.vm.info().path()._rc().to_path_buf(),
+					_ => to_path(kind, &self.stack[start])?,
+				};
+				let root = match offset {
+					2 => to_path(kind, &self.stack[start + 1])?,
+					_ => self.vm.root().to_path_buf(),
+				};
+				self.stack.truncate(start);
+
+				(path_depth(&path, &root)? as f64).into()
+			}
+			Prune => {
+				self.vm.prune();
+				false.into()
 			}
-			Skip => todo!(),
-			Quit { implicit } => std::process::exit(if implicit { 0 } else { todo!("top to int") }),
-			Depth { implicit: _ } => todo!(),
-			Sleep { implicit: _ } => todo!(),
+			Sleep | SleepI => todo!(),
 
 			// Interactive
-			Mv { implicit: _, force: _ } => todo!(),
-			Rm { implicit: _, force: _ } => todo!(),
-			RmR { implicit: _, force: _ } => todo!(),
-			Cp { implicit: _, force: _ } => todo!(),
-			Ln { implicit: _, force: _ } => todo!(),
-			LnS { implicit: _, force: _ } => todo!(),
-			Mkdir => todo!(),
-			Touch { implicit: _ } => todo!(),
+			Mv | MvA | MvF | MvI | MvIA | MvIF => {
+				let dst = to_path(kind, &self.pop())?;
+				let src = self.path_arg(kind)?;
+				self.vm.mv(&src, &dst, kind.force())?.into()
+			}
+			Cp | CpA | CpF | CpI | CpIA | CpIF => {
+				let dst = to_path(kind, &self.pop())?;
+				let src = self.path_arg(kind)?;
+				self.vm.cp(&src, &dst, kind.force())?.into()
+			}
+			Ln | LnA | LnF | LnI | LnIA | LnIF | LnS | LnSA | LnSF | LnSI | LnSIA | LnSIF => {
+				let dst = to_path(kind, &self.pop())?;
+				let src = self.path_arg(kind)?;
+				let symbolic = matches!(kind, LnS | LnSA | LnSF | LnSI | LnSIA | LnSIF);
+				self.vm.ln(&src, &dst, symbolic, kind.force())?.into()
+			}
+			Rm | RmA | RmF | RmI | RmIA | RmIF => {
+				let path = self.path_arg(kind)?;
+				self.vm.rm(&path, kind.force())?.into()
+			}
+			RmR | RmRA | RmRF | RmRI | RmRIA | RmRIF => {
+				let path = self.path_arg(kind)?;
+				self.vm.rmr(&path, kind.force())?.into()
+			}
+			Mkdir => {
+				let path = to_path(kind, &self.pop())?;
+				self.vm.mkdir(&path)?.into()
+			}
+			Touch | TouchI => {
+				let path = self.path_arg(kind)?;
+				self.vm.touch(&path)?.into()
+			}
 		};
 
 		self.push(topush);
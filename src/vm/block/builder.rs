@@ -1,5 +1,5 @@
 use super::Block;
-use crate::vm::Opcode;
+use crate::vm::{instruction, Opcode};
 use crate::Value;
 use std::collections::HashMap;
 
@@ -32,9 +32,22 @@ impl<'a> Builder<'a> {
 		}
 	}
 
+	/// Creates a builder for the body of a function which takes `args`.
+	///
+	/// Functions share global variables with the code they're declared in, but have their own
+	/// arguments and loops.
+	pub fn function(&mut self, args: Vec<String>) -> Builder<'_> {
+		Builder::new(args, self.global_vars)
+	}
+
 	pub fn build(self) -> Block {
 		assert!(self.loops.is_empty());
-		Block { code: self.code, consts: self.consts, args: self.arguments }
+		Block {
+			code: instruction::assemble(&self.code),
+			consts: self.consts,
+			args: self.arguments,
+			max_stack: max_stack(&self.code),
+		}
 	}
 
 	pub fn opcode(&mut self, op: Opcode) {
@@ -149,6 +162,57 @@ impl<'a> Builder<'a> {
 	}
 }
 
+/// Roughly how many values `code` has on the stack at once, so it can be allocated up front.
+///
+/// `break` can leave values behind, so the stack isn't always the same size when two paths meet.
+/// Only the first path to each opcode is followed, as this is just a capacity hint.
+fn max_stack(code: &[Opcode]) -> usize {
+	let mut seen = vec![false; code.len()];
+	let mut todo = vec![(0, 0)];
+	let mut max = 0;
+
+	while let Some((idx, depth)) = todo.pop() {
+		if idx >= code.len() || std::mem::replace(&mut seen[idx], true) {
+			continue;
+		}
+
+		let opcode = code[idx];
+		let depth = depth - opcode.arity().min(depth);
+		let pushed = match opcode {
+			Opcode::Dup => 2,
+			Opcode::Dup2 => 4,
+			Opcode::ForEach(_) => 3,
+			Opcode::StoreArgument(_)
+			| Opcode::StoreVariable(_)
+			| Opcode::Pop
+			| Opcode::Jump(_)
+			| Opcode::JumpIf(_)
+			| Opcode::JumpUnless(_)
+			| Opcode::Return
+			| Opcode::Skip
+			| Opcode::Quit { .. } => 0,
+			_ => 1,
+		};
+		max = max.max(depth + pushed);
+
+		match opcode {
+			Opcode::Return | Opcode::Skip | Opcode::Quit { .. } => {}
+			Opcode::Jump(position) => todo.push((position, depth)),
+			Opcode::JumpIf(position) | Opcode::JumpUnless(position) => {
+				todo.push((position, depth));
+				todo.push((idx + 1, depth));
+			}
+			Opcode::ForEach(position) => {
+				todo.push((position, depth + 2)); // the array and index stay when it's done
+				todo.push((idx + 1, depth + pushed));
+			}
+			_ => todo.push((idx + 1, depth + pushed)),
+		}
+	}
+
+	max
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LoopToken(usize);
 
@@ -170,4 +234,8 @@ impl JumpIndex {
 	pub fn jump_unless(self, builder: &mut Builder) {
 		builder.code[self.0] = Opcode::JumpUnless(builder.code.len());
 	}
+
+	pub fn for_each(self, builder: &mut Builder) {
+		builder.code[self.0] = Opcode::ForEach(builder.code.len());
+	}
 }
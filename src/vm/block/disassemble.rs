@@ -0,0 +1,166 @@
+use super::Block;
+use crate::vm::instruction::{self, OpKind};
+use crate::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter, Write};
+
+/// A human-readable listing of a [`Block`], from [`Block::disassemble`].
+///
+/// Each instruction is listed with its offset, and constants, variables, and arguments are
+/// resolved to what they refer to. Jump targets are given labels, and functions the block declares
+/// are listed after it.
+#[derive(Debug)]
+pub struct Disassembly<'a> {
+	block: &'a Block,
+	globals: &'a HashMap<String, usize>,
+}
+
+impl Block {
+	/// Disassembles `self`. `globals` are the indices of global variables it was compiled with,
+	/// which is used for their names.
+	pub fn disassemble<'a>(&'a self, globals: &'a HashMap<String, usize>) -> Disassembly<'a> {
+		Disassembly { block: self, globals }
+	}
+}
+
+/// Formats `value` as a constant, which is like it's written in source code.
+fn constant(value: &Value, functions: &[(&Block, String)]) -> String {
+	match value {
+		Value::Text(text) => format!("{:?}", String::from_utf8_lossy(text)),
+		Value::Function(block) => {
+			let (_, name) = functions.iter().find(|(func, _)| std::ptr::eq(*func, &**block)).unwrap();
+			format!("<{name}>")
+		}
+		other => other.to_string(),
+	}
+}
+
+impl Disassembly<'_> {
+	/// Lists `block`, adding the functions it declares to `functions`.
+	fn list<'b>(
+		&self,
+		f: &mut Formatter,
+		block: &'b Block,
+		functions: &mut Vec<(&'b Block, String)>,
+		names: &[Option<&str>],
+	) -> fmt::Result {
+		for value in &block.consts {
+			if let Value::Function(func) = value {
+				let name = format!("fn{}", functions.len() + 1);
+				functions.push((func, name));
+			}
+		}
+
+		let mut decoded = Vec::new();
+		let mut ip = 0;
+		while ip < block.code.len() {
+			let start = ip;
+			let (kind, offset) = instruction::fetch(&block.code, &mut ip).unwrap();
+			decoded.push((start, kind, offset));
+		}
+
+		let targets = decoded
+			.iter()
+			.filter(|(_, kind, _)| {
+				matches!(kind, OpKind::Jump | OpKind::JumpIf | OpKind::JumpUnless | OpKind::ForEach)
+			})
+			.map(|&(_, _, offset)| offset)
+			.collect::<BTreeSet<_>>();
+		let label = |position| targets.iter().position(|&target| target == position);
+
+		for (start, kind, offset) in decoded {
+			if let Some(label) = label(start) {
+				writeln!(f, "L{label}:")?;
+			}
+
+			let mut line = format!("{start:>5}  {:<20}", kind.unpack(offset).name());
+			let mut comments = Vec::new();
+			match kind {
+				OpKind::LoadConstant => {
+					write!(line, "{offset}")?;
+					comments.push(constant(&block.consts[offset], functions));
+				}
+				OpKind::LoadVariable | OpKind::StoreVariable => {
+					write!(line, "{offset}")?;
+					comments.push(names.get(offset).copied().flatten().unwrap_or("?").to_owned());
+				}
+				OpKind::LoadArgument | OpKind::StoreArgument => {
+					write!(line, "{offset}")?;
+					comments.push(block.args[offset].clone());
+				}
+				OpKind::Jump | OpKind::JumpIf | OpKind::JumpUnless | OpKind::ForEach => {
+					write!(line, "L{}", label(offset).unwrap())?;
+				}
+				OpKind::CreateAssocArray => write!(line, "{}", offset / 2)?,
+				_ if kind.takes_offset() => write!(line, "{offset}")?,
+				_ => {}
+			}
+
+			if kind.has_implicit_path() {
+				comments.push("implicit".to_owned());
+			}
+			match kind.force() {
+				Some(true) => comments.push("force".to_owned()),
+				Some(false) => comments.push("ask".to_owned()),
+				None => {}
+			}
+
+			if !comments.is_empty() {
+				line = format!("{line:<34}; {}", comments.join(", "));
+			}
+			writeln!(f, "{}", line.trim_end())?;
+		}
+
+		if let Some(label) = label(block.code.len()) {
+			writeln!(f, "L{label}:")?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Display for Disassembly<'_> {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		let mut names = vec![None; self.globals.values().max().map_or(0, |&max| max + 1)];
+		for (name, &idx) in self.globals {
+			names[idx] = Some(name.as_str());
+		}
+
+		let mut functions = Vec::new();
+		self.list(f, self.block, &mut functions, &names)?;
+
+		// Functions can declare functions too, which get added to the end as they're listed.
+		let mut idx = 0;
+		while idx < functions.len() {
+			let (block, name) = functions[idx].clone();
+			writeln!(f, "\n{name}({}):", block.args.join(", "))?;
+			self.list(f, block, &mut functions, &names)?;
+			idx += 1;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parse2::Parser;
+	use crate::play::Env;
+	use crate::vm::{vm::Config, Vm};
+
+	#[test]
+	fn resolves_operands() {
+		let env = Env::default();
+		let expr = Parser::new(r#"n = "x"; fn f(a) a; if n do f(1)"#, &env).parse_toplevel().unwrap();
+		let (vm, block) = Vm::compile(Config::default(), expr).unwrap();
+		let listing = block.disassemble(vm.global_indices()).to_string();
+
+		assert!(listing.contains(r#"load_constant       0      ; "x""#), "{listing}");
+		assert!(listing.contains("; n"), "{listing}");
+		assert!(listing.contains("<fn1>"), "{listing}");
+		assert!(listing.contains("jump_unless         L0"), "{listing}");
+		assert!(listing.contains("\nL0:\n"), "{listing}");
+		assert!(listing.contains("\nfn1(a):\n"), "{listing}");
+		assert!(listing.contains("load_argument       0      ; a"), "{listing}");
+	}
+}
@@ -0,0 +1,638 @@
+//! The packed form of [`Opcode`]s, which is what [`Block`](crate::vm::Block)s actually run.
+#![allow(clippy::unusual_byte_groupings)] // the groupings mirror the bit layout below.
+
+use crate::vm::Opcode;
+
+/*
+ * Structure:
+ * IIIIIIII PVoxFFAA OOOOOOOO OOOOOOOO
+ * - `I` is a unique ID
+ * - `A` is the arity; for opcodes with varargs, it's how many arguments there are beyond `O`.
+ * - `F` is the "force mode":
+ *    - `00` = use cli default
+ *    - `01` = always prompt
+ *    - `10` = always force
+ * - `o` is whether it takes an offset
+ * - `O` is the offset (zero for non-offset opcodes). Offsets which don't fit in 16 bits are
+ *       preceded by a `Wide` instruction, whose offset is the upper 16 bits.
+ * - `P` is whether it is taking an implicit path parameter.
+ * - `V` is whether it takes a variable amount of arguments, which is given by its offset.
+ * - `x` is reserved
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, align(4))]
+pub struct Instruction {
+	kind: OpKind,
+	offset: u16,
+}
+
+sa::assert_eq_size!(Instruction, u32);
+sa::assert_eq_align!(Instruction, u32);
+
+#[rustfmt::skip]
+macro_rules! declop {
+	($id:literal $($rest:tt)*) => {
+		($id << 8 | declop!(@ $($rest)*)) as u16
+	};
+	(@) => {0};
+	(@ , arity = $arity:literal $($rest:tt)*) => {
+		{sa::const_assert!($arity <= Instruction::MAX_ARITY); $arity} | declop!(@ $($rest)*)
+	};
+	(@ , offset $($rest:tt)*)   => { 0b_0010_00_00 | declop!(@ $($rest)*) };
+	(@ , varargs $($rest:tt)*)  => { 0b_0100_00_00 | declop!(@ , offset $($rest)*) };
+	(@ , implicit $($rest:tt)*) => { 0b_1000_00_00 | declop!(@ $($rest)*) };
+	(@ , ask $($rest:tt)*)      => { 0b_0000_01_00 | declop!(@ $($rest)*) };
+	(@ , force $($rest:tt)*)    => { 0b_0000_10_00 | declop!(@ $($rest)*) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+#[rustfmt::skip]
+pub enum OpKind {
+	Wide = declop!(0, offset),
+
+	LoadConstant  = declop!(1, offset),
+	LoadArgument  = declop!(2, offset),
+	LoadVariable  = declop!(3, offset),
+	StoreArgument = declop!(4, arity = 1, offset),
+	StoreVariable = declop!(5, arity = 1, offset),
+
+	Dup  = declop!(6, arity = 1),
+	Dup2 = declop!(7, arity = 2),
+	Pop  = declop!(8, arity = 1),
+
+	GenericCall = declop!(9, arity = 1, varargs),
+
+	CreatePath       = declop!(10, varargs),
+	CreateRegex      = declop!(11, arity = 1, varargs),
+	CreateString     = declop!(12, varargs),
+	CreateArray      = declop!(13, varargs),
+	CreateAssocArray = declop!(14, varargs), // the offset is twice the amount of pairs
+
+	Index    = declop!(15, arity = 2),
+	SetIndex = declop!(16, arity = 3),
+
+	Return     = declop!(17, arity = 1),
+	Jump       = declop!(18, offset),
+	JumpIf     = declop!(19, arity = 1, offset),
+	JumpUnless = declop!(20, arity = 1, offset),
+	ForEach    = declop!(21, arity = 2, offset),
+
+	Not           = declop!(22, arity = 1),
+	Negate        = declop!(23, arity = 1),
+	UPositive     = declop!(24, arity = 1),
+	ForcedLogical = declop!(25, arity = 1),
+
+	Add      = declop!(30, arity = 2),
+	Subtract = declop!(31, arity = 2),
+	Multiply = declop!(32, arity = 2),
+	Divide   = declop!(33, arity = 2),
+	Modulo   = declop!(34, arity = 2),
+
+	Matches            = declop!(40, arity = 2),
+	NotMatches         = declop!(41, arity = 2),
+	Equal              = declop!(42, arity = 2),
+	NotEqual           = declop!(43, arity = 2),
+	LessThan           = declop!(44, arity = 2),
+	LessThanOrEqual    = declop!(45, arity = 2),
+	GreaterThan        = declop!(46, arity = 2),
+	GreaterThanOrEqual = declop!(47, arity = 2),
+
+	// Querying functions
+	IsFile        = declop!(50, arity = 1),
+	IsFileI       = declop!(50, implicit),
+	IsDirectory   = declop!(51, arity = 1),
+	IsDirectoryI  = declop!(51, implicit),
+	IsExecutable  = declop!(52, arity = 1),
+	IsExecutableI = declop!(52, implicit),
+	IsSymlink     = declop!(53, arity = 1),
+	IsSymlinkI    = declop!(53, implicit),
+	IsBinary      = declop!(54, arity = 1),
+	IsBinaryI     = declop!(54, implicit),
+	IsHidden      = declop!(55, arity = 1),
+	IsHiddenI     = declop!(55, implicit),
+	IsGitIgnored  = declop!(56, arity = 1),
+	IsGitIgnoredI = declop!(56, implicit),
+	IsBroken      = declop!(57, arity = 1),
+	IsBrokenI     = declop!(57, implicit),
+	Mime          = declop!(58, arity = 1),
+	MimeI         = declop!(58, implicit),
+	Encoding      = declop!(59, arity = 1),
+	EncodingI     = declop!(59, implicit),
+	IsOk          = declop!(60, varargs),
+
+	// Path-related functions
+	FileSize    = declop!(70, arity = 1),
+	FileSizeI   = declop!(70, implicit),
+	ModifyTime  = declop!(71, arity = 1),
+	ModifyTimeI = declop!(71, implicit),
+	AccessTime  = declop!(72, arity = 1),
+	AccessTimeI = declop!(72, implicit),
+	ChangeTime  = declop!(73, arity = 1),
+	ChangeTimeI = declop!(73, implicit),
+	BirthTime   = declop!(74, arity = 1),
+	BirthTimeI  = declop!(74, implicit),
+	PushRoot    = declop!(75),
+	PushPath    = declop!(76),
+	PushPwd     = declop!(77),
+	Dirname     = declop!(78, arity = 1),
+	DirnameI    = declop!(78, implicit),
+	Extname     = declop!(79, arity = 1),
+	ExtnameI    = declop!(79, implicit),
+	ExtnameDot  = declop!(80, arity = 1),
+	ExtnameDotI = declop!(80, implicit),
+	Basename    = declop!(81, arity = 1),
+	BasenameI   = declop!(81, implicit),
+	Stemname    = declop!(82, arity = 1),
+	StemnameI   = declop!(82, implicit),
+	ReadLink    = declop!(83, arity = 1),
+	ReadLinkI   = declop!(83, implicit),
+
+	// Arrays
+	Len       = declop!(90, arity = 1),
+	Keys      = declop!(91, arity = 1),
+	Values    = declop!(92, arity = 1),
+	Push      = declop!(93, arity = 2),
+	Get       = declop!(94, arity = 3),
+	Range     = declop!(95, varargs),
+	Lines     = declop!(96, arity = 1),
+	LinesI    = declop!(96, implicit),
+	Children  = declop!(97, arity = 1),
+	ChildrenI = declop!(97, implicit),
+	Head      = declop!(98, arity = 2),
+	HeadI     = declop!(98, arity = 1, implicit),
+	Tail      = declop!(99, arity = 2),
+	TailI     = declop!(99, arity = 1, implicit),
+
+	// Misc
+	Print  = declop!(110, varargs),
+	Write  = declop!(111, varargs),
+	Skip   = declop!(112),
+	Quit   = declop!(113, arity = 1),
+	QuitI  = declop!(113, implicit),
+	Depth  = declop!(114, varargs),
+	Prune  = declop!(115),
+	Sleep  = declop!(116, arity = 1),
+	SleepI = declop!(116, implicit),
+
+	// Interactive
+	Mv    = declop!(120, arity = 2),
+	MvA   = declop!(120, arity = 2, ask),
+	MvF   = declop!(120, arity = 2, force),
+	MvI   = declop!(120, arity = 1, implicit),
+	MvIA  = declop!(120, arity = 1, implicit, ask),
+	MvIF  = declop!(120, arity = 1, implicit, force),
+
+	Rm    = declop!(121, arity = 1),
+	RmA   = declop!(121, arity = 1, ask),
+	RmF   = declop!(121, arity = 1, force),
+	RmI   = declop!(121, implicit),
+	RmIA  = declop!(121, implicit, ask),
+	RmIF  = declop!(121, implicit, force),
+
+	RmR   = declop!(122, arity = 1),
+	RmRA  = declop!(122, arity = 1, ask),
+	RmRF  = declop!(122, arity = 1, force),
+	RmRI  = declop!(122, implicit),
+	RmRIA = declop!(122, implicit, ask),
+	RmRIF = declop!(122, implicit, force),
+
+	Cp    = declop!(123, arity = 2),
+	CpA   = declop!(123, arity = 2, ask),
+	CpF   = declop!(123, arity = 2, force),
+	CpI   = declop!(123, arity = 1, implicit),
+	CpIA  = declop!(123, arity = 1, implicit, ask),
+	CpIF  = declop!(123, arity = 1, implicit, force),
+
+	Ln    = declop!(124, arity = 2),
+	LnA   = declop!(124, arity = 2, ask),
+	LnF   = declop!(124, arity = 2, force),
+	LnI   = declop!(124, arity = 1, implicit),
+	LnIA  = declop!(124, arity = 1, implicit, ask),
+	LnIF  = declop!(124, arity = 1, implicit, force),
+
+	LnS   = declop!(125, arity = 2),
+	LnSA  = declop!(125, arity = 2, ask),
+	LnSF  = declop!(125, arity = 2, force),
+	LnSI  = declop!(125, arity = 1, implicit),
+	LnSIA = declop!(125, arity = 1, implicit, ask),
+	LnSIF = declop!(125, arity = 1, implicit, force),
+
+	// Note that `mkdir` doesn't have an implicit verison.
+	Mkdir  = declop!(126, arity = 1),
+	Touch  = declop!(127, arity = 1),
+	TouchI = declop!(127, implicit),
+}
+
+impl Instruction {
+	pub const MAX_ARITY: usize = 3;
+
+	pub const fn new(kind: OpKind, offset: u16) -> Self {
+		// either we take an offset or `offset` should be zero.
+		assert!(kind.takes_offset() || offset == 0);
+
+		Self { kind, offset }
+	}
+
+	pub const fn kind(self) -> OpKind {
+		self.kind
+	}
+
+	pub const fn offset(self) -> usize {
+		self.offset as usize
+	}
+}
+
+impl OpKind {
+	const fn data(self) -> u8 {
+		self as u16 as u8
+	}
+
+	/// How many values this pops off the stack, given its `offset`.
+	pub const fn arity(self, offset: usize) -> usize {
+		let arity = (self.data() & 0b0000_00_11) as usize;
+		if self.has_va_args() {
+			arity + offset
+		} else {
+			arity
+		}
+	}
+
+	/// Whether to always force (`Some(true)`), always prompt (`Some(false)`), or use the default.
+	pub const fn force(self) -> Option<bool> {
+		match self.data() & 0b0000_11_00 {
+			0b0000_01_00 => Some(false),
+			0b0000_10_00 => Some(true),
+			_ => None,
+		}
+	}
+
+	pub const fn takes_offset(self) -> bool {
+		self.data() & 0b_0010_0000 != 0
+	}
+
+	pub const fn has_implicit_path(self) -> bool {
+		self.data() & 0b_1000_0000 != 0
+	}
+
+	pub const fn has_va_args(self) -> bool {
+		self.data() & 0b_0100_0000 != 0
+	}
+
+	/// The name of the opcode, for error messages.
+	pub fn name(self) -> &'static str {
+		self.unpack(0).name()
+	}
+
+	/// Converts `self` back into the [`Opcode`] it was packed from.
+	pub fn unpack(self, offset: usize) -> Opcode {
+		use OpKind::*;
+
+		let implicit = self.has_implicit_path();
+		let force = self.force();
+		match self {
+			Wide => Opcode::Illegal,
+
+			LoadConstant => Opcode::LoadConstant(offset),
+			LoadArgument => Opcode::LoadArgument(offset),
+			LoadVariable => Opcode::LoadVariable(offset),
+			StoreArgument => Opcode::StoreArgument(offset),
+			StoreVariable => Opcode::StoreVariable(offset),
+
+			Dup => Opcode::Dup,
+			Dup2 => Opcode::Dup2,
+			Pop => Opcode::Pop,
+
+			GenericCall => Opcode::GenericCall(offset),
+
+			CreatePath => Opcode::CreatePath(offset),
+			CreateRegex => Opcode::CreateRegex(offset),
+			CreateString => Opcode::CreateString(offset),
+			CreateArray => Opcode::CreateArray(offset),
+			CreateAssocArray => Opcode::CreateAssocArray(offset / 2),
+
+			Index => Opcode::Index,
+			SetIndex => Opcode::SetIndex,
+
+			Return => Opcode::Return,
+			Jump => Opcode::Jump(offset),
+			JumpIf => Opcode::JumpIf(offset),
+			JumpUnless => Opcode::JumpUnless(offset),
+			ForEach => Opcode::ForEach(offset),
+
+			Not => Opcode::Not,
+			Negate => Opcode::Negate,
+			UPositive => Opcode::UPositive,
+			ForcedLogical => Opcode::ForcedLogical,
+
+			Add => Opcode::Add,
+			Subtract => Opcode::Subtract,
+			Multiply => Opcode::Multiply,
+			Divide => Opcode::Divide,
+			Modulo => Opcode::Modulo,
+
+			Matches => Opcode::Matches,
+			NotMatches => Opcode::NotMatches,
+			Equal => Opcode::Equal,
+			NotEqual => Opcode::NotEqual,
+			LessThan => Opcode::LessThan,
+			LessThanOrEqual => Opcode::LessThanOrEqual,
+			GreaterThan => Opcode::GreaterThan,
+			GreaterThanOrEqual => Opcode::GreaterThanOrEqual,
+
+			IsFile | IsFileI => Opcode::IsFile { implicit },
+			IsDirectory | IsDirectoryI => Opcode::IsDirectory { implicit },
+			IsExecutable | IsExecutableI => Opcode::IsExecutable { implicit },
+			IsSymlink | IsSymlinkI => Opcode::IsSymlink { implicit },
+			IsBinary | IsBinaryI => Opcode::IsBinary { implicit },
+			IsHidden | IsHiddenI => Opcode::IsHidden { implicit },
+			IsGitIgnored | IsGitIgnoredI => Opcode::IsGitIgnored { implicit },
+			IsBroken | IsBrokenI => Opcode::IsBroken { implicit },
+			Mime | MimeI => Opcode::Mime { implicit },
+			Encoding | EncodingI => Opcode::Encoding { implicit },
+			IsOk => Opcode::IsOk(offset),
+
+			FileSize | FileSizeI => Opcode::FileSize { implicit },
+			ModifyTime | ModifyTimeI => Opcode::ModifyTime { implicit },
+			AccessTime | AccessTimeI => Opcode::AccessTime { implicit },
+			ChangeTime | ChangeTimeI => Opcode::ChangeTime { implicit },
+			BirthTime | BirthTimeI => Opcode::BirthTime { implicit },
+			PushRoot => Opcode::PushRoot,
+			PushPath => Opcode::PushPath,
+			PushPwd => Opcode::PushPwd,
+			Dirname | DirnameI => Opcode::Dirname { implicit },
+			Extname | ExtnameI => Opcode::Extname { implicit },
+			ExtnameDot | ExtnameDotI => Opcode::ExtnameDot { implicit },
+			Basename | BasenameI => Opcode::Basename { implicit },
+			Stemname | StemnameI => Opcode::Stemname { implicit },
+			ReadLink | ReadLinkI => Opcode::ReadLink { implicit },
+
+			Len => Opcode::Len,
+			Keys => Opcode::Keys,
+			Values => Opcode::Values,
+			Push => Opcode::Push,
+			Get => Opcode::Get,
+			Range => Opcode::Range(offset),
+			Lines | LinesI => Opcode::Lines { implicit },
+			Children | ChildrenI => Opcode::Children { implicit },
+			Head | HeadI => Opcode::Head { implicit },
+			Tail | TailI => Opcode::Tail { implicit },
+
+			Print => Opcode::Print(offset),
+			Write => Opcode::Write(offset),
+			Skip => Opcode::Skip,
+			Quit | QuitI => Opcode::Quit { implicit },
+			Depth => Opcode::Depth(offset),
+			Prune => Opcode::Prune,
+			Sleep | SleepI => Opcode::Sleep { implicit },
+
+			Mv | MvA | MvF | MvI | MvIA | MvIF => Opcode::Mv { implicit, force },
+			Rm | RmA | RmF | RmI | RmIA | RmIF => Opcode::Rm { implicit, force },
+			RmR | RmRA | RmRF | RmRI | RmRIA | RmRIF => Opcode::RmR { implicit, force },
+			Cp | CpA | CpF | CpI | CpIA | CpIF => Opcode::Cp { implicit, force },
+			Ln | LnA | LnF | LnI | LnIA | LnIF => Opcode::Ln { implicit, force },
+			LnS | LnSA | LnSF | LnSI | LnSIA | LnSIF => Opcode::LnS { implicit, force },
+			Mkdir => Opcode::Mkdir,
+			Touch | TouchI => Opcode::Touch { implicit },
+		}
+	}
+}
+
+/// Picks the [`OpKind`] for `implicit` and `force` out of `kinds`, which are in the order
+/// `[plain, ask, force, implicit, implicit ask, implicit force]`.
+fn forced(kinds: [OpKind; 6], implicit: bool, force: Option<bool>) -> OpKind {
+	let force = match force {
+		None => 0,
+		Some(false) => 1,
+		Some(true) => 2,
+	};
+
+	kinds[implicit as usize * 3 + force]
+}
+
+/// Packs `opcode`, returning its kind and offset. Jumps are to `starts[position]`, which is where
+/// the opcode at `position` ends up once packed.
+fn pack(opcode: Opcode, starts: &[usize]) -> (OpKind, usize) {
+	use OpKind::*;
+
+	macro_rules! implicit {
+		($implicit:expr, $plain:ident, $with_implicit:ident) => {
+			(if $implicit { $with_implicit } else { $plain }, 0)
+		};
+	}
+
+	match opcode {
+		Opcode::Illegal => unreachable!("<internal error: a deferred jump was never set>"),
+
+		Opcode::LoadConstant(idx) => (LoadConstant, idx),
+		Opcode::LoadArgument(idx) => (LoadArgument, idx),
+		Opcode::LoadVariable(idx) => (LoadVariable, idx),
+		Opcode::StoreArgument(idx) => (StoreArgument, idx),
+		Opcode::StoreVariable(idx) => (StoreVariable, idx),
+
+		Opcode::Dup => (Dup, 0),
+		Opcode::Dup2 => (Dup2, 0),
+		Opcode::Pop => (Pop, 0),
+
+		Opcode::GenericCall(argc) => (GenericCall, argc),
+
+		Opcode::CreatePath(num) => (CreatePath, num),
+		Opcode::CreateRegex(num) => (CreateRegex, num),
+		Opcode::CreateString(num) => (CreateString, num),
+		Opcode::CreateArray(num) => (CreateArray, num),
+		Opcode::CreateAssocArray(num) => (CreateAssocArray, num * 2),
+
+		Opcode::Index => (Index, 0),
+		Opcode::SetIndex => (SetIndex, 0),
+
+		Opcode::Return => (Return, 0),
+		Opcode::Jump(position) => (Jump, starts[position]),
+		Opcode::JumpIf(position) => (JumpIf, starts[position]),
+		Opcode::JumpUnless(position) => (JumpUnless, starts[position]),
+		Opcode::ForEach(position) => (ForEach, starts[position]),
+
+		Opcode::Not => (Not, 0),
+		Opcode::Negate => (Negate, 0),
+		Opcode::UPositive => (UPositive, 0),
+		Opcode::ForcedLogical => (ForcedLogical, 0),
+
+		Opcode::Add => (Add, 0),
+		Opcode::Subtract => (Subtract, 0),
+		Opcode::Multiply => (Multiply, 0),
+		Opcode::Divide => (Divide, 0),
+		Opcode::Modulo => (Modulo, 0),
+
+		Opcode::Matches => (Matches, 0),
+		Opcode::NotMatches => (NotMatches, 0),
+		Opcode::Equal => (Equal, 0),
+		Opcode::NotEqual => (NotEqual, 0),
+		Opcode::LessThan => (LessThan, 0),
+		Opcode::LessThanOrEqual => (LessThanOrEqual, 0),
+		Opcode::GreaterThan => (GreaterThan, 0),
+		Opcode::GreaterThanOrEqual => (GreaterThanOrEqual, 0),
+
+		Opcode::IsFile { implicit } => implicit!(implicit, IsFile, IsFileI),
+		Opcode::IsDirectory { implicit } => implicit!(implicit, IsDirectory, IsDirectoryI),
+		Opcode::IsExecutable { implicit } => implicit!(implicit, IsExecutable, IsExecutableI),
+		Opcode::IsSymlink { implicit } => implicit!(implicit, IsSymlink, IsSymlinkI),
+		Opcode::IsBinary { implicit } => implicit!(implicit, IsBinary, IsBinaryI),
+		Opcode::IsHidden { implicit } => implicit!(implicit, IsHidden, IsHiddenI),
+		Opcode::IsGitIgnored { implicit } => implicit!(implicit, IsGitIgnored, IsGitIgnoredI),
+		Opcode::IsBroken { implicit } => implicit!(implicit, IsBroken, IsBrokenI),
+		Opcode::Mime { implicit } => implicit!(implicit, Mime, MimeI),
+		Opcode::Encoding { implicit } => implicit!(implicit, Encoding, EncodingI),
+		Opcode::IsOk(argc) => (IsOk, argc),
+
+		Opcode::FileSize { implicit } => implicit!(implicit, FileSize, FileSizeI),
+		Opcode::ModifyTime { implicit } => implicit!(implicit, ModifyTime, ModifyTimeI),
+		Opcode::AccessTime { implicit } => implicit!(implicit, AccessTime, AccessTimeI),
+		Opcode::ChangeTime { implicit } => implicit!(implicit, ChangeTime, ChangeTimeI),
+		Opcode::BirthTime { implicit } => implicit!(implicit, BirthTime, BirthTimeI),
+		Opcode::PushRoot => (PushRoot, 0),
+		Opcode::PushPath => (PushPath, 0),
+		Opcode::PushPwd => (PushPwd, 0),
+		Opcode::Dirname { implicit } => implicit!(implicit, Dirname, DirnameI),
+		Opcode::Extname { implicit } => implicit!(implicit, Extname, ExtnameI),
+		Opcode::ExtnameDot { implicit } => implicit!(implicit, ExtnameDot, ExtnameDotI),
+		Opcode::Basename { implicit } => implicit!(implicit, Basename, BasenameI),
+		Opcode::Stemname { implicit } => implicit!(implicit, Stemname, StemnameI),
+		Opcode::ReadLink { implicit } => implicit!(implicit, ReadLink, ReadLinkI),
+
+		Opcode::Len => (Len, 0),
+		Opcode::Keys => (Keys, 0),
+		Opcode::Values => (Values, 0),
+		Opcode::Push => (Push, 0),
+		Opcode::Get => (Get, 0),
+		Opcode::Range(argc) => (Range, argc),
+		Opcode::Lines { implicit } => implicit!(implicit, Lines, LinesI),
+		Opcode::Children { implicit } => implicit!(implicit, Children, ChildrenI),
+		Opcode::Head { implicit } => implicit!(implicit, Head, HeadI),
+		Opcode::Tail { implicit } => implicit!(implicit, Tail, TailI),
+
+		Opcode::Print(argc) => (Print, argc),
+		Opcode::Write(argc) => (Write, argc),
+		Opcode::Skip => (Skip, 0),
+		Opcode::Quit { implicit } => implicit!(implicit, Quit, QuitI),
+		Opcode::Depth(argc) => (Depth, argc),
+		Opcode::Prune => (Prune, 0),
+		Opcode::Sleep { implicit } => implicit!(implicit, Sleep, SleepI),
+
+		Opcode::Mv { implicit, force } => {
+			(forced([Mv, MvA, MvF, MvI, MvIA, MvIF], implicit, force), 0)
+		}
+		Opcode::Rm { implicit, force } => {
+			(forced([Rm, RmA, RmF, RmI, RmIA, RmIF], implicit, force), 0)
+		}
+		Opcode::RmR { implicit, force } => {
+			(forced([RmR, RmRA, RmRF, RmRI, RmRIA, RmRIF], implicit, force), 0)
+		}
+		Opcode::Cp { implicit, force } => {
+			(forced([Cp, CpA, CpF, CpI, CpIA, CpIF], implicit, force), 0)
+		}
+		Opcode::Ln { implicit, force } => {
+			(forced([Ln, LnA, LnF, LnI, LnIA, LnIF], implicit, force), 0)
+		}
+		Opcode::LnS { implicit, force } => {
+			(forced([LnS, LnSA, LnSF, LnSI, LnSIA, LnSIF], implicit, force), 0)
+		}
+		Opcode::Mkdir => (Mkdir, 0),
+		Opcode::Touch { implicit } => implicit!(implicit, Touch, TouchI),
+	}
+}
+
+/// Packs `code`, adding `Wide` prefixes for offsets that don't fit in 16 bits.
+pub fn assemble(code: &[Opcode]) -> Vec<Instruction> {
+	// Adding a prefix moves everything after it, which can make more jumps need prefixes, so
+	// keep going until nothing changes. Each round only ever adds prefixes, so this terminates.
+	let mut wide = vec![false; code.len()];
+	loop {
+		let mut starts = Vec::with_capacity(code.len() + 1);
+		let mut position = 0;
+		for &is_wide in &wide {
+			starts.push(position);
+			position += 1 + is_wide as usize;
+		}
+		starts.push(position);
+
+		let mut changed = false;
+		for (idx, &opcode) in code.iter().enumerate() {
+			if !wide[idx] && pack(opcode, &starts).1 > u16::MAX as usize {
+				wide[idx] = true;
+				changed = true;
+			}
+		}
+
+		if changed {
+			continue;
+		}
+
+		let mut packed = Vec::with_capacity(position);
+		for (&opcode, is_wide) in code.iter().zip(wide) {
+			let (kind, offset) = pack(opcode, &starts);
+			let offset = u32::try_from(offset).expect("offsets must fit in 32 bits");
+			if is_wide {
+				packed.push(Instruction::new(OpKind::Wide, (offset >> 16) as u16));
+			}
+			packed.push(Instruction::new(kind, offset as u16));
+		}
+		return packed;
+	}
+}
+
+/// Gets the instruction at `*ip` and its offset, advancing `ip` past it.
+#[inline]
+pub fn fetch(code: &[Instruction], ip: &mut usize) -> Option<(OpKind, usize)> {
+	let instruction = *code.get(*ip)?;
+	*ip += 1;
+
+	if unlikely!(instruction.kind == OpKind::Wide) {
+		let low = code[*ip];
+		*ip += 1;
+		return Some((low.kind, instruction.offset() << 16 | low.offset()));
+	}
+
+	Some((instruction.kind, instruction.offset()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trips() {
+		let opcodes = [
+			Opcode::LoadConstant(3),
+			Opcode::CreateAssocArray(2),
+			Opcode::Head { implicit: true },
+			Opcode::Tail { implicit: false },
+			Opcode::Mv { implicit: false, force: Some(false) },
+			Opcode::RmR { implicit: true, force: Some(true) },
+			Opcode::LnS { implicit: true, force: None },
+			Opcode::Print(4),
+			Opcode::Jump(0),
+		];
+
+		let code = assemble(&opcodes);
+		let mut ip = 0;
+		for opcode in opcodes {
+			let (kind, offset) = fetch(&code, &mut ip).unwrap();
+			assert_eq!(opcode, kind.unpack(offset));
+			assert_eq!(opcode.arity(), kind.arity(offset), "{opcode:?}");
+		}
+	}
+
+	#[test]
+	fn wide_offsets() {
+		let mut opcodes = vec![Opcode::LoadConstant(70_000), Opcode::JumpIf(70_002)];
+		opcodes.extend(std::iter::repeat_n(Opcode::Pop, 70_000));
+		opcodes.push(Opcode::LoadVariable(1));
+
+		let code = assemble(&opcodes);
+		assert_eq!(opcodes.len() + 2, code.len());
+
+		let mut ip = 0;
+		assert_eq!(Some((OpKind::LoadConstant, 70_000)), fetch(&code, &mut ip));
+		let Some((OpKind::JumpIf, target)) = fetch(&code, &mut ip) else { panic!() };
+		assert_eq!(Some((OpKind::LoadVariable, 1)), fetch(&code, &mut { target }));
+	}
+}
@@ -1,17 +1,39 @@
 #![allow(unused)]
 pub mod block;
+mod instruction;
 mod opcode;
 
 pub use block::{Block, Builder};
+pub use instruction::{Instruction, OpKind};
 pub use opcode::Opcode;
 
+#[allow(clippy::module_inception)]
 pub mod vm;
 pub use vm::Vm;
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug)]
 pub enum RunError {
 	Io(std::io::Error),
+	// The contents of a file couldn't be read, eg because it's a directory or was just removed. To
+	// the traversal, the file just doesn't match.
+	Unreadable(std::io::Error),
 	InvalidType { func: &'static str, given: &'static str },
+	InvalidTypes { func: &'static str, lhs: &'static str, rhs: &'static str },
+	DivisionByZero,
+	TooLarge { func: &'static str }, // the result would've been too large, eg `"a" * 1e18`.
+	ArgumentCount { expected: usize, given: usize },
+	StackTooDeep,
+	InvalidRegex(crate::regex::RegexParseError),
+	InvalidPath(crate::pathglob::PathParseError),
+	InvalidExitStatus(f64), // `exit` was given a status that isn't a whole number from 0 to 255.
+
+	// Not actually errors, but they unwind the same way: `skip` abandons the current path, and
+	// `exit` stops everything, with the status it was given (if any).
+	Skip,
+	Exit(Option<u8>),
 }
 
 pub type RunResult<T> = Result<T, RunError>;
@@ -21,3 +43,15 @@ impl From<std::io::Error> for RunError {
 		Self::Io(err)
 	}
 }
+
+impl From<crate::regex::RegexParseError> for RunError {
+	fn from(err: crate::regex::RegexParseError) -> Self {
+		Self::InvalidRegex(err)
+	}
+}
+
+impl From<crate::pathglob::PathParseError> for RunError {
+	fn from(err: crate::pathglob::PathParseError) -> Self {
+		Self::InvalidPath(err)
+	}
+}
@@ -10,18 +10,25 @@ pub enum Opcode {
 	StoreVariable(usize),
 
 	Dup,
+	Dup2, // duplicates the top two values
 	Pop,
 
 	GenericCall(usize),
 
 	CreatePath(usize),
-	CreateRegex(usize),
+	CreateRegex(usize), // the flags are pushed before the parts
 	CreateString(usize),
+	CreateArray(usize),
+	CreateAssocArray(usize), // the amount of key-value pairs
+
+	Index,
+	SetIndex,
 
 	Return,
 	Jump(usize),
 	JumpIf(usize),
 	JumpUnless(usize),
+	ForEach(usize), // jumps to the position when there are no more elements
 
 	Not,
 	Negate,
@@ -51,10 +58,17 @@ pub enum Opcode {
 	IsBinary { implicit: bool },
 	IsHidden { implicit: bool },
 	IsGitIgnored { implicit: bool },
+	IsBroken { implicit: bool },
+	Mime { implicit: bool },
+	Encoding { implicit: bool },
 	IsOk(usize),
 
 	// Path-related funcitons
 	FileSize { implicit: bool },
+	ModifyTime { implicit: bool },
+	AccessTime { implicit: bool },
+	ChangeTime { implicit: bool },
+	BirthTime { implicit: bool },
 	PushRoot,
 	PushPath,
 	PushPwd,
@@ -63,13 +77,27 @@ pub enum Opcode {
 	ExtnameDot { implicit: bool },
 	Basename { implicit: bool },
 	Stemname { implicit: bool },
+	ReadLink { implicit: bool },
+
+	// Arrays
+	Len,
+	Keys,
+	Values,
+	Push,
+	Get,
+	Range(usize),
+	Lines { implicit: bool },
+	Children { implicit: bool },
+	Head { implicit: bool },
+	Tail { implicit: bool },
 
 	// Misc
 	Print(usize),
 	Write(usize), // same as print just no newline at end
 	Skip,
 	Quit { implicit: bool },
-	Depth { implicit: bool },
+	Depth(usize),
+	Prune,
 	Sleep { implicit: bool },
 
 	// Interactive
@@ -92,15 +120,21 @@ impl Opcode {
 			StoreArgument(_) | StoreVariable(_) => 1,
 
 			Dup | Pop => 1,
+			Dup2 => 2,
 
 			GenericCall(argc) => argc + 1,
-			CreatePath(_num) => todo!(),
-			CreateRegex(_num) => todo!(),
-			CreateString(_num) => todo!(),
+			CreatePath(num) | CreateString(num) => num,
+			CreateRegex(num) => num + 1,
+			CreateArray(num) => num,
+			CreateAssocArray(num) => num * 2,
+
+			Index => 2,
+			SetIndex => 3,
 
 			Return => 1,
 			Jump(_) => 0,
 			JumpIf(_) | JumpUnless(_) => 1,
+			ForEach(_) => 2,
 
 			Not | Negate | UPositive | ForcedLogical => 1,
 
@@ -115,29 +149,45 @@ impl Opcode {
 			| IsSymlink { implicit }
 			| IsBinary { implicit }
 			| IsHidden { implicit }
-			| IsGitIgnored { implicit } => !implicit as usize,
+			| IsGitIgnored { implicit }
+			| IsBroken { implicit }
+			| Mime { implicit }
+			| Encoding { implicit } => !implicit as usize,
 			IsOk(argc) => argc,
 
 			// Path-related funcitons
 			PushRoot | PushPath | PushPwd => 0,
 			FileSize { implicit }
+			| ModifyTime { implicit }
+			| AccessTime { implicit }
+			| ChangeTime { implicit }
+			| BirthTime { implicit }
 			| Dirname { implicit }
 			| Extname { implicit }
 			| ExtnameDot { implicit }
 			| Basename { implicit }
-			| Stemname { implicit } => !implicit as usize,
+			| Stemname { implicit }
+			| ReadLink { implicit } => !implicit as usize,
+
+			// Arrays
+			Len | Keys | Values => 1,
+			Push => 2,
+			Get => 3,
+			Range(argc) => argc,
+			Lines { implicit } | Children { implicit } => !implicit as usize,
+			Head { implicit } | Tail { implicit } => 1 + !implicit as usize,
 
 			// Misc
 			Print(argc) | Write(argc) => argc,
-			Skip => todo!(),
+			Skip => 0,
 			Quit { implicit } => !implicit as usize,
-			Depth { implicit } => (!implicit as usize) + 1,
+			Depth(argc) => argc,
+			Prune => 0,
 			Sleep { implicit } => !implicit as usize,
 
-			// Interactiv => todo!()e
+			// Interactive
+			Rm { implicit, force: _ } | RmR { implicit, force: _ } => !implicit as usize,
 			Mv { implicit, force: _ }
-			| Rm { implicit, force: _ }
-			| RmR { implicit, force: _ }
 			| Cp { implicit, force: _ }
 			| Ln { implicit, force: _ }
 			| LnS { implicit, force: _ } => (!implicit as usize) + 1,
@@ -146,16 +196,147 @@ impl Opcode {
 		}
 	}
 
+	/// The name of the opcode; for builtin functions, it's the name they're called by.
+	pub fn name(self) -> &'static str {
+		use Opcode::*;
+		match self {
+			Illegal => "illegal",
+			LoadConstant(_) => "load_constant",
+			LoadArgument(_) => "load_argument",
+			LoadVariable(_) => "load_variable",
+			StoreArgument(_) => "store_argument",
+			StoreVariable(_) => "store_variable",
+
+			Dup => "dup",
+			Dup2 => "dup2",
+			Pop => "pop",
+
+			GenericCall(_) => "call",
+
+			CreatePath(_) => "create_path",
+			CreateRegex(_) => "create_regex",
+			CreateString(_) => "create_string",
+			CreateArray(_) => "create_array",
+			CreateAssocArray(_) => "create_assoc_array",
+
+			Index => "[]",
+			SetIndex => "[]=",
+
+			Return => "return",
+			Jump(_) => "jump",
+			JumpIf(_) => "jump_if",
+			JumpUnless(_) => "jump_unless",
+			ForEach(_) => "for_each",
+
+			Not => "!",
+			Negate => "unary-",
+			UPositive => "unary+",
+			ForcedLogical => "?",
+
+			Add => "+",
+			Subtract => "-",
+			Multiply => "*",
+			Divide => "/",
+			Modulo => "%",
+
+			Matches => "=~",
+			NotMatches => "!~",
+			Equal => "==",
+			NotEqual => "!=",
+			LessThan => "<",
+			LessThanOrEqual => "<=",
+			GreaterThan => ">",
+			GreaterThanOrEqual => ">=",
+
+			// Querying
+			IsFile { .. } => "file?",
+			IsDirectory { .. } => "directory?",
+			IsExecutable { .. } => "executable?",
+			IsSymlink { .. } => "symlink?",
+			IsBinary { .. } => "binary?",
+			IsHidden { .. } => "hidden?",
+			IsGitIgnored { .. } => "gitignore?",
+			IsBroken { .. } => "broken?",
+			Mime { .. } => "mime",
+			Encoding { .. } => "encoding",
+			IsOk(_) => "ok?",
+
+			// Path-related funcitons
+			FileSize { .. } => "size",
+			ModifyTime { .. } => "mtime",
+			AccessTime { .. } => "atime",
+			ChangeTime { .. } => "ctime",
+			BirthTime { .. } => "btime",
+			PushRoot => "root",
+			PushPath => "path",
+			PushPwd => "pwd",
+			Dirname { .. } => "dirname",
+			Extname { .. } => "extname",
+			ExtnameDot { .. } => "extnamedot",
+			Basename { .. } => "basename",
+			Stemname { .. } => "stemname",
+			ReadLink { .. } => "readlink",
+
+			// Arrays
+			Len => "len",
+			Keys => "keys",
+			Values => "values",
+			Push => "push",
+			Get => "get",
+			Range(_) => "range",
+			Lines { .. } => "lines",
+			Children { .. } => "children",
+			Head { .. } => "head",
+			Tail { .. } => "tail",
+
+			// Misc
+			Print(_) => "print",
+			Write(_) => "write",
+			Skip => "skip",
+			Quit { .. } => "quit",
+			Depth(_) => "depth",
+			Prune => "prune",
+			Sleep { .. } => "sleep",
+
+			// Interactive
+			Mv { .. } => "mv",
+			Rm { .. } => "rm",
+			RmR { .. } => "rmr",
+			Cp { .. } => "cp",
+			Ln { .. } => "ln",
+			LnS { .. } => "lns",
+			Mkdir => "mkdir",
+			Touch { .. } => "touch",
+		}
+	}
+
+	/// Compiles a call to the builtin function `name` with `argc` arguments, returning whether
+	/// there is one.
 	pub fn compile_fn_call(name: &str, argc: usize, builder: &mut Builder) -> bool {
+		if matches!(name, "date" | "exec") {
+			todo!("`{name}` isn't implemented yet");
+		}
+
+		let Some(opcode) = Self::builtin(name, argc) else {
+			return false;
+		};
+
+		builder.opcode(opcode);
+		true
+	}
+
+	/// The opcode for calling the builtin function `name` with `argc` arguments, if there is one.
+	pub fn builtin(name: &str, argc: usize) -> Option<Self> {
 		macro_rules! implicit {
 			($name:ident) => {
 				implicit!($name, 0)
 			};
 			($name:ident, $amount:literal $($rest:tt)*) => {
-				builder.opcode(Self::$name { implicit: argc == $amount $($rest)* })
+				Self::$name { implicit: argc == $amount $($rest)* }
 			};
 		}
-		match (name, argc) {
+
+		Some(match (name, argc) {
 			// Querying
 			("f?" | "file?" | "isfile", 0 | 1) => implicit!(IsFile),
 			("d?" | "dir?" | "directory?" | "isdir", 0 | 1) => implicit!(IsDirectory),
@@ -164,38 +345,57 @@ impl Opcode {
 			("b?" | "bin?" | "binary?" | "isbin", 0 | 1) => implicit!(IsBinary),
 			("gi?" | "gitignore?" | "gitignored?" | "isgi", 0 | 1) => implicit!(IsGitIgnored),
 			("h?" | "hidden?" | "dot?" | "ishidden" | "isdot", 0 | 1) => implicit!(IsHidden),
-			("ok?", 1..) => builder.opcode(Opcode::IsOk(argc)),
+			("broken?" | "isbroken", 0 | 1) => implicit!(IsBroken),
+			("ok?", 1..) => Opcode::IsOk(argc),
+			("mime", 0 | 1) => implicit!(Mime),
+			("enc" | "encoding", 0 | 1) => implicit!(Encoding),
 
 			// Path-related funcitons
-			("r" | "root", 0) => builder.opcode(Self::PushRoot),
-			("p" | "path", 0) => builder.opcode(Self::PushPath),
-			("pwd", 0) => builder.opcode(Self::PushPwd),
+			("r" | "root", 0) => Self::PushRoot,
+			("p" | "path", 0) => Self::PushPath,
+			("pwd", 0) => Self::PushPwd,
 			("d" | "dir" | "dirname" | "directory" | "parent", 0 | 1) => implicit!(Dirname),
 			("e" | "ext" | "extname" | "extension", 0 | 1) => implicit!(Extname),
 			("ed" | "extd" | "extnamed" | "extnamedot" | "extensiond", 0 | 1) => implicit!(ExtnameDot),
 			("b" | "base" | "basename", 0 | 1) => implicit!(Basename),
 			("s" | "stem" | "stemname", 0 | 1) => implicit!(Stemname),
 			("z" | "size" | "filesize", 0 | 1) => implicit!(FileSize),
+			("mtime" | "modify" | "modified", 0 | 1) => implicit!(ModifyTime),
+			("atime" | "access" | "accessed", 0 | 1) => implicit!(AccessTime),
+			("ctime" | "change" | "changed", 0 | 1) => implicit!(ChangeTime),
+			("btime" | "birth" | "created", 0 | 1) => implicit!(BirthTime),
+			("readlink", 0 | 1) => implicit!(ReadLink),
+
+			// Arrays
+			("len" | "length", 1) => Self::Len,
+			("keys", 1) => Self::Keys,
+			("values", 1) => Self::Values,
+			("push", 2) => Self::Push,
+			("get", 3) => Self::Get,
+			("range", 1 | 2) => Self::Range(argc),
+			("lines", 0 | 1) => implicit!(Lines),
+			("children", 0 | 1) => implicit!(Children),
+			("head", 1 | 2) => implicit!(Head, 1),
+			("tail", 1 | 2) => implicit!(Tail, 1),
 
 			// Misc
-			("pr" | "print", _) => builder.opcode(Self::Print(argc)),
-			("wr" | "write", _) => builder.opcode(Self::Write(argc)),
-			("next" | "skip", 0) => builder.opcode(Self::Skip),
+			("pr" | "print", _) => Self::Print(argc),
+			("wr" | "write", _) => Self::Write(argc),
+			("next" | "skip", 0) => Self::Skip,
 			("q" | "quit" | "exit", 0 | 1) => implicit!(Quit),
-			("depth", 1 | 2) => implicit!(Depth, 1),
-			("date", _) => todo!(),
+			("depth", 0..=2) => Self::Depth(argc),
+			("prune", 0) => Self::Prune,
 			("sleep", 0 | 1) => implicit!(Sleep),
 
 			// Executable functions
-			("exec", _) => todo!(),
 			("mv" | "mvf" | "mvi", 1 | 2) => {
 				implicit!(Mv, 1, force: (name != "mv").then_some(name == "mvf"))
 			}
-			("rm" | "rmf" | "rmi", 1 | 2) => {
-				implicit!(Rm, 1, force: (name != "rm").then_some(name == "rmf"))
+			("rm" | "rmf" | "rmi", 0 | 1) => {
+				implicit!(Rm, 0, force: (name != "rm").then_some(name == "rmf"))
 			}
-			("rmr" | "rmrf" | "rmri", 1 | 2) => {
-				implicit!(RmR, 1, force: (name != "rmr").then_some(name == "rmrf"))
+			("rmr" | "rmrf" | "rmri", 0 | 1) => {
+				implicit!(RmR, 0, force: (name != "rmr").then_some(name == "rmrf"))
 			}
 			("cp" | "cpf" | "cpi", 1 | 2) => {
 				implicit!(Cp, 1, force: (name != "cp").then_some(name == "cpf"))
@@ -206,11 +406,9 @@ impl Opcode {
 			("lns" | "lnsf" | "lnsi", 1 | 2) => {
 				implicit!(LnS, 1, force: (name != "lns").then_some(name == "lnsf"))
 			}
-			("mkdir", 1) => builder.opcode(Self::Mkdir),
+			("mkdir", 1) => Self::Mkdir,
 			("t" | "touch", 0 | 1) => implicit!(Touch),
-			_ => return false,
-		}
-
-		true
+			_ => return None,
+		})
 	}
 }
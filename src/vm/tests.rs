@@ -0,0 +1,234 @@
+//! Runs expressions end-to-end, from source to the value they evaluate to.
+
+use crate::parse2::Parser;
+use crate::play::{Env, PathInfo};
+use crate::vm::{vm::Config, RunError, Vm};
+use crate::Value;
+use std::path::Path;
+
+// Top-level expressions are converted to booleans, so the result is assigned to a variable instead.
+fn run(source: &str) -> Result<Value, RunError> {
+	let env = Env::default();
+	let source = format!("result = ({source})");
+	let expr = Parser::new(&source, &env).parse_toplevel().unwrap_or_else(|err| {
+		panic!("{source:?} didn't parse: {}", err.kind());
+	});
+
+	let (mut vm, block) = Vm::compile(Config::default(), expr).unwrap();
+	vm.set_root(Path::new(".").into());
+	vm.set_pathinfo(PathInfo::new("Cargo.toml").unwrap());
+	block.run(&mut vm)?;
+
+	let result = vm.globals().find(|&(name, _)| name == "result").map(|(_, value)| value.clone());
+	Ok(result.unwrap_or_default())
+}
+
+fn eval(source: &str) -> String {
+	match run(source) {
+		Ok(value) => value.to_string(),
+		Err(err) => panic!("{source:?} failed: {err:?}"),
+	}
+}
+
+#[test]
+fn operators() {
+	assert_eq!("7", eval("1 + 2 * 3"));
+	assert_eq!("-1", eval("1 - 2"));
+	assert_eq!("2.5", eval("5 div 2"));
+	assert_eq!("1", eval("7 mod 3"));
+	assert_eq!("5", eval("-(2 - 7)"));
+	assert_eq!("1", eval("1 < 2 && 2 <= 2 && 3 > 2 && 3 >= 3"));
+	assert_eq!("1", eval("1 == 1 && 1 != 2"));
+	assert_eq!("0", eval("!1"));
+	assert_eq!("1", eval("?1"));
+	assert_eq!("1", eval("0 || 2"));
+	assert_eq!("0", eval("0 && 2"));
+	assert_eq!("1", eval(r#""abbc" =~ $/b+/"#));
+	assert_eq!("1", eval(r#""abc" !~ $/x/"#));
+}
+
+#[test]
+fn literals() {
+	assert_eq!("a3b", eval(r#""a{1 + 2}b""#));
+	assert_eq!("x-y", eval(r#"name = "y"; "x-{name}""#));
+	assert_eq!("[1, 2, 3]", eval("[1, 2, 3]"));
+	assert_eq!("src/main.rs", eval("name = \"main\"; src/${ name }.rs"));
+	assert_eq!("1", eval(r#"mid = "b"; "abc" =~ $/a{ mid }c/"#));
+	assert_eq!("src/main.rs", eval(r#"name = "main"; src/${name}.rs"#));
+	assert_eq!("src/main.rs", eval(r#"folder = "src"; ${folder}/main.rs"#));
+	assert_eq!("1", eval(r#"mid = "b"; "abc" =~ $/a{mid}c/"#));
+	assert_eq!("1", eval(r#"mid = "B"; "abc" =~ $/{mid}/i"#));
+	assert_eq!("src/main.rs", eval(r#"name = "main"; "{src/${name}.rs}""#));
+	assert_eq!("2", eval(r#"{"a": 1, "b": 2}["b"]"#));
+}
+
+#[test]
+fn variables_and_control_flow() {
+	assert_eq!("6", eval("n = 0; for item in [1, 2, 3] do n += item; n"));
+	assert_eq!("3", eval("i = 0; while i < 3 do i += 1; i"));
+	assert_eq!("5", eval("i = 0; while true do (i += 1; if i == 5 do break); i"));
+	assert_eq!(
+		"4",
+		eval("n = 0; for item in range(5) do (if item == 2 do continue; n += 1); n")
+	);
+	assert_eq!("2", eval("if 0 do 1 else 2"));
+	assert_eq!("3", eval("if 0 do 1 elif 1 do 3 else 2"));
+	assert_eq!("1", eval("a = 0; a ||= 1; a"));
+	assert_eq!("2", eval("a = 1; a &&= 2; a"));
+	assert_eq!("9", eval("a = 3; a *= a; a"));
+}
+
+#[test]
+fn for_loops() {
+	assert_eq!("9", eval("n = 0; for i in range(2, 5) do n += i; n"));
+	assert_eq!("3", eval("n = 0; for i in range(3) do n += 1; n"));
+	assert_eq!("5", eval("n = 5; for i in range(0) do n = 0; n"));
+	assert_eq!("5", eval("n = 5; for i in range(3, 1) do n = 0; n"));
+	assert_eq!("3", eval(r#"n = 0; for value in {"a": 1, "b": 2} do n += value; n"#));
+	assert_eq!("2", eval("item = 0; for item in [1, 2] do 0; item"));
+
+	// `break` and `continue` only affect the innermost loop.
+	assert_eq!(
+		"[00, 01, 10, 11, 20, 21]",
+		eval(
+			r#"seen = []; for i in range(3) do for j in range(5) do (if j == 2 do break; push(seen, "{i}{j}")); seen"#
+		)
+	);
+	assert_eq!(
+		"[0, 2, 4]",
+		eval("even = []; for i in range(6) do (if i mod 2 do continue; push(even, i)); even")
+	);
+
+	// The array can be changed while it's looped over.
+	assert_eq!(
+		"[1, 2, 3, 3]",
+		eval("ary = [1, 2, 3]; for i in ary do if i == 1 do push(ary, 3); ary")
+	);
+
+	assert!(matches!(run("for x in 1 do 0"), Err(RunError::InvalidType { func: "for", .. })));
+}
+
+#[test]
+fn for_lines_and_children() {
+	let lines = std::fs::read_to_string("Cargo.toml").unwrap().lines().count();
+	assert_eq!(lines.to_string(), eval("n = 0; for line in lines do n += 1; n"));
+	assert_eq!(
+		lines.to_string(),
+		eval(r#"n = 0; for line in lines("Cargo.toml") do n += 1; n"#)
+	);
+	assert_eq!(
+		"[package]",
+		eval(r#"first = ""; for line in lines do (first = line; break); first"#)
+	);
+
+	assert_eq!(
+		"[config, config.rs, lines.rs, walker.rs]",
+		eval(
+			r#"names = []; for child in children("src/play/program") do push(names, basename(child)); names"#
+		)
+	);
+	let children = std::fs::read_dir("src/ast").unwrap().count();
+	assert_eq!(
+		children.to_string(),
+		eval(r#"n = 0; for child in children("src/ast") do n += 1; n"#)
+	);
+
+	assert!(matches!(
+		run(r#"for line in lines("missing") do 0"#),
+		Err(RunError::Unreadable(_))
+	));
+	assert!(matches!(
+		run(r#"for child in children("Cargo.toml") do 0"#),
+		Err(RunError::Io(_))
+	));
+}
+
+#[test]
+fn functions() {
+	assert_eq!("7", eval("fn sub(lhs, rhs) lhs - rhs; sub(10, 3)"));
+	assert_eq!("55", eval("fn fib(n) if n < 2 do n else fib(n - 1) + fib(n - 2); fib(10)"));
+	assert_eq!("2", eval("fn inc(a) (a += 1; a); inc(1)"));
+	assert_eq!(
+		"3",
+		eval("fn f(a) (for item in [1, 2] do if item == 2 do return a + item; 0); f(1)")
+	);
+	assert_eq!("4", eval("g = 1; fn f() g += 3; f(); g"));
+	assert_eq!("12", eval("fn twice(f, num) f(f(num)); twice((fn (num) num * 2), 3)"));
+	assert_eq!("5", eval("fn f(one, two, three) one + two * three; f(1, 2, 2)"));
+}
+
+#[test]
+fn prompts() {
+	let prompts = |source: &str, prompt| {
+		let expr = Parser::new(source, &Env::default()).parse_toplevel().unwrap();
+		Vm::compile(Config::default(), expr).unwrap().1.prompts(prompt)
+	};
+
+	// Some builtins always prompt, whereas others only do when prompting's turned on.
+	assert!(prompts(r#"file? && mvi(path, "x")"#, false));
+	assert!(prompts(r#"ok?("really?") && rm"#, false));
+	assert!(prompts("fn f() (rmi; 1); file? && f()", false));
+	assert!(prompts("lns(path, path)", true));
+	assert!(!prompts("lns(path, path)", false));
+	assert!(!prompts("rmf", true));
+	assert!(!prompts(r#"touch && mkdir("x") && print(1)"#, true));
+}
+
+#[test]
+fn arrays() {
+	assert_eq!("3", eval("ary = [1, 2]; push(ary, 3); len(ary)"));
+	assert_eq!("[1, 5]", eval("ary = [1, 2]; ary[1] = 5; ary"));
+	assert_eq!("5", eval(r#"get({"a": 1}, "b", 5)"#));
+	assert_eq!("[2, 3, 4]", eval("range(2, 5)"));
+	assert_eq!("[a, b]", eval(r#"keys({"a": 1, "b": 2})"#));
+	assert_eq!("[1, 2]", eval(r#"values({"a": 1, "b": 2})"#));
+	assert_eq!("[1, [...]]", eval("ary = [1]; push(ary, ary); ary"));
+	assert_eq!("1", eval("lhs = []; push(lhs, lhs); rhs = []; push(rhs, rhs); lhs == rhs"));
+
+	// Arrays can be keys, even of themselves.
+	assert_eq!("1", eval("m = {}; m[[1]] = 1; m[[1]]"));
+	assert_eq!("", eval("m = {}; m[[1]] = 1; m[m]"));
+	assert_eq!("3", eval("m = {}; k = [m]; m[k] = 1; m[m] = 2; m[k] + m[m]"));
+}
+
+#[test]
+fn paths() {
+	assert_eq!("1", eval("file? && !dir?"));
+	assert_eq!("Cargo.toml", eval("basename"));
+	assert_eq!("Cargo", eval("stem"));
+	assert_eq!("1", eval("depth"));
+	assert_eq!("1", eval(r#"dir?("src") && !file?("src")"#));
+	assert_eq!("2", eval(r#"depth("src/main.rs")"#));
+	assert_eq!("1", eval(r#"?"[package]""#));
+}
+
+#[test]
+fn errors() {
+	assert!(matches!(run("1 + [1]"), Err(RunError::InvalidTypes { .. })));
+	assert!(matches!(run("1 =~ 2"), Err(RunError::InvalidTypes { func: "=~", .. })));
+	assert!(matches!(run("1 div 0"), Err(RunError::DivisionByZero)));
+	assert!(matches!(run(r#""a" * 1e18"#), Err(RunError::TooLarge { func: "*" })));
+	assert!(matches!(
+		run("for i in range(1e12) do 1"),
+		Err(RunError::TooLarge { func: "range" })
+	));
+	assert!(matches!(run("range(-1e12, 1e12)"), Err(RunError::TooLarge { func: "range" })));
+	assert!(matches!(run("len(1)"), Err(RunError::InvalidType { func: "len", .. })));
+	assert!(matches!(
+		run("fn f(a) a; f()"),
+		Err(RunError::ArgumentCount { expected: 1, given: 0 })
+	));
+	assert!(matches!(run("fn f() f(); f()"), Err(RunError::StackTooDeep)));
+	assert!(matches!(run("1; skip; 2"), Err(RunError::Skip)));
+	assert!(matches!(run("exit(3)"), Err(RunError::Exit(Some(3)))));
+	assert!(matches!(run("exit(255)"), Err(RunError::Exit(Some(255)))));
+	assert!(matches!(run("exit"), Err(RunError::Exit(None))));
+	for status in ["256", "-1", "1.5", "1e300"] {
+		let result = run(&format!("exit({status})"));
+		assert!(matches!(result, Err(RunError::InvalidExitStatus(_))), "{status}: {result:?}");
+	}
+	assert!(matches!(
+		run("num = 1; num(2)"),
+		Err(RunError::InvalidType { func: "call", .. })
+	));
+}
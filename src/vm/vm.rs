@@ -1,46 +1,152 @@
 use crate::ast::Expression;
+use crate::gitignore::Ignores;
 use crate::parse::ParseError;
 use crate::play::PathInfo;
 
-use crate::vm::{self, block::BuildContext, Block};
-use crate::Value;
+use crate::vm::{self, block::BuildContext, block::Frame, Block, RunError, RunResult};
+use crate::{Regex, Value};
 use std::collections::HashMap;
+use std::ops::Range;
 use std::path::Path;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 mod config;
+mod dry;
+mod fs;
 pub use config::Config;
+pub use dry::DryRun;
+pub use fs::Action;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Vm {
 	config: Config,
 	vars: Vec<Option<Value>>,
+	globals: HashMap<String, usize>,
+	stack: Vec<Value>, // kept around between runs, so it only has to be allocated once.
+	frames: Vec<Frame>, // the functions that are being called.
 	info: Option<PathInfo>,
-	root: Option<Rc<Path>>,
+	root: Option<Arc<Path>>,
+	dry: Option<Arc<Mutex<DryRun>>>,
+	ignores: Arc<Ignores>,
+	regexes: HashMap<(Vec<u8>, Vec<u8>), Regex>, // interpolated regexes, by source and flags
+	matches: Vec<Range<usize>>,                  // matches against the current file's contents
+	pruned: bool,                                // whether `prune` was called for the current file
 }
 
 impl Vm {
+	/// How deeply functions can call each other before we give up.
+	pub const MAX_CALL_DEPTH: usize = 256;
+
+	/// How many interpolated regexes are kept around before they're all thrown out.
+	const MAX_CACHED_REGEXES: usize = 64;
+
 	pub fn compile(config: Config, expr: Expression) -> Result<(Self, Block), ParseError> {
-		let mut map = HashMap::new();
+		Self::compile_with_globals(config, expr, HashMap::new())
+	}
+
+	/// Compiles `expr`, keeping the indices of globals in `map`.
+	///
+	/// Functions refer to globals by index, so code which calls functions from a previous
+	/// compilation has to be compiled with the globals that compilation ended up with.
+	pub fn compile_with_globals(
+		config: Config,
+		expr: Expression,
+		mut map: HashMap<String, usize>,
+	) -> Result<(Self, Block), ParseError> {
+		let expr = if config.optimizes() { expr.optimize(&config) } else { expr };
 		let mut builder = vm::block::Builder::new(vec![], &mut map);
 		expr.compile(&mut builder, BuildContext::TopLevel)?;
 
 		let block = builder.build();
-		let vm = Self { config, vars: vec![None; map.len()], info: None, root: None };
+		let vm = Self {
+			dry: config.is_dry().then(Default::default),
+			ignores: Default::default(),
+			config,
+			vars: vec![None; map.len()],
+			globals: map,
+			stack: Vec::new(),
+			frames: Vec::new(),
+			info: None,
+			root: None,
+			regexes: HashMap::new(),
+			matches: Vec::new(),
+			pruned: false,
+		};
 
 		Ok((vm, block))
 	}
 
-	pub fn _set_root(&mut self, root: Rc<Path>) {
+	/// Assigns `value` to the global variable `name`. Does nothing if the code never uses `name`.
+	pub fn assign_global(&mut self, name: &str, value: Value) {
+		if let Some(&idx) = self.globals.get(name) {
+			self.vars[idx] = Some(value);
+		}
+	}
+
+	/// Returns the indices of every global variable the code knows about.
+	pub fn global_indices(&self) -> &HashMap<String, usize> {
+		&self.globals
+	}
+
+	/// Returns all the global variables which have been assigned.
+	pub fn globals(&self) -> impl Iterator<Item = (&str, &Value)> {
+		self.globals.iter().filter_map(|(name, &idx)| Some((name.as_str(), self.vars[idx].as_ref()?)))
+	}
+
+	/// Takes the stack and call frames, for a [`Block`] to run with.
+	pub(super) fn take_stack(&mut self) -> (Vec<Value>, Vec<Frame>) {
+		(std::mem::take(&mut self.stack), std::mem::take(&mut self.frames))
+	}
+
+	/// Gives back what [`Vm::take_stack`] took, so the next run can reuse their allocations.
+	pub(super) fn restore_stack(&mut self, mut stack: Vec<Value>, mut frames: Vec<Frame>) {
+		stack.clear();
+		frames.clear();
+		self.stack = stack;
+		self.frames = frames;
+	}
+
+	/// Sets the path that traversal started at, which `root` and `depth` are relative to.
+	pub fn set_root(&mut self, root: Arc<Path>) {
 		self.root = Some(root);
 	}
 
+	/// The ignore files that have been read so far, for `gitignore?` and `--respect-ignore`.
+	pub fn ignores(&self) -> &Ignores {
+		&self.ignores
+	}
+
+	/// Makes this use `dry` as its plan of actions, so it's shared with other `Vm`s.
+	pub fn set_dry_run(&mut self, dry: Arc<Mutex<DryRun>>) {
+		self.dry = Some(dry);
+	}
+
+	/// The plan of actions, if this is a dry run.
+	pub fn dry_run(&self) -> Option<&Mutex<DryRun>> {
+		self.dry.as_deref()
+	}
+
+	/// Compiles `source` with `flags`, reusing the regex from last time if it's the same.
+	pub fn regex(&mut self, source: Vec<u8>, flags: Vec<u8>) -> Result<Regex, vm::RunError> {
+		let key = (source, flags);
+		if let Some(regex) = self.regexes.get(&key) {
+			return Ok(regex.clone());
+		}
+
+		let regex = Regex::new(&key.0, &key.1)?;
+		if self.regexes.len() == Self::MAX_CACHED_REGEXES {
+			self.regexes.clear();
+		}
+		self.regexes.insert(key, regex.clone());
+		Ok(regex)
+	}
+
 	pub fn config(&self) -> &Config {
 		&self.config
 	}
 
-	pub fn root(&self) -> &Rc<Path> {
-		&self.root.as_ref().unwrap()
+	pub fn root(&self) -> &Arc<Path> {
+		self.root.as_ref().unwrap()
 	}
 
 	pub fn info(&self) -> &PathInfo {
@@ -51,6 +157,27 @@ impl Vm {
 		self.info.as_mut().expect("todo: when wouldnt we have one?")
 	}
 
+	/// Reads the current file's contents, up to `--max-read`.
+	pub fn contents(&mut self) -> RunResult<Arc<[u8]>> {
+		let limit = self.config.max_read();
+		self.info_mut().contents(limit).map_err(RunError::Unreadable)
+	}
+
+	/// Whether the current file's contents, up to `--max-read`, contain `slice`.
+	pub fn contents_contains(&mut self, slice: &[u8]) -> RunResult<bool> {
+		let limit = self.config.max_read();
+		self.info_mut().contents_contains(slice, limit).map_err(RunError::Unreadable)
+	}
+
+	/// Whether content matches should skip the current file because it's binary.
+	pub fn skips_contents(&mut self) -> RunResult<bool> {
+		if self.config.searches_binary() {
+			return Ok(false);
+		}
+
+		Ok(self.info_mut().sniff().map_err(RunError::Unreadable)?.encoding.is_binary())
+	}
+
 	pub fn get_variable(&self, idx: usize) -> Option<Value> {
 		self.vars[idx].clone()
 	}
@@ -61,5 +188,27 @@ impl Vm {
 
 	pub fn set_pathinfo(&mut self, info: PathInfo) {
 		self.info = Some(info);
+		self.matches.clear();
+		self.pruned = false;
+	}
+
+	/// Records that `range` of the current file's contents was matched, for `--lines`.
+	pub fn record_match(&mut self, range: Range<usize>) {
+		self.matches.push(range);
+	}
+
+	/// Stops traversal from descending into the current directory.
+	pub fn prune(&mut self) {
+		self.pruned = true;
+	}
+
+	/// Whether `prune` was called for the current file, resetting it.
+	pub fn take_pruned(&mut self) -> bool {
+		std::mem::take(&mut self.pruned)
+	}
+
+	/// Takes the ranges that were matched in the current file's contents.
+	pub fn take_matches(&mut self) -> Vec<Range<usize>> {
+		std::mem::take(&mut self.matches)
 	}
 }
@@ -1,7 +1,7 @@
 use crate::play::{PlayError, PlayResult};
 use std::io::{self, Write};
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Config {
 	dont_print: bool,
 	count: bool,
@@ -13,18 +13,15 @@ pub struct Config {
 	ignore_os_errors: bool,
 	ignore_subcommand_errors: bool,
 	prompt: bool,
+	dry: bool,
+	lines: bool,
 	colour: bool,
+	max_read: Option<u64>,
+	binary: bool,
+	optimize: bool,
 }
 
 fn check_for_unimplemented_features(args: &crate::cli::Args) {
-	macro_rules! check {
-		($($t:ident)*) => {$(
-			if args.$t != Default::default() {
-				unimplemented!("unimplemented option: {}", stringify!($t));
-			}
-		)*};
-	}
-	check!(stable jobs prompt interactive force color);
 	if args.ignored_errors.contains(&crate::cli::IgnoreErrors::Subcommand) {
 		unimplemented!("unimplemented option: ignore subcommands");
 	}
@@ -36,25 +33,40 @@ impl From<&crate::cli::Args> for Config {
 		check_for_unimplemented_features(args);
 
 		Self {
-			dont_print: args.dont_print || args.count,
+			dont_print: args.dont_print || args.count || args.dry,
 			count: args.count,
 			print0: args.print0,
 			invert: args.invert,
 			stable: args.stable,
-			jobs: args.jobs.unwrap_or(1),
+			jobs: if args.stable || args.interactive || args.run_once {
+				1 // none of these can be done with more than one job.
+			} else {
+				args.jobs.unwrap_or_else(num_cpus::get).max(1)
+			},
 			ignore_os_errors: args.ignored_errors.contains(&IgnoreErrors::Os),
 			ignore_permission_errors: args.ignored_errors.contains(&IgnoreErrors::Permission),
 			ignore_subcommand_errors: args.ignored_errors.contains(&IgnoreErrors::Subcommand),
-			prompt: match args.prompt {
-				Prompt::Auto => atty::is(atty::Stream::Stdin),
-				Prompt::Always => true,
-				Prompt::Never => true,
+			prompt: if args.interactive {
+				true
+			} else if args.force {
+				false
+			} else {
+				match args.prompt {
+					Prompt::Auto => atty::is(atty::Stream::Stdin),
+					Prompt::Always => true,
+					Prompt::Never => false,
+				}
 			},
+			dry: args.dry,
+			lines: args.prints_lines(),
 			colour: match args.color {
 				Colour::Auto => atty::is(atty::Stream::Stdout),
 				Colour::Always => true,
-				Colour::Never => true,
+				Colour::Never => false,
 			},
+			max_read: args.max_read,
+			binary: args.binary,
+			optimize: !args.no_optimize,
 		}
 	}
 }
@@ -85,6 +97,36 @@ impl Config {
 		self.prompt
 	}
 
+	/// Whether destructive actions should be printed out instead of being done.
+	#[must_use]
+	pub fn is_dry(&self) -> bool {
+		self.dry
+	}
+
+	/// Whether every match against the contents of files should be recorded, for `--lines`.
+	#[must_use]
+	pub fn should_record_matches(&self) -> bool {
+		self.lines
+	}
+
+	/// How many bytes of each file can be read when looking at its contents.
+	#[must_use]
+	pub fn max_read(&self) -> u64 {
+		self.max_read.unwrap_or(u64::MAX)
+	}
+
+	/// Whether strings and regexes should match against the contents of binary files.
+	#[must_use]
+	pub fn searches_binary(&self) -> bool {
+		self.binary
+	}
+
+	/// Whether expressions should be optimized before they're compiled.
+	#[must_use]
+	pub fn optimizes(&self) -> bool {
+		self.optimize
+	}
+
 	#[must_use]
 	pub fn should_colour(&self) -> bool {
 		self.colour
@@ -106,13 +148,13 @@ impl Config {
 		debug_assert!(!self.dont_print);
 
 		if self.print0 {
-			return out.write_all(&[b'\0']);
+			return out.write_all(b"\0");
 		}
 
 		if cfg!(windows) {
-			out.write_all(&[b'\r', b'\n'])
+			out.write_all(b"\r\n")
 		} else {
-			out.write_all(&[b'\n'])
+			out.write_all(b"\n")
 		}
 	}
 
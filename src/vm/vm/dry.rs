@@ -0,0 +1,69 @@
+use super::fs::Action;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// The plan of actions for `--dry`.
+///
+/// Instead of touching the disk, each action is printed out, and is checked against the ones
+/// planned before it for conflicts (such as two sources being moved to the same destination).
+/// Since nothing's actually done, the plan is shared between every job.
+#[derive(Debug, Default)]
+pub struct DryRun {
+	destinations: HashMap<PathBuf, Action>, // what's created each planned destination.
+	removed: HashMap<PathBuf, Action>,      // what's removed each planned source.
+	conflicts: usize,
+}
+
+impl DryRun {
+	/// How many conflicts have been found so far.
+	pub fn conflicts(&self) -> usize {
+		self.conflicts
+	}
+
+	pub fn plan(&mut self, action: Action) -> io::Result<()> {
+		let mut conflicts = Vec::new();
+		let mut overwrites = false;
+
+		if let Some(src) = action.source().map(normalize) {
+			if let Some(prev) = src.ancestors().find_map(|path| self.removed.get(path)) {
+				conflicts.push(format!("{} was already removed by `{prev}`", src.display()));
+			}
+		}
+
+		if let Some(dst) = action.destination().map(normalize) {
+			if let Some(prev) = self.destinations.get(&dst) {
+				conflicts.push(format!("{} is also the destination of `{prev}`", dst.display()));
+			}
+
+			overwrites = !self.removed.contains_key(&dst) && std::fs::symlink_metadata(&dst).is_ok();
+			self.removed.remove(&dst);
+			self.destinations.insert(dst, action.clone());
+		}
+
+		if let Some(removed) = action.removes().map(normalize) {
+			self.destinations.remove(&removed);
+			self.removed.insert(removed, action.clone());
+		}
+
+		let mut line = action.to_string();
+		if overwrites {
+			line.push_str(" (overwrites)");
+		}
+		line.push('\n');
+		io::stdout().lock().write_all(line.as_bytes())?;
+
+		let mut stderr = io::stderr().lock();
+		for conflict in conflicts {
+			self.conflicts += 1;
+			writeln!(stderr, "conflict: {conflict}")?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Removes `.`s from `path`, so that `./a` and `a` are considered the same destination.
+fn normalize(path: &Path) -> PathBuf {
+	path.components().filter(|component| *component != Component::CurDir).collect()
+}
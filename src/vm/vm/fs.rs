@@ -0,0 +1,459 @@
+//! The destructive builtins, such as `mv` and `rm`.
+//!
+//! Each of them takes a `force`, which says whether to prompt before doing anything destructive:
+//! `None` follows [`Config::should_prompt`](super::Config::should_prompt), `Some(true)` never
+//! prompts (eg `mvf`), and `Some(false)` always prompts (eg `mvi`). They return whether the
+//! action was actually performed, which is only `false` when the user declined a prompt.
+//!
+//! Every one of them goes through [`Vm::perform`], so that `--dry` can plan them instead.
+use super::Vm;
+use crate::vm::RunResult;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Something which modifies the filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+	Mv { src: PathBuf, dst: PathBuf },
+	Cp { src: PathBuf, dst: PathBuf },
+	Ln { src: PathBuf, dst: PathBuf },
+	LnS { src: PathBuf, dst: PathBuf },
+	Rm(PathBuf),
+	RmR(PathBuf),
+	Touch(PathBuf),
+	Mkdir(PathBuf),
+}
+
+impl Action {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Mv { .. } => "mv",
+			Self::Cp { .. } => "cp",
+			Self::Ln { .. } => "ln",
+			Self::LnS { .. } => "lns",
+			Self::Rm(_) => "rm",
+			Self::RmR(_) => "rmr",
+			Self::Touch(_) => "touch",
+			Self::Mkdir(_) => "mkdir",
+		}
+	}
+
+	/// The path that's read (or removed), if any.
+	pub fn source(&self) -> Option<&Path> {
+		match self {
+			Self::Mv { src, .. } | Self::Cp { src, .. } | Self::Ln { src, .. } => Some(src),
+			Self::Rm(src) | Self::RmR(src) => Some(src),
+
+			// Symlinks can point to things that don't exist.
+			Self::LnS { .. } | Self::Touch(_) | Self::Mkdir(_) => None,
+		}
+	}
+
+	/// The path that's created (or overwritten), if any.
+	pub fn destination(&self) -> Option<&Path> {
+		match self {
+			Self::Mv { dst, .. } | Self::Cp { dst, .. } | Self::Ln { dst, .. } => Some(dst),
+			Self::LnS { dst, .. } => Some(dst),
+			Self::Rm(_) | Self::RmR(_) | Self::Touch(_) | Self::Mkdir(_) => None,
+		}
+	}
+
+	/// The path that no longer exists after the action, if any.
+	pub fn removes(&self) -> Option<&Path> {
+		match self {
+			Self::Mv { src, .. } | Self::Rm(src) | Self::RmR(src) => Some(src),
+			_ => None,
+		}
+	}
+}
+
+impl Display for Action {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Self::Mv { src, dst }
+			| Self::Cp { src, dst }
+			| Self::Ln { src, dst }
+			| Self::LnS { src, dst } => {
+				write!(f, "{} {} -> {}", self.name(), src.display(), dst.display())
+			}
+			Self::Rm(path) | Self::RmR(path) | Self::Touch(path) | Self::Mkdir(path) => {
+				write!(f, "{} {}", self.name(), path.display())
+			}
+		}
+	}
+}
+
+impl Vm {
+	fn should_prompt(&self, force: Option<bool>) -> bool {
+		force.map_or_else(|| self.config().should_prompt(), |force| !force)
+	}
+
+	/// Asks the user `question`, returning whether they answered yes.
+	fn confirm(&self, question: fmt::Arguments<'_>) -> io::Result<bool> {
+		// Lock stdin first so that prompts from different jobs don't get mixed up.
+		let mut stdin = io::stdin().lock();
+		let mut stderr = io::stderr().lock();
+		write!(stderr, "{question} [y/N] ")?;
+		stderr.flush()?;
+
+		let mut answer = String::new();
+		stdin.read_line(&mut answer)?;
+		Ok(matches!(answer.trim_start().as_bytes().first(), Some(b'y' | b'Y')))
+	}
+
+	/// Checks whether `action` should go ahead, prompting if need be.
+	fn should_perform(&self, action: &Action, force: Option<bool>) -> io::Result<bool> {
+		if !self.should_prompt(force) {
+			return Ok(true);
+		}
+
+		let name = action.name();
+		match action {
+			// Removing things is always destructive.
+			Action::Rm(src) | Action::RmR(src) => {
+				let kind = if fs::symlink_metadata(src)?.is_dir() { "directory" } else { "file" };
+				self.confirm(format_args!("{name}: remove {kind} {src:?}?"))
+			}
+
+			// Everything else only is when it'd overwrite something.
+			_ => match action.destination() {
+				Some(dst) if fs::symlink_metadata(dst).is_ok() => {
+					self.confirm(format_args!("{name}: overwrite {dst:?}?"))
+				}
+				_ => Ok(true),
+			},
+		}
+	}
+
+	/// Does `action`, unless we're doing a dry run, in which case it's planned instead.
+	pub fn perform(&self, action: Action, force: Option<bool>) -> RunResult<bool> {
+		if let Some(dry) = self.dry_run() {
+			dry.lock().unwrap().plan(action)?;
+			return Ok(true);
+		}
+
+		if !self.should_perform(&action, force)? {
+			return Ok(false);
+		}
+
+		match action {
+			Action::Mv { src, dst } => match fs::rename(&src, &dst) {
+				Err(err) if err.kind() == io::ErrorKind::CrossesDevices && !src.is_dir() => {
+					fs::copy(&src, &dst)?;
+					fs::remove_file(&src)?;
+				}
+				other => other?,
+			},
+			Action::Cp { src, dst } => {
+				fs::copy(src, dst)?;
+			}
+			Action::Ln { src, dst } => replace(&dst, |tmp| fs::hard_link(&src, tmp))?,
+			Action::LnS { src, dst } => replace(&dst, |tmp| symlink(&src, tmp))?,
+			Action::Rm(src) => {
+				if fs::symlink_metadata(&src)?.is_dir() {
+					fs::remove_dir(src)?;
+				} else {
+					fs::remove_file(src)?;
+				}
+			}
+			Action::RmR(src) => {
+				if fs::symlink_metadata(&src)?.is_dir() {
+					fs::remove_dir_all(src)?;
+				} else {
+					fs::remove_file(src)?;
+				}
+			}
+			Action::Touch(path) => {
+				let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+				file.set_modified(std::time::SystemTime::now())?;
+			}
+			Action::Mkdir(path) => fs::create_dir_all(path)?,
+		}
+
+		Ok(true)
+	}
+
+	pub fn mv(&self, src: &Path, dst: &Path, force: Option<bool>) -> RunResult<bool> {
+		let dst = destination(src, dst);
+		if src == dst {
+			return Ok(true);
+		}
+
+		self.perform(Action::Mv { src: src.to_owned(), dst }, force)
+	}
+
+	pub fn cp(&self, src: &Path, dst: &Path, force: Option<bool>) -> RunResult<bool> {
+		let dst = destination(src, dst);
+		if same_file(src, &dst) {
+			return Ok(true);
+		}
+
+		self.perform(Action::Cp { src: src.to_owned(), dst }, force)
+	}
+
+	/// Removes `src`. If it's a directory, it must be empty, like `rmdir`.
+	pub fn rm(&self, src: &Path, force: Option<bool>) -> RunResult<bool> {
+		self.perform(Action::Rm(src.to_owned()), force)
+	}
+
+	/// Removes `src` and, if it's a directory, everything within it.
+	pub fn rmr(&self, src: &Path, force: Option<bool>) -> RunResult<bool> {
+		self.perform(Action::RmR(src.to_owned()), force)
+	}
+
+	/// Creates a link at `dst` pointing to `src`; it's a symlink if `symbolic` is set.
+	pub fn ln(
+		&self,
+		src: &Path,
+		dst: &Path,
+		symbolic: bool,
+		force: Option<bool>,
+	) -> RunResult<bool> {
+		let (src, dst) = (src.to_owned(), destination(src, dst));
+
+		// Symlinks are relative to the directory they're in, not to us.
+		let target = if symbolic { dst.with_file_name(&src) } else { src.clone() };
+		if same_file(&target, &dst) {
+			return Ok(true);
+		}
+
+		if symbolic {
+			self.perform(Action::LnS { src, dst }, force)
+		} else {
+			self.perform(Action::Ln { src, dst }, force)
+		}
+	}
+
+	/// Creates `path` if it doesn't exist, otherwise updates its modification time.
+	pub fn touch(&self, path: &Path) -> RunResult<bool> {
+		self.perform(Action::Touch(path.to_owned()), None)
+	}
+
+	/// Creates the directory `path`, along with any of its parents.
+	pub fn mkdir(&self, path: &Path) -> RunResult<bool> {
+		self.perform(Action::Mkdir(path.to_owned()), None)
+	}
+}
+
+/// Like the shell commands, moving (or copying or linking) something to a directory actually puts
+/// it inside of that directory.
+fn destination(src: &Path, dst: &Path) -> PathBuf {
+	match src.file_name() {
+		Some(name) if dst.is_dir() => dst.join(name),
+		_ => dst.to_owned(),
+	}
+}
+
+/// Whether `lhs` and `rhs` both exist and are the same file, in which case copying (or linking)
+/// one to the other would destroy it.
+fn same_file(lhs: &Path, rhs: &Path) -> bool {
+	match (fs::canonicalize(lhs), fs::canonicalize(rhs)) {
+		(Ok(lhs), Ok(rhs)) => lhs == rhs,
+		_ => false,
+	}
+}
+
+/// Unlike `rename` and `copy`, links can't overwrite things. So they're made with `create` under a
+/// temporary name next to `dst`, and then renamed over it, so `dst` is left alone if that fails.
+fn replace(dst: &Path, create: impl Fn(&Path) -> io::Result<()>) -> io::Result<()> {
+	static COUNTER: AtomicUsize = AtomicUsize::new(0);
+	let name = dst.file_name().unwrap_or_default().to_string_lossy();
+
+	let tmp = loop {
+		let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+		let tmp = dst.with_file_name(format!(".{name}.ff-{}-{count}", std::process::id()));
+		match create(&tmp) {
+			Ok(()) => break tmp,
+			Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+			Err(err) => return Err(err),
+		}
+	};
+
+	fs::rename(&tmp, dst).inspect_err(|_| {
+		let _ = fs::remove_file(&tmp);
+	})
+}
+
+#[cfg(unix)]
+fn symlink(src: &Path, dst: &Path) -> io::Result<()> {
+	std::os::unix::fs::symlink(src, dst)
+}
+
+#[cfg(windows)]
+fn symlink(src: &Path, dst: &Path) -> io::Result<()> {
+	if src.is_dir() {
+		std::os::windows::fs::symlink_dir(src, dst)
+	} else {
+		std::os::windows::fs::symlink_file(src, dst)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parse2::Parser;
+	use crate::play::Env;
+	use crate::vm::{vm::Config, RunError};
+
+	fn vm() -> Vm {
+		let expr = Parser::new("1", &Env::default()).parse_toplevel().unwrap();
+		Vm::compile(Config::default(), expr).unwrap().0
+	}
+
+	/// Creates an empty scratch directory for the test `name`.
+	fn scratch(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("ff-fs-{name}-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	fn io_error(result: RunResult<bool>) -> io::ErrorKind {
+		match result {
+			Err(RunError::Io(err)) => err.kind(),
+			other => panic!("expected an io error, got {other:?}"),
+		}
+	}
+
+	#[test]
+	fn mv_and_cp() {
+		let (vm, dir) = (vm(), scratch("mv"));
+		fs::create_dir(dir.join("into")).unwrap();
+		fs::write(dir.join("a"), "a").unwrap();
+		fs::write(dir.join("b"), "b").unwrap();
+
+		// Moving or copying into a directory puts it inside of it.
+		assert!(vm.mv(&dir.join("a"), &dir.join("into"), None).unwrap());
+		assert!(!dir.join("a").exists());
+		assert_eq!("a", fs::read_to_string(dir.join("into/a")).unwrap());
+
+		assert!(vm.cp(&dir.join("b"), &dir.join("into"), None).unwrap());
+		assert_eq!("b", fs::read_to_string(dir.join("b")).unwrap());
+		assert_eq!("b", fs::read_to_string(dir.join("into/b")).unwrap());
+
+		// Without prompting, existing files are overwritten.
+		assert!(vm.mv(&dir.join("into/a"), &dir.join("into/b"), Some(true)).unwrap());
+		assert_eq!("a", fs::read_to_string(dir.join("into/b")).unwrap());
+		assert!(vm.cp(&dir.join("b"), &dir.join("into/b"), None).unwrap());
+		assert_eq!("b", fs::read_to_string(dir.join("into/b")).unwrap());
+
+		// Moving or copying something onto itself does nothing.
+		assert!(vm.mv(&dir.join("b"), &dir.join("b"), None).unwrap());
+		assert_eq!("b", fs::read_to_string(dir.join("b")).unwrap());
+		assert!(vm.cp(&dir.join("b"), &dir.join("b"), None).unwrap());
+		assert!(vm.cp(&dir.join("b"), &dir.join("into/../b"), None).unwrap());
+		assert_eq!("b", fs::read_to_string(dir.join("b")).unwrap());
+
+		assert_eq!(io::ErrorKind::NotFound, io_error(vm.mv(&dir.join("a"), &dir.join("c"), None)));
+		assert_eq!(io::ErrorKind::NotFound, io_error(vm.cp(&dir.join("a"), &dir.join("c"), None)));
+		assert!(!dir.join("c").exists());
+
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn rm_and_rmr() {
+		let (vm, dir) = (vm(), scratch("rm"));
+		fs::create_dir_all(dir.join("full/nested")).unwrap();
+		fs::write(dir.join("full/nested/file"), "").unwrap();
+		fs::create_dir(dir.join("empty")).unwrap();
+		fs::write(dir.join("file"), "").unwrap();
+
+		assert!(vm.rm(&dir.join("file"), None).unwrap());
+		assert!(!dir.join("file").exists());
+		assert!(vm.rm(&dir.join("empty"), None).unwrap());
+		assert!(!dir.join("empty").exists());
+
+		// Like `rmdir`, `rm` doesn't remove directories with anything in them.
+		assert_eq!(io::ErrorKind::DirectoryNotEmpty, io_error(vm.rm(&dir.join("full"), None)));
+		assert!(dir.join("full/nested/file").exists());
+
+		assert!(vm.rmr(&dir.join("full"), None).unwrap());
+		assert!(!dir.join("full").exists());
+
+		assert_eq!(io::ErrorKind::NotFound, io_error(vm.rm(&dir.join("file"), None)));
+		assert_eq!(io::ErrorKind::NotFound, io_error(vm.rmr(&dir.join("full"), None)));
+
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn mkdir_and_touch() {
+		let (vm, dir) = (vm(), scratch("mkdir"));
+
+		assert!(vm.mkdir(&dir.join("a/b/c")).unwrap());
+		assert!(dir.join("a/b/c").is_dir());
+		assert!(vm.mkdir(&dir.join("a/b")).unwrap()); // it's fine if it already exists.
+
+		let file = dir.join("a/file");
+		assert!(vm.touch(&file).unwrap());
+		assert_eq!(0, fs::metadata(&file).unwrap().len());
+
+		let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+		fs::write(&file, "contents").unwrap();
+		fs::File::options().write(true).open(&file).unwrap().set_modified(old).unwrap();
+		assert!(vm.touch(&file).unwrap());
+		assert!(fs::metadata(&file).unwrap().modified().unwrap() > old);
+		assert_eq!("contents", fs::read_to_string(&file).unwrap());
+
+		assert_eq!(io::ErrorKind::NotFound, io_error(vm.touch(&dir.join("missing/file"))));
+
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn ln_and_lns() {
+		use std::os::unix::fs::MetadataExt;
+		let (vm, dir) = (vm(), scratch("ln"));
+		fs::create_dir(dir.join("into")).unwrap();
+		fs::write(dir.join("src"), "src").unwrap();
+		fs::write(dir.join("existing"), "existing").unwrap();
+
+		// Hard links are the same file.
+		assert!(vm.ln(&dir.join("src"), &dir.join("hard"), false, None).unwrap());
+		assert!(!fs::symlink_metadata(dir.join("hard")).unwrap().is_symlink());
+		assert_eq!(
+			fs::metadata(dir.join("src")).unwrap().ino(),
+			fs::metadata(dir.join("hard")).unwrap().ino()
+		);
+
+		// Symlinks just point to their source, which needn't exist.
+		assert!(vm.ln(&dir.join("src"), &dir.join("soft"), true, None).unwrap());
+		assert_eq!(dir.join("src"), fs::read_link(dir.join("soft")).unwrap());
+		assert!(vm.ln(&dir.join("missing"), &dir.join("dangling"), true, None).unwrap());
+		assert_eq!(dir.join("missing"), fs::read_link(dir.join("dangling")).unwrap());
+		assert_eq!(
+			io::ErrorKind::NotFound,
+			io_error(vm.ln(&dir.join("missing"), &dir.join("x"), false, None))
+		);
+
+		// Links go into directories too, and replace what's already there.
+		assert!(vm.ln(&dir.join("src"), &dir.join("into"), true, None).unwrap());
+		assert_eq!(dir.join("src"), fs::read_link(dir.join("into/src")).unwrap());
+		assert!(vm.ln(&dir.join("src"), &dir.join("existing"), false, None).unwrap());
+		assert_eq!("src", fs::read_to_string(dir.join("existing")).unwrap());
+
+		// Linking something to itself does nothing, rather than removing it.
+		fs::write(dir.join("self"), "self").unwrap();
+		assert!(vm.ln(&dir.join("self"), &dir.join("self"), false, None).unwrap());
+		assert!(vm.ln(Path::new("self"), &dir.join("self"), true, None).unwrap());
+		assert!(vm.ln(&dir.join("self"), &dir.join("into/../self"), true, None).unwrap());
+		assert_eq!("self", fs::read_to_string(dir.join("self")).unwrap());
+		assert!(!fs::symlink_metadata(dir.join("self")).unwrap().is_symlink());
+
+		// If the link can't be made, what's already there is left alone.
+		assert_eq!(
+			io::ErrorKind::NotFound,
+			io_error(vm.ln(&dir.join("missing"), &dir.join("self"), false, None))
+		);
+		assert_eq!("self", fs::read_to_string(dir.join("self")).unwrap());
+		let mut names = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name());
+		assert!(!names.any(|name| name.to_string_lossy().starts_with('.'))); // no temporary files
+
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
@@ -0,0 +1,151 @@
+//! Runs the `ff` binary within scratch directories.
+#![allow(dead_code)] // not every test uses every helper.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// A scratch directory which is removed once it's dropped.
+#[derive(Debug)]
+pub struct Sandbox {
+	root: PathBuf,
+}
+
+/// What running `ff` printed, and the status it exited with.
+#[derive(Debug)]
+pub struct Output {
+	pub stdout: String,
+	pub stderr: String,
+	pub status: i32,
+}
+
+impl Sandbox {
+	/// Creates an empty scratch directory. `name` has to be unique across every test.
+	pub fn new(name: &str) -> Self {
+		let root = std::env::temp_dir().join(format!("ff-test-{name}-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&root);
+		std::fs::create_dir_all(&root).unwrap();
+		Self { root }
+	}
+
+	/// Creates a scratch directory containing `files`, which are pairs of paths and contents.
+	/// Paths ending in `/` are created as empty directories instead.
+	pub fn with_files(name: &str, files: &[(&str, &str)]) -> Self {
+		let sandbox = Self::new(name);
+		for (path, contents) in files {
+			match path.strip_suffix('/') {
+				Some(dir) => sandbox.dir(dir),
+				None => sandbox.file(path, contents),
+			};
+		}
+		sandbox
+	}
+
+	pub fn path(&self, path: &str) -> PathBuf {
+		self.root.join(path)
+	}
+
+	/// Creates the file `path` with `contents`, creating any missing directories.
+	pub fn file(&self, path: &str, contents: &str) -> &Self {
+		let path = self.path(path);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, contents).unwrap();
+		self
+	}
+
+	pub fn dir(&self, path: &str) -> &Self {
+		std::fs::create_dir_all(self.path(path)).unwrap();
+		self
+	}
+
+	/// Creates `path` as a symlink to `target`.
+	#[cfg(unix)]
+	pub fn symlink(&self, path: &str, target: &str) -> &Self {
+		std::os::unix::fs::symlink(target, self.path(path)).unwrap();
+		self
+	}
+
+	#[cfg(unix)]
+	pub fn chmod(&self, path: &str, mode: u32) -> &Self {
+		use std::os::unix::fs::PermissionsExt;
+		std::fs::set_permissions(self.path(path), std::fs::Permissions::from_mode(mode)).unwrap();
+		self
+	}
+
+	pub fn exists(&self, path: &str) -> bool {
+		self.path(path).symlink_metadata().is_ok()
+	}
+
+	pub fn read(&self, path: &str) -> String {
+		std::fs::read_to_string(self.path(path)).unwrap()
+	}
+
+	/// Every path within the sandbox, relative to it and sorted, along with file contents.
+	pub fn snapshot(&self) -> Vec<(PathBuf, Option<Vec<u8>>)> {
+		fn walk(root: &Path, dir: &Path, into: &mut Vec<(PathBuf, Option<Vec<u8>>)>) {
+			for entry in std::fs::read_dir(dir).unwrap() {
+				let path = entry.unwrap().path();
+				let meta = path.symlink_metadata().unwrap();
+				let relative = path.strip_prefix(root).unwrap().to_owned();
+				if meta.is_dir() {
+					into.push((relative, None));
+					walk(root, &path, into);
+				} else if meta.is_symlink() {
+					into.push((
+						relative,
+						Some(std::fs::read_link(&path).unwrap().into_os_string().into_encoded_bytes()),
+					));
+				} else {
+					into.push((relative, Some(std::fs::read(&path).unwrap())));
+				}
+			}
+		}
+
+		let mut paths = Vec::new();
+		walk(&self.root, &self.root, &mut paths);
+		paths.sort();
+		paths
+	}
+
+	/// Runs `ff` with `args` from within the sandbox.
+	pub fn run(&self, args: &[&str]) -> Output {
+		self.run_with_input(args, "")
+	}
+
+	/// Runs `ff` with `args` from within the sandbox, with `input` as its stdin.
+	pub fn run_with_input(&self, args: &[&str], input: &str) -> Output {
+		let mut child = Command::new(env!("CARGO_BIN_EXE_ff"))
+			.args(args)
+			.current_dir(&self.root)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.unwrap();
+
+		// Dropping stdin closes it, so prompts after the input is exhausted are declined.
+		child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+		let output = child.wait_with_output().unwrap();
+
+		Output {
+			stdout: String::from_utf8(output.stdout).unwrap(),
+			stderr: String::from_utf8(output.stderr).unwrap(),
+			status: output.status.code().unwrap(),
+		}
+	}
+}
+
+impl Drop for Sandbox {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_dir_all(&self.root);
+	}
+}
+
+impl Output {
+	/// The lines that were printed, sorted so that the order jobs printed them in doesn't matter.
+	pub fn lines(&self) -> Vec<&str> {
+		let mut lines = self.stdout.lines().collect::<Vec<_>>();
+		lines.sort_unstable();
+		lines
+	}
+}
@@ -0,0 +1,88 @@
+mod common;
+use common::Sandbox;
+
+const FILES: &[(&str, &str)] = &[
+	("top.js", ""),
+	("a/one.js", ""),
+	("a/b/two.js", ""),
+	("a/b/c/three.js", ""),
+	("x/own.js", ""),
+	("x/node_modules/m/i.js", ""),
+];
+
+#[test]
+fn depth() {
+	let sandbox = Sandbox::with_files("depth-depth", FILES);
+
+	// The start position is at depth 0.
+	let output = sandbox.run(&["-n", r#"file? && print(depth, " ", basename)"#]);
+	assert_eq!(
+		["1 top.js", "2 one.js", "2 own.js", "3 two.js", "4 i.js", "4 three.js"],
+		*output.lines()
+	);
+
+	let output = sandbox.run(&["-n", r#"depth == 0 && print(depth("a/b/c", "a"), depth("a"))"#]);
+	assert_eq!("21\n", output.stdout);
+}
+
+#[test]
+fn min_and_max_depth() {
+	let sandbox = Sandbox::with_files("depth-min-max", FILES);
+
+	for jobs in ["--jobs=1", "--jobs=4"] {
+		let output = sandbox.run(&[jobs, "--max-depth=0", "1"]);
+		assert_eq!(["."], *output.lines(), "{jobs}");
+
+		let output = sandbox.run(&[jobs, "--max-depth=1", "1"]);
+		assert_eq!([".", "./a", "./top.js", "./x"], *output.lines(), "{jobs}");
+
+		let output = sandbox.run(&[jobs, "--min-depth=3", "1"]);
+		assert_eq!(
+			[
+				"./a/b/c",
+				"./a/b/c/three.js",
+				"./a/b/two.js",
+				"./x/node_modules/m",
+				"./x/node_modules/m/i.js"
+			],
+			*output.lines(),
+			"{jobs}"
+		);
+
+		let output = sandbox.run(&[jobs, "--min-depth=2", "--max-depth=2", "1"]);
+		assert_eq!(
+			["./a/b", "./a/one.js", "./x/node_modules", "./x/own.js"],
+			*output.lines(),
+			"{jobs}"
+		);
+
+		// Depths are relative to where traversal starts, not to `.`.
+		let output = sandbox.run(&[jobs, "--max-depth=1", "a/**/*.js"]);
+		assert_eq!(["./a/one.js"], *output.lines(), "{jobs}");
+		let output = sandbox.run(&[jobs, "--min-depth=2", "a/**/*.js"]);
+		assert_eq!(["./a/b/c/three.js", "./a/b/two.js"], *output.lines(), "{jobs}");
+
+		let output = sandbox.run(&[jobs, "--min-depth=2", "--count", "1"]);
+		assert_eq!("9\n", output.stdout, "{jobs}");
+	}
+}
+
+#[test]
+fn prune() {
+	let sandbox = Sandbox::with_files("depth-prune", FILES);
+
+	for jobs in ["--jobs=1", "--jobs=4"] {
+		let output = sandbox.run(&[jobs, r#"(basename == "node_modules" && prune) || **/*.js"#]);
+		assert_eq!(
+			["./a/b/c/three.js", "./a/b/two.js", "./a/one.js", "./top.js", "./x/own.js"],
+			*output.lines(),
+			"{jobs}"
+		);
+
+		// `prune` never matches, and only stops traversal into directories.
+		let output = sandbox.run(&[jobs, r#"(basename =~ $/^[ab]$/ || file?) && prune"#]);
+		assert_eq!(("", 1), (&*output.stdout, output.status), "{jobs}");
+		let output = sandbox.run(&[jobs, r#"(basename =~ $/^[ab]$/ && prune) || file?"#]);
+		assert_eq!(["./top.js", "./x/node_modules/m/i.js", "./x/own.js"], *output.lines(), "{jobs}");
+	}
+}
@@ -0,0 +1,71 @@
+mod common;
+use common::Sandbox;
+
+const FILES: &[(&str, &str)] = &[("a.txt", "a"), ("b.txt", "b"), ("full/file", ""), ("empty/", "")];
+
+#[test]
+fn changes_nothing() {
+	let sandbox = Sandbox::with_files("dry-nothing", FILES);
+	let before = sandbox.snapshot();
+
+	for (expr, planned) in [
+		(r#"mv("a.txt", "moved")"#, "mv a.txt -> moved"),
+		(r#"mvf("a.txt", "b.txt")"#, "mv a.txt -> b.txt (overwrites)"),
+		(r#"cp("a.txt", "full")"#, "cp a.txt -> full/a.txt"),
+		(r#"cpi("a.txt", "b.txt")"#, "cp a.txt -> b.txt (overwrites)"),
+		(r#"ln("a.txt", "hard")"#, "ln a.txt -> hard"),
+		(r#"lns("a.txt", "soft")"#, "lns a.txt -> soft"),
+		(r#"rm("a.txt")"#, "rm a.txt"),
+		(r#"rmi("empty")"#, "rm empty"),
+		(r#"rmr("full")"#, "rmr full"),
+		(r#"touch("new")"#, "touch new"),
+		(r#"mkdir("x/y/z")"#, "mkdir x/y/z"),
+	] {
+		// Nothing's prompted for, either.
+		let output = sandbox.run_with_input(&["--dry", "--run-once", "--interactive", expr], "");
+		assert_eq!(
+			(planned, "", 0),
+			(output.stdout.trim_end(), &*output.stderr, output.status),
+			"{expr}"
+		);
+		assert_eq!(before, sandbox.snapshot(), "{expr}");
+	}
+
+	// Matches aren't printed with `--dry`, only the plan is.
+	let output = sandbox.run(&["--dry", r#"basename == "a.txt" && rm"#]);
+	assert_eq!((["rm ./a.txt"].as_slice(), 0), (&*output.lines(), output.status));
+	assert_eq!(before, sandbox.snapshot());
+}
+
+#[test]
+fn reports_conflicts() {
+	let sandbox = Sandbox::with_files("dry-conflicts", FILES);
+	let before = sandbox.snapshot();
+
+	let output =
+		sandbox.run(&["--dry", "--run-once", r#"mv("a.txt", "same"); mv("b.txt", "same")"#]);
+	assert_eq!(["mv a.txt -> same", "mv b.txt -> same"], *output.lines());
+	assert_eq!(
+		"conflict: same is also the destination of `mv a.txt -> same`\n1 conflict found\n",
+		output.stderr
+	);
+
+	let output =
+		sandbox.run(&["--dry", "--run-once", r#"rmr("full"); cp("full/file", "x"); rm("full")"#]);
+	assert!(
+		output.stderr.contains("full/file was already removed by `rmr full`"),
+		"{output:?}"
+	);
+	assert!(output.stderr.ends_with("2 conflicts found\n"), "{output:?}");
+
+	// Anything that's moved away can be replaced, though.
+	let output = sandbox.run(&["--dry", "--run-once", r#"mv("a.txt", "c"); mv("b.txt", "a.txt")"#]);
+	assert_eq!(["mv a.txt -> c", "mv b.txt -> a.txt"], *output.lines());
+	assert_eq!("", output.stderr);
+
+	// Conflicts are found across jobs too.
+	let output = sandbox.run(&["--dry", "--jobs=4", r#"file? && mv("same")"#]);
+	assert!(output.stderr.ends_with("2 conflicts found\n"), "{output:?}");
+
+	assert_eq!(before, sandbox.snapshot());
+}
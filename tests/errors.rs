@@ -0,0 +1,42 @@
+mod common;
+use common::Sandbox;
+
+#[test]
+fn unreadable_contents_dont_match() {
+	let sandbox = Sandbox::new("errors-unreadable");
+	sandbox.file("hello.txt", "hello").file("d/other.txt", "other");
+
+	// Every directory is searched for `hello` too, but that's not an error.
+	let output = sandbox.run(&[r#""hello""#]);
+	assert_eq!(["./hello.txt"], *output.lines());
+	assert_eq!(("", 0), (&*output.stderr, output.status));
+
+	let output = sandbox.run(&[r#"head(1) == "other" || readlink == "x""#]);
+	assert_eq!(["./d/other.txt"], *output.lines());
+	assert_eq!(("", 0), (&*output.stderr, output.status));
+}
+
+#[test]
+fn other_io_errors_are_reported() {
+	let sandbox = Sandbox::new("errors-reported");
+	sandbox.file("d/inner.txt", "");
+
+	// `rm` only removes empty directories.
+	let output = sandbox.run(&[r#"basename == "d" && rm"#]);
+	assert!(output.stderr.contains("DirectoryNotEmpty"), "{output:?}");
+	assert_eq!(2, output.status);
+	assert!(sandbox.exists("d/inner.txt"));
+
+	let output = sandbox.run(&["--jobs=4", r#"basename == "d" && rm"#]);
+	assert!(output.stderr.contains("DirectoryNotEmpty"), "{output:?}");
+	assert_eq!(2, output.status);
+
+	let output = sandbox.run(&[r#"mv("missing", "elsewhere")"#]);
+	assert!(output.stderr.contains("NotFound"), "{output:?}");
+	assert_eq!(2, output.status);
+
+	// Unless they're ignored, in which case it's just not a match.
+	let output = sandbox.run(&["--ignored-errors=os", r#"basename == "d" && rm"#]);
+	assert_eq!(("", "", 1), (&*output.stdout, &*output.stderr, output.status));
+	assert!(sandbox.exists("d/inner.txt"));
+}
@@ -0,0 +1,52 @@
+mod common;
+use common::Sandbox;
+
+const FILES: &[(&str, &str)] = &[("a.txt", ""), ("b.txt", ""), ("c.md", "")];
+
+#[test]
+fn exit() {
+	let sandbox = Sandbox::with_files("exit-exit", FILES);
+
+	for (expr, status) in [
+		("exit(0)", 0),
+		("exit(3)", 3),
+		("exit(255)", 255),
+		("^{ exit(4) }", 4),
+		("${ exit(5) }", 5),
+		(r#"basename == "c.md" && exit(6)"#, 6),
+		("*.txt ${ exit }", 0),
+		("*.rs ${ exit }", 1),
+	] {
+		for jobs in ["--jobs=1", "--jobs=4"] {
+			let output = sandbox.run(&[jobs, expr]);
+			assert_eq!(("", status), (&*output.stderr, output.status), "{jobs} {expr}");
+		}
+	}
+
+	// Statuses which can't be exited with are errors, rather than being truncated.
+	for status in ["256", "-1", "1.5"] {
+		let output = sandbox.run(&[&format!("exit({status})")]);
+		assert_eq!(2, output.status, "{status}: {output:?}");
+		assert!(output.stderr.contains("InvalidExitStatus"), "{status}: {output:?}");
+	}
+}
+
+#[test]
+fn next_and_skip() {
+	let sandbox = Sandbox::with_files("exit-next", FILES);
+
+	for skip in ["next", "skip"] {
+		// Skipped paths don't match, so skipping everything is like matching nothing.
+		let output = sandbox.run(&[&format!("{skip}; 1")]);
+		assert_eq!(("", "", 1), (&*output.stdout, &*output.stderr, output.status), "{skip}");
+
+		let output = sandbox.run(&[&format!("file? && (*.md && {skip}; 1)")]);
+		assert_eq!(["./a.txt", "./b.txt"], *output.lines(), "{skip}");
+		assert_eq!(0, output.status, "{skip}");
+
+		// Outside of the main expression, there's no path to abandon, so it just stops the block.
+		let output = sandbox.run(&[&format!("^{{ {skip}; exit(7) }} file?")]);
+		assert_eq!(["./a.txt", "./b.txt", "./c.md"], *output.lines(), "{skip}");
+		assert_eq!(0, output.status, "{skip}");
+	}
+}
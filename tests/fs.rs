@@ -0,0 +1,96 @@
+mod common;
+use common::Sandbox;
+
+const FILES: &[(&str, &str)] = &[("src.txt", "src"), ("dst.txt", "dst"), ("into/", "")];
+
+#[test]
+fn prompts_before_overwriting() {
+	let sandbox = Sandbox::with_files("fs-prompts", FILES);
+
+	// Declining leaves everything as it was, and the action returns false.
+	let output = sandbox
+		.run_with_input(&["--run-once", "--interactive", r#"mv("src.txt", "dst.txt")"#], "n\n");
+	assert!(output.stderr.contains("overwrite"), "{output:?}");
+	assert_eq!(("", 1), (&*output.stdout, output.status));
+	assert_eq!(("src", "dst"), (&*sandbox.read("src.txt"), &*sandbox.read("dst.txt")));
+
+	for builtin in ["mvi", "cpi", "lni", "lnsi"] {
+		let output = sandbox
+			.run_with_input(&["--run-once", &format!(r#"{builtin}("src.txt", "dst.txt")"#)], "");
+		assert_eq!(1, output.status, "{builtin}: {output:?}");
+		assert_eq!(("src", "dst"), (&*sandbox.read("src.txt"), &*sandbox.read("dst.txt")));
+	}
+
+	let output =
+		sandbox.run_with_input(&["--run-once", "--prompt=always", r#"rm("src.txt")"#], "no\n");
+	assert!(output.stderr.contains("remove file"), "{output:?}");
+	assert!(sandbox.exists("src.txt"));
+
+	// Nothing's asked when it wouldn't overwrite anything.
+	let output = sandbox.run_with_input(&["--run-once", r#"cpi("src.txt", "new.txt")"#], "");
+	assert_eq!(("", 0), (&*output.stderr, output.status));
+	assert_eq!("src", sandbox.read("new.txt"));
+
+	// Accepting goes ahead.
+	let output = sandbox.run_with_input(&["--run-once", r#"cpi("src.txt", "dst.txt")"#], "y\n");
+	assert_eq!(0, output.status, "{output:?}");
+	assert_eq!("src", sandbox.read("dst.txt"));
+}
+
+#[test]
+fn force_never_prompts() {
+	let sandbox = Sandbox::with_files("fs-force", FILES);
+
+	// Even when prompting's requested, the `f` variants don't.
+	let output = sandbox
+		.run_with_input(&["--run-once", "--interactive", r#"cpf("src.txt", "dst.txt")"#], "n\n");
+	assert_eq!(("", 0), (&*output.stderr, output.status));
+	assert_eq!("src", sandbox.read("dst.txt"));
+
+	let output = sandbox.run(&["--run-once", "--force", r#"mv("src.txt", "into")"#]);
+	assert_eq!(("", 0), (&*output.stderr, output.status));
+	assert_eq!(("src", false), (&*sandbox.read("into/src.txt"), sandbox.exists("src.txt")));
+}
+
+#[test]
+fn failures_are_errors() {
+	let sandbox = Sandbox::with_files("fs-failures", FILES);
+	sandbox.file("full/file", "");
+
+	for expr in [
+		r#"rm("full")"#,
+		r#"mv("missing", "into")"#,
+		r#"cp("missing", "into")"#,
+		r#"ln("missing", "link")"#,
+		r#"cp("into", "copy")"#,
+		r#"touch("missing/file")"#,
+		r#"mkdir("src.txt/dir")"#,
+	] {
+		let output = sandbox.run(&["--run-once", "--force", expr]);
+		assert_eq!(2, output.status, "{expr}: {output:?}");
+		assert!(output.stderr.contains("error"), "{expr}: {output:?}");
+	}
+
+	assert!(sandbox.exists("full/file"));
+	assert!(!sandbox.exists("link") && !sandbox.exists("copy"));
+}
+
+#[test]
+fn failures_within_directories_are_errors() {
+	let sandbox = Sandbox::with_files("fs-nested-failures", FILES);
+	sandbox.file("into/a.txt", "").file("into/b.txt", "");
+
+	for jobs in ["--jobs=1", "--jobs=4"] {
+		// Only files within `into` fail, so the errors have to make it out of its traversal.
+		for expr in [r#"depth == 2, rm("./missing")"#, r#"depth == 2 && mv(path, "./missing/x")"#] {
+			let output = sandbox.run(&[jobs, "--force", expr]);
+			assert_eq!(2, output.status, "{jobs} {expr}: {output:?}");
+			assert!(output.stderr.contains("NotFound"), "{jobs} {expr}: {output:?}");
+		}
+	}
+
+	// Only the directory itself disappearing is fine, as the expression might've removed it.
+	let output = sandbox.run(&["--force", r#"basename == "into" && rmr(path)"#]);
+	assert_eq!(("./into\n", "", 0), (&*output.stdout, &*output.stderr, output.status));
+	assert!(!sandbox.exists("into"));
+}
@@ -0,0 +1,81 @@
+mod common;
+use common::Sandbox;
+
+const FILES: &[(&str, &str)] = &[
+	("f.txt", "foo\nbar\nfoo foo\nbaz\n\nqux foo\r\nlast foo"),
+	("d/g.txt", "nothing\nfood\n"),
+	("h.txt", "none"),
+];
+
+#[test]
+fn prefixes() {
+	let sandbox = Sandbox::with_files("lines-prefixes", FILES);
+
+	// Each matched line is `path:line:column:text`, without any `\r`.
+	let output = sandbox.run(&["--lines", r#"basename == "f.txt" && "foo""#]);
+	assert_eq!(
+		"./f.txt:1:1:foo\n./f.txt:3:1:foo foo\n./f.txt:6:5:qux foo\n./f.txt:7:6:last foo\n",
+		output.stdout
+	);
+	assert_eq!(0, output.status);
+
+	// The column is where the first match on the line starts.
+	let output = sandbox.run(&["--lines", r#"basename == "f.txt" && $/o+ /"#]);
+	assert_eq!("./f.txt:3:2:foo foo\n", output.stdout);
+
+	// Paths which matched without their contents are printed by themselves.
+	let output = sandbox.run(&["--lines", r#"basename == "h.txt" || "food""#]);
+	assert_eq!(["./d/g.txt:2:1:food", "./h.txt"], *output.lines());
+}
+
+#[test]
+fn multiple_matches() {
+	let sandbox = Sandbox::with_files("lines-multiple", FILES);
+
+	let output = sandbox.run(&["--lines", r#""foo""#]);
+	assert_eq!(
+		[
+			"./d/g.txt:2:1:food",
+			"./f.txt:1:1:foo",
+			"./f.txt:3:1:foo foo",
+			"./f.txt:6:5:qux foo",
+			"./f.txt:7:6:last foo",
+		],
+		*output.lines()
+	);
+
+	// Every match is recorded, even those from different checks.
+	let output = sandbox.run(&["--lines", r#"basename == "f.txt" && "bar" && $/^q/m"#]);
+	assert_eq!("./f.txt:2:1:bar\n./f.txt:6:1:qux foo\n", output.stdout);
+
+	// Each match on a line is highlighted.
+	let output = sandbox.run(&["--lines", "--color=always", r#"basename == "f.txt" && "foo""#]);
+	let (path, foo) = ("\x1b[35m./f.txt\x1b[0m", "\x1b[1;31mfoo\x1b[0m");
+	assert!(
+		output.stdout.contains(&format!("{path}:\x1b[32m3\x1b[0m:1:{foo} {foo}\n")),
+		"{output:?}"
+	);
+}
+
+#[test]
+fn context() {
+	let sandbox = Sandbox::with_files("lines-context", FILES);
+	let query = r#"basename == "f.txt" && "foo""#;
+
+	// Context lines use `-` instead of `:`, and gaps between them are marked with `--`.
+	let output = sandbox.run(&["--after-context=1", query]);
+	assert_eq!(
+		"./f.txt:1:1:foo\n./f.txt-2-bar\n./f.txt:3:1:foo foo\n./f.txt-4-baz\n--\n./f.txt:6:5:qux foo\n./f.txt:7:6:last foo\n",
+		output.stdout
+	);
+
+	let output = sandbox.run(&["--before-context=1", r#"basename == "f.txt" && "baz""#]);
+	assert_eq!("./f.txt-3-foo foo\n./f.txt:4:1:baz\n", output.stdout);
+
+	// Overlapping context is only printed once.
+	let output = sandbox.run(&["--context=2", r#"basename == "f.txt" && ("bar" && "baz")"#]);
+	assert_eq!(
+		"./f.txt-1-foo\n./f.txt:2:1:bar\n./f.txt-3-foo foo\n./f.txt:4:1:baz\n./f.txt-5-\n./f.txt-6-qux foo\n",
+		output.stdout
+	);
+}
@@ -0,0 +1,88 @@
+#![cfg(unix)]
+
+mod common;
+use common::Sandbox;
+
+const FILES: &[(&str, &str)] =
+	&[("file.txt", "file"), ("dir/inner.txt", "inner"), ("script.sh", "#!/bin/sh\n")];
+
+// Pairs of links and what they point to.
+const LINKS: &[(&str, &str)] = &[
+	("link-file", "file.txt"),
+	("link-dir", "dir"),
+	("link-script", "script.sh"),
+	("broken", "missing"),
+];
+
+#[test]
+fn predicates() {
+	let sandbox = Sandbox::with_files("links-predicates", FILES);
+	sandbox.chmod("script.sh", 0o755);
+	for (link, target) in LINKS {
+		sandbox.symlink(link, target);
+	}
+
+	let matches = |expr| {
+		let output = sandbox.run(&[expr]);
+		assert_eq!(("", 0), (&*output.stderr, output.status), "{expr}");
+		output.lines().into_iter().map(String::from).collect::<Vec<_>>()
+	};
+
+	// Only `symlink?` and `broken?` look at the links themselves.
+	assert_eq!(["./broken", "./link-dir", "./link-file", "./link-script"], *matches("symlink?"));
+	assert_eq!(["./broken"], *matches("broken?"));
+	assert_eq!(["./link-script", "./script.sh"], *matches("executable?"));
+	assert_eq!([".", "./dir", "./link-dir"], *matches("dir?"));
+	assert_eq!(
+		["./dir/inner.txt", "./file.txt", "./link-file", "./link-script", "./script.sh"],
+		*matches("file?")
+	);
+	assert_eq!(["./link-file"], *matches(r#"symlink? && "file""#));
+
+	// They can be given paths too.
+	assert_eq!(
+		["."],
+		*matches(r#"depth == 0 && symlink?("link-dir") && !symlink?("dir") && exe?("script.sh")"#)
+	);
+}
+
+#[test]
+fn readlink() {
+	let sandbox = Sandbox::with_files("links-readlink", FILES);
+	sandbox.chmod("script.sh", 0o755);
+	for (link, target) in LINKS {
+		sandbox.symlink(link, target);
+	}
+
+	let output = sandbox.run(&["-n", r#"symlink? && print(basename, " -> ", readlink)"#]);
+	assert_eq!(
+		["broken -> missing", "link-dir -> dir", "link-file -> file.txt", "link-script -> script.sh"],
+		*output.lines()
+	);
+
+	// Paths that aren't links just don't match.
+	let output = sandbox.run(&[r#"readlink == "file.txt" || readlink("dir") == "x""#]);
+	assert_eq!(["./link-file"], *output.lines());
+	assert_eq!(("", 0), (&*output.stderr, output.status));
+}
+
+#[test]
+fn follow() {
+	let sandbox = Sandbox::new("links-follow");
+	sandbox.file("tree/own.txt", "").file("other/linked.txt", "");
+	sandbox.symlink("tree/link", "../other").symlink("other/back", "../tree"); // a loop
+
+	// Symlinked directories are only traversed into with `--follow`.
+	let output = sandbox.run(&["tree/**/*.txt"]);
+	assert_eq!(["./tree/own.txt"], *output.lines());
+
+	// Each directory is only traversed once, so loops stop.
+	for jobs in ["--jobs=1", "--jobs=4"] {
+		let output = sandbox.run(&["--follow", jobs, "tree/**/*.txt"]);
+		assert_eq!(["./tree/link/linked.txt", "./tree/own.txt"], *output.lines(), "{jobs}");
+		assert_eq!(0, output.status, "{jobs}: {output:?}");
+
+		let output = sandbox.run(&["--follow", jobs, "tree/**/back"]);
+		assert_eq!(["./tree/link/back"], *output.lines(), "{jobs}");
+	}
+}
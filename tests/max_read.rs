@@ -0,0 +1,28 @@
+mod common;
+use common::Sandbox;
+
+// Files are treated as if they ended after `--max-read` bytes.
+#[test]
+fn max_read() {
+	let sandbox = Sandbox::new("max-read");
+	sandbox.file("a.txt", "one\ntwo\nthree\n");
+
+	let print = |limit: &str, what: &str| {
+		let output = sandbox.run(&[limit, "-n", &format!("print({what})")]);
+		assert_eq!(0, output.status, "{output:?}");
+		output.stdout
+	};
+
+	assert_eq!("two\n\n", print("--max-read=8", "tail(1)"));
+	assert_eq!("tw\n", print("--max-read=6", "tail(1)"));
+	assert_eq!("one\ntw\n", print("--max-read=6", "head(5)"));
+	assert_eq!("[one, tw]\n", print("--max-read=6", "lines"));
+	assert_eq!("three\n\n", print("--max-read=1kb", "tail(1)"));
+
+	assert_eq!("", sandbox.run(&["--max-read=8", r#""three""#]).stdout);
+	assert_eq!("", sandbox.run(&["--max-read=8", "$/^three/m"]).stdout);
+	assert_eq!("./a.txt\n", sandbox.run(&["--max-read=9", r#""two\nt""#]).stdout);
+
+	let output = sandbox.run(&["--max-read=8", "--lines", "$/o$/m"]);
+	assert_eq!("./a.txt:2:3:two\n", output.stdout);
+}
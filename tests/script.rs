@@ -0,0 +1,68 @@
+mod common;
+use common::Sandbox;
+
+const FILES: &[(&str, &str)] = &[("a.txt", ""), ("b.txt", ""), ("d/c.txt", "")];
+
+#[test]
+fn begin_and_end_blocks() {
+	let sandbox = Sandbox::with_files("script-blocks", FILES);
+
+	// Begin blocks set up variables for the main expression, and end blocks see what it did.
+	let output = sandbox.run(&["-n", r#"^{ n = 10 } file? && (n += 1) ${ print("total: {n}") }"#]);
+	assert_eq!(("total: 13\n", 0), (&*output.stdout, output.status));
+
+	// They're run in the order they're written, wherever they are.
+	let output = sandbox.run(&[
+		"-n",
+		r#"${ print("end 1") } ^{ print("begin 1") } file? ${ print("end 2") } ^{ print("begin 2") }"#,
+	]);
+	assert_eq!("begin 1\nbegin 2\nend 1\nend 2\n", output.stdout);
+
+	// Matches are printed between them.
+	let output = sandbox.run(&[r#"^{ print("begin") } basename == "c.txt" ${ print("end") }"#]);
+	assert_eq!("begin\n./d/c.txt\nend\n", output.stdout);
+}
+
+#[test]
+fn only_blocks_dont_traverse() {
+	let sandbox = Sandbox::with_files("script-only-blocks", FILES);
+
+	let output = sandbox.run(&[r#"^{ print("begin") } ${ print("end") }"#]);
+	assert_eq!(("begin\nend\n", 0), (&*output.stdout, output.status));
+
+	let output = sandbox.run(&[r#"^{ for line in lines("a.txt") do print(line) }"#]);
+	assert_eq!(("", ""), (&*output.stdout, &*output.stderr));
+}
+
+#[test]
+fn end_blocks_see_every_job() {
+	let sandbox = Sandbox::with_files("script-jobs", FILES);
+	for idx in 0..20 {
+		sandbox.file(&format!("dir{}/file{idx}", idx % 4), "");
+	}
+
+	let output = sandbox.run(&["--jobs=8", "-n", r#"^{ n = 0 } file? && (n += 1) ${ print(n) }"#]);
+	assert_eq!("23\n", output.stdout);
+}
+
+#[test]
+fn blocks_share_a_dry_run() {
+	let sandbox = Sandbox::with_files("script-dry", FILES);
+
+	let output = sandbox.run(&["--dry", r#"^{ mv("a.txt", "x") } ${ mv("b.txt", "x") }"#]);
+	assert_eq!("mv a.txt -> x\nmv b.txt -> x\n", output.stdout);
+	assert!(output.stderr.ends_with("1 conflict found\n"), "{output:?}");
+	assert!(sandbox.exists("a.txt") && sandbox.exists("b.txt"));
+}
+
+#[test]
+fn imports_cant_have_blocks() {
+	let sandbox = Sandbox::with_files("script-imports", FILES);
+	sandbox.file("lib.ff", "fn double(n) n * 2\n").file("bad.ff", "^{ print(1) }\n");
+
+	let output = sandbox.run(&["-I", "lib.ff", "--run-once", "-n", "print(double(21))"]);
+	assert_eq!(("42\n", 0), (&*output.stdout, output.status));
+
+	let output = sandbox.run(&["-I", "bad.ff", "--run-once", "1"]);
+	assert_eq!(("", 2), (&*output.stdout, output.status));
+}
@@ -0,0 +1,77 @@
+mod common;
+use common::Sandbox;
+
+fn populate(sandbox: &Sandbox) {
+	for dir in ["a", "a/b", "a/b/c", "d", "d/e", "f/g/h/i"] {
+		for file in ["one.txt", "two.rs", "three.md"] {
+			sandbox.file(&format!("{dir}/{file}"), dir);
+		}
+	}
+	sandbox.dir("empty").dir("f/empty");
+}
+
+#[test]
+fn jobs_match_the_same_paths() {
+	let sandbox = Sandbox::new("walker-jobs");
+	populate(&sandbox);
+
+	for expr in [".", "file?", "dir?", "**/*.rs", r#""b""#, "a/**/*.txt || d/*"] {
+		let single = sandbox.run(&["--jobs=1", expr]);
+		assert_eq!(0, single.status, "{expr}: {single:?}");
+
+		for jobs in ["--jobs=2", "--jobs=8"] {
+			let multi = sandbox.run(&[jobs, expr]);
+			assert_eq!(single.lines(), multi.lines(), "{jobs} {expr}");
+			assert_eq!(0, multi.status, "{jobs} {expr}: {multi:?}");
+		}
+	}
+
+	assert_eq!(
+		sandbox.run(&["--jobs=1", "--count", "file?"]).stdout,
+		sandbox.run(&["--jobs=8", "--count", "file?"]).stdout
+	);
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_dirs_dont_stop_the_walk() {
+	use std::os::unix::fs::PermissionsExt;
+
+	let sandbox = Sandbox::new("walker-unreadable");
+	populate(&sandbox);
+	sandbox.file("locked/secret.txt", "");
+
+	let set_mode = |mode| {
+		std::fs::set_permissions(sandbox.path("locked"), std::fs::Permissions::from_mode(mode))
+			.unwrap()
+	};
+
+	set_mode(0o000);
+	if std::fs::read_dir(sandbox.path("locked")).is_ok() {
+		set_mode(0o755);
+		eprintln!("skipping, as permissions aren't enforced (eg running as root)");
+		return;
+	}
+
+	let expected = [
+		"./a/b/c/one.txt",
+		"./a/b/one.txt",
+		"./a/one.txt",
+		"./d/e/one.txt",
+		"./d/one.txt",
+		"./f/g/h/i/one.txt",
+	];
+
+	for jobs in ["--jobs=1", "--jobs=2", "--jobs=8", "--stable"] {
+		let output = sandbox.run(&[jobs, "--ignored-errors=perms", "**/*.txt"]);
+		assert_eq!(expected, *output.lines(), "{jobs}");
+		assert_eq!(0, output.status, "{jobs}: {output:?}");
+
+		// Without ignoring them, permission errors are reported.
+		let output = sandbox.run(&[jobs, "**/*.txt"]);
+		assert_ne!(0, output.status, "{jobs}: {output:?}");
+		assert!(!output.stderr.is_empty(), "{jobs}: {output:?}");
+	}
+
+	set_mode(0o755);
+}